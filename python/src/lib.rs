@@ -0,0 +1,149 @@
+//! Python bindings of faimm.
+//!
+//! The `faimm` Python module exposes an `IndexedFasta` class with the most used parts of the
+//! Rust API. Build it with [maturin](https://www.maturin.rs) from this directory, for instance
+//! `maturin develop`. Coordinates are zero-based and half-open like in Rust,
+//! and the work is done without holding the GIL.
+//!
+//! ```python
+//! import faimm
+//! fa = faimm.IndexedFasta("test/genome.fa")
+//! fa.names                              # ['A-10', 'A-100', 'ACGT-25']
+//! fa.length("ACGT-25")                  # 100
+//! fa.fetch("ACGT-25", 20, 30)           # 'AAAAACCCCC'
+//! fa.count_bases("ACGT-25", 0, 100)     # {'A': 25, 'C': 25, 'G': 25, 'T': 25, 'N': 0, 'other': 0}
+//! ```
+//!
+//! Errors are raised as `KeyError` for unknown names, `IndexError` for regions outside of a
+//! sequence, `OSError` for I/O errors and `ValueError` otherwise.
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyOSError, PyValueError};
+use pyo3::prelude::*;
+
+use faimm::{Error, IndexedFasta};
+
+/// Convert a faimm `Error` to the matching Python exception.
+fn py_err(e: Error) -> PyErr {
+    match e {
+        Error::UnknownName(name) => PyKeyError::new_err(name),
+        Error::UnknownTid(_) | Error::OutOfRange { .. } => PyIndexError::new_err(e.to_string()),
+        Error::Io(_) | Error::Mmap(_) | Error::MissingIndex(_) => PyOSError::new_err(e.to_string()),
+        _ => PyValueError::new_err(e.to_string()),
+    }
+}
+
+/// An indexed fasta file, opened with `IndexedFasta(path)`.
+#[pyclass(name = "IndexedFasta", module = "faimm", frozen)]
+struct PyIndexedFasta {
+    fasta: IndexedFasta,
+}
+
+impl PyIndexedFasta {
+    fn tid(&self, name: &str) -> PyResult<usize> {
+        self.fasta
+            .fai()
+            .tid(name)
+            .ok_or_else(|| PyKeyError::new_err(name.to_owned()))
+    }
+}
+
+#[pymethods]
+impl PyIndexedFasta {
+    /// Open a fasta file with a `.fai` index.
+    #[new]
+    fn new(py: Python<'_>, path: std::path::PathBuf) -> PyResult<Self> {
+        let fasta = py
+            .detach(|| IndexedFasta::from_file(path))
+            .map_err(py_err)?;
+        Ok(PyIndexedFasta { fasta })
+    }
+
+    /// The names of the sequences in index order.
+    #[getter]
+    fn names(&self) -> Vec<&str> {
+        self.fasta.fai().names()
+    }
+
+    /// The length of sequence `name`.
+    fn length(&self, name: &str) -> PyResult<usize> {
+        self.fasta.fai().size(self.tid(name)?).map_err(py_err)
+    }
+
+    /// The bases of `name` from `start` up to `end`.
+    fn fetch(&self, py: Python<'_>, name: &str, start: usize, end: usize) -> PyResult<String> {
+        let tid = self.tid(name)?;
+        py.detach(|| self.fasta.view(tid, start, end).map(|v| v.to_string()))
+            .map_err(py_err)
+    }
+
+    /// The counts of A, C, G, T, N and other bases of `name` from `start` up to `end`.
+    fn count_bases(
+        &self,
+        py: Python<'_>,
+        name: &str,
+        start: usize,
+        end: usize,
+    ) -> PyResult<HashMap<&'static str, usize>> {
+        let tid = self.tid(name)?;
+        let c = py
+            .detach(|| self.fasta.view(tid, start, end).map(|v| v.count_bases()))
+            .map_err(py_err)?;
+        Ok(HashMap::from([
+            ("A", c.a),
+            ("C", c.c),
+            ("G", c.g),
+            ("T", c.t),
+            ("N", c.n),
+            ("other", c.other),
+        ]))
+    }
+
+    fn __len__(&self) -> usize {
+        self.fasta.fai().names().len()
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.fasta.fai().tid(name).is_some()
+    }
+}
+
+/// The `faimm` Python module.
+#[pymodule(name = "faimm")]
+fn faimm_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyIndexedFasta>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    #[test]
+    fn module() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = pyo3::wrap_pymodule!(faimm_module)(py);
+            let globals = PyDict::new(py);
+            globals.set_item("faimm", module).unwrap();
+            let code = c"
+fa = faimm.IndexedFasta('../test/genome.fa')
+assert fa.names == ['A-10', 'A-100', 'ACGT-25']
+assert len(fa) == 3 and 'A-100' in fa and 'chr1' not in fa
+assert fa.length('ACGT-25') == 100
+assert fa.fetch('ACGT-25', 20, 30) == 'AAAAACCCCC'
+assert fa.count_bases('ACGT-25', 0, 100)['G'] == 25
+for exc, call in [(KeyError, lambda: fa.fetch('chr1', 0, 1)),
+                  (IndexError, lambda: fa.fetch('A-10', 0, 11)),
+                  (OSError, lambda: faimm.IndexedFasta('../test/missing.fa'))]:
+    try:
+        call()
+        raise AssertionError('expected ' + exc.__name__)
+    except exc:
+        pass
+";
+            py.run(code, Some(&globals), None).unwrap();
+        });
+    }
+}
@@ -0,0 +1,110 @@
+//! Access pattern hints for the kernel.
+//!
+//! The kernel reads a bit ahead when a page of a mapped file is read, but it doesn't know that a
+//! scan will read a complete chromosome. With `IndexedFasta::advise_sequential` it reads ahead
+//! aggressively, which makes a scan on a cold cache much faster. `advise_willneed` starts reading
+//! a region in the background, and `advise_dontneed` drops the pages of a region that is done,
+//! so a batch job doesn't push more useful pages out of the cache.
+//!
+//! The hints are `madvise` calls and are only given on Unix. On other platforms, and for
+//! sources that are not memory mapped, they do nothing.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! fa.advise_sequential(2).expect("Cannot advise");
+//! let counts = fa.view_tid(2).expect("Cannot get view").count_bases();
+//! fa.advise_dontneed(2, 0, 100).expect("Cannot advise");
+//! assert_eq!(counts.g, 25);
+//! ```
+
+use crate::{Error, IndexedFasta, Result};
+
+/// The expected use of a range of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    /// No special treatment, the default.
+    Normal,
+    /// Random access, read ahead is not useful.
+    Random,
+    /// Sequential access, read ahead aggressively.
+    Sequential,
+    /// The range will be accessed soon, start reading it.
+    WillNeed,
+    /// The range will not be accessed soon, its pages can be dropped from memory. A later view of
+    /// the range reads the pages from disk again.
+    DontNeed,
+}
+
+#[cfg(unix)]
+pub(crate) fn advise_mmap(
+    mmap: &memmap2::Mmap,
+    advice: Advice,
+    offset: usize,
+    len: usize,
+) -> std::io::Result<()> {
+    use memmap2::{Advice as A, UncheckedAdvice};
+    match advice {
+        Advice::Normal => mmap.advise_range(A::Normal, offset, len),
+        Advice::Random => mmap.advise_range(A::Random, offset, len),
+        Advice::Sequential => mmap.advise_range(A::Sequential, offset, len),
+        Advice::WillNeed => mmap.advise_range(A::WillNeed, offset, len),
+        // SAFETY: the file is mapped read-only and shared, so dropped pages are read from the
+        // file again and no data is lost.
+        Advice::DontNeed => unsafe {
+            mmap.unchecked_advise_range(UncheckedAdvice::DontNeed, offset, len)
+        },
+    }
+}
+
+impl IndexedFasta {
+    /// Give the kernel a hint about the use of the zero-based, half-open region tid:start-stop.
+    ///
+    /// Returns `Ok` if successful, Error if the region is invalid and `Error::Mmap` if the
+    /// kernel rejects the hint.
+    pub fn advise(&self, tid: usize, start: usize, stop: usize, advice: Advice) -> Result<()> {
+        let (start_byte, stop_byte) = self.checked_offset(tid, start, stop)?;
+        self.source
+            .advise(advice, start_byte, stop_byte - start_byte)
+            .map_err(Error::Mmap)
+    }
+
+    /// Hint that chromosome tid will be read sequentially, see `advise`.
+    pub fn advise_sequential(&self, tid: usize) -> Result<()> {
+        self.advise(tid, 0, self.fasta_index.size(tid)?, Advice::Sequential)
+    }
+
+    /// Hint that the region tid:start-stop will be read soon, see `advise`.
+    pub fn advise_willneed(&self, tid: usize, start: usize, stop: usize) -> Result<()> {
+        self.advise(tid, start, stop, Advice::WillNeed)
+    }
+
+    /// Hint that the region tid:start-stop will not be read soon, see `advise`.
+    pub fn advise_dontneed(&self, tid: usize, start: usize, stop: usize) -> Result<()> {
+        self.advise(tid, start, stop, Advice::DontNeed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advise() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        for advice in [
+            Advice::Sequential,
+            Advice::WillNeed,
+            Advice::DontNeed,
+            Advice::Random,
+            Advice::Normal,
+        ] {
+            fa.advise(1, 10, 90, advice).unwrap();
+        }
+        assert_eq!(fa.view(1, 10, 90).unwrap().count_bases().a, 80);
+        assert!(fa.advise_willneed(1, 10, 200).is_err());
+        let in_memory = IndexedFasta::index_source(b">a\nACGT\n".to_vec()).unwrap();
+        in_memory.advise_sequential(0).unwrap();
+    }
+}
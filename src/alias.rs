@@ -0,0 +1,203 @@
+//! Chromosome name aliases, to look up `chr1`, `1` and `NC_000001.11` as the same chromosome.
+//!
+//! `Aliases` stores groups of names that refer to the same sequence. When a name is not in the
+//! index, `Fai::tid` tries the other names of its group, so every lookup by name, like
+//! `IndexedFasta::view_by_name`, regions and BED files, accepts all names of a chromosome once
+//! the aliases are set with `Fai::with_aliases` or `IndexedFasta::with_aliases`.
+//! `Aliases::human` has the UCSC, Ensembl and RefSeq (GRCh37 and GRCh38) names of the human
+//! chromosomes, and `Aliases::from_file` reads a table with all names of a sequence on every
+//! line, like the UCSC `chromAlias.txt` files. With `Aliases::case_insensitive` names also match
+//! when they differ in case.
+//!
+//! # Example
+//! ```
+//! use faimm::alias::Aliases;
+//! use faimm::{Fai, IndexedFasta};
+//! let data = b">chr1\nACGT\n>chrM\nGATC\n".to_vec();
+//! let fai = Fai::create_from_bytes(&data).expect("Cannot index");
+//! let fa = IndexedFasta::from_source(data, fai).with_aliases(Aliases::human());
+//! for name in ["chr1", "1", "NC_000001.11", "NC_000001.10"] {
+//!     assert_eq!(fa.fai().tid(name), Some(0));
+//! }
+//! assert_eq!(fa.view_tid_by_name("MT").unwrap().to_string(), "GATC");
+//! assert_eq!(fa.fai().tid("CHR1"), None);
+//!
+//! let mut aliases = Aliases::new().case_insensitive(true);
+//! aliases.add(&["A-100", "hundred"]);
+//! let fa = IndexedFasta::from_file("test/genome.fa")
+//!     .expect("Error opening fa")
+//!     .with_aliases(aliases);
+//! assert_eq!(fa.fai().tid("Hundred"), Some(1));
+//! assert_eq!(fa.fai().tid("acgt-25"), Some(2));
+//! ```
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use indexmap::IndexSet;
+
+use crate::Result;
+
+/// The versions of the RefSeq accessions of the human chromosomes 1 to 22, X and Y in GRCh37.
+/// The versions in GRCh38 are one higher.
+const HUMAN_REFSEQ_GRCH37: [u8; 24] = [
+    10, 11, 11, 11, 9, 11, 13, 10, 11, 10, 9, 11, 10, 8, 9, 9, 10, 9, 9, 10, 8, 10, 10, 9,
+];
+
+/// Groups of chromosome names that refer to the same sequence, see the module documentation.
+#[derive(Debug, Clone, Default)]
+pub struct Aliases {
+    groups: Vec<Vec<String>>,
+    /// The group of every name.
+    lookup: HashMap<String, usize>,
+    case_insensitive: bool,
+}
+
+impl Aliases {
+    /// Create an empty set of aliases.
+    pub fn new() -> Self {
+        Aliases::default()
+    }
+
+    /// The UCSC (`chr1`), Ensembl (`1`) and RefSeq (`NC_000001.11`, `NC_000001.10`) names of the
+    /// human chromosomes 1 to 22, X, Y and the mitochondrial genome (`chrM`, `MT`,
+    /// `NC_012920.1`).
+    pub fn human() -> Self {
+        let mut aliases = Aliases::new();
+        for (i, version) in HUMAN_REFSEQ_GRCH37.iter().enumerate() {
+            let name = match i + 1 {
+                23 => "X".to_owned(),
+                24 => "Y".to_owned(),
+                n => n.to_string(),
+            };
+            aliases.add(&[
+                &format!("chr{}", name),
+                &name,
+                &format!("NC_{:06}.{}", i + 1, version + 1),
+                &format!("NC_{:06}.{}", i + 1, version),
+            ]);
+        }
+        aliases.add(&["chrM", "MT", "NC_012920.1"]);
+        aliases
+    }
+
+    /// Read aliases from the file at path `P`, see `Aliases::from_reader`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Aliases::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read aliases from `reader`. Every line contains the tab separated names of one sequence,
+    /// like the UCSC `chromAlias.txt` files. Empty fields and lines starting with `#` are
+    /// skipped.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        let mut aliases = Aliases::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.starts_with('#') {
+                continue;
+            }
+            let names: Vec<&str> = line
+                .trim_end_matches('\r')
+                .split('\t')
+                .filter(|n| !n.is_empty())
+                .collect();
+            aliases.add(&names);
+        }
+        Ok(aliases)
+    }
+
+    /// Add `names` as aliases of each other. Names that are already known merge the new names
+    /// into their group.
+    pub fn add(&mut self, names: &[&str]) {
+        let group = names.iter().find_map(|n| self.group(n)).unwrap_or_else(|| {
+            self.groups.push(Vec::new());
+            self.groups.len() - 1
+        });
+        for &name in names {
+            if self.group(name).is_none() {
+                self.lookup.insert(name.to_owned(), group);
+                self.groups[group].push(name.to_owned());
+            }
+        }
+    }
+
+    /// Also match names that differ in case (default false).
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Return all names of the group of `name`, including `name`, or an empty slice if the name
+    /// is unknown.
+    pub fn aliases(&self, name: &str) -> &[String] {
+        self.group(name).map_or(&[], |g| &self.groups[g])
+    }
+
+    /// The group of `name`, ignoring case when the aliases are case insensitive.
+    fn group(&self, name: &str) -> Option<usize> {
+        match self.lookup.get(name) {
+            Some(&group) => Some(group),
+            None if self.case_insensitive => self
+                .lookup
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, &group)| group),
+            None => None,
+        }
+    }
+
+    /// Find the index of `name` or one of its aliases in `names`.
+    pub(crate) fn resolve(&self, names: &IndexSet<String>, name: &str) -> Option<usize> {
+        let group = self.aliases(name);
+        group
+            .iter()
+            .find_map(|n| names.get_index_of(n))
+            .or_else(|| {
+                if !self.case_insensitive {
+                    return None;
+                }
+                names.iter().position(|n| {
+                    n.eq_ignore_ascii_case(name) || group.iter().any(|a| n.eq_ignore_ascii_case(a))
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fai;
+
+    #[test]
+    fn aliases() {
+        let table = "# ucsc\tassembly\trefseq\nchr1\t1\tNC_000001.11\nchrUn_x\t\tNW_1\n1\tone\n";
+        let aliases = Aliases::from_reader(table.as_bytes()).unwrap();
+        assert_eq!(aliases.aliases("one"), ["chr1", "1", "NC_000001.11", "one"]);
+        assert_eq!(aliases.aliases("NW_1"), ["chrUn_x", "NW_1"]);
+        assert!(aliases.aliases("CHR1").is_empty());
+        assert!(aliases.clone().case_insensitive(true).aliases("CHR1").len() == 4);
+
+        let data = b">1\nAC\n>NW_1\nGT\n".to_vec();
+        let fai = Fai::create_from_bytes(&data).unwrap();
+        assert_eq!(fai.tid("chr1"), None);
+        let fai = fai.with_aliases(aliases.case_insensitive(true));
+        assert_eq!(fai.tid("one"), Some(0));
+        assert_eq!(fai.tid("ChrUn_X"), Some(1));
+        assert_eq!(fai.tid("nw_1"), Some(1));
+        assert_eq!(fai.tid("chr2"), None);
+    }
+
+    #[test]
+    fn human() {
+        let aliases = Aliases::human();
+        assert_eq!(
+            aliases.aliases("chrX"),
+            ["chrX", "X", "NC_000023.11", "NC_000023.10"]
+        );
+        assert_eq!(aliases.aliases("NC_000007.14")[0], "chr7");
+        assert_eq!(aliases.aliases("MT")[0], "chrM");
+        assert!(aliases.aliases("chr23").is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+//! Sequence alphabets, to count and validate protein as well as nucleotide sequences.
+//!
+//! An `Alphabet` is a set of symbols, matched without regard to case. `Dna` contains the IUPAC
+//! nucleotide codes and `Protein` the IUPAC amino acid codes with `*` for a stop. Views of an
+//! indexed protein fasta, like a proteome reference, are created the same way as views of a
+//! genome. `FastaView::count_symbols` counts the symbols of any alphabet and
+//! `FastaView::count_amino_acids` is a shorthand for proteins, and `FastaView::strict_symbols`
+//! validates a view against an alphabet.
+//!
+//! Unlike `FastaView::bases`, which only returns bytes in the ASCII range 64..128, these
+//! functions only skip line breaks, so the `*` of a stop codon is counted.
+//!
+//! # Example
+//! ```
+//! use faimm::alphabet::Protein;
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::index_source(b">p\nMKTAYIAKQR\nQISFVKSHF*\n".to_vec()).unwrap();
+//! let v = fa.view_tid(0).unwrap();
+//! let counts = v.count_amino_acids();
+//! assert_eq!((counts.get(b'K'), counts.get(b'*'), counts.total()), (3, 1, 20));
+//! assert!(v.strict_symbols::<Protein>().all(|aa| aa.is_ok()));
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::FastaView;
+
+/// The code in `Alphabet::CODES` of a byte that is not in the alphabet.
+pub const INVALID: u8 = u8::MAX;
+
+/// A set of sequence symbols.
+pub trait Alphabet {
+    /// The symbols in uppercase, their lowercase versions belong to the alphabet as well.
+    const SYMBOLS: &'static [u8];
+    /// The index in `SYMBOLS` of every byte, `INVALID` when it is not in the alphabet.
+    const CODES: [u8; 256] = codes(Self::SYMBOLS);
+}
+
+const fn codes(symbols: &[u8]) -> [u8; 256] {
+    let mut codes = [INVALID; 256];
+    let mut i = 0;
+    while i < symbols.len() {
+        codes[symbols[i] as usize] = i as u8;
+        codes[symbols[i].to_ascii_lowercase() as usize] = i as u8;
+        i += 1;
+    }
+    codes
+}
+
+/// The IUPAC nucleotide codes, including U and N.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dna;
+
+impl Alphabet for Dna {
+    const SYMBOLS: &'static [u8] = b"ACGTUNRYSWKMBDHV";
+}
+
+/// The 20 standard amino acids, the ambiguity codes B, Z, J and X, selenocysteine (U),
+/// pyrrolysine (O) and `*` for a stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Protein;
+
+impl Alphabet for Protein {
+    const SYMBOLS: &'static [u8] = b"ACDEFGHIKLMNPQRSTVWYBZJXUO*";
+}
+
+/// The number of occurrences of every symbol of alphabet `A`, and of bytes that are not in it.
+pub struct SymbolCounts<A> {
+    counts: Vec<usize>,
+    /// Bytes that are not in the alphabet, except line breaks.
+    pub other: usize,
+    alphabet: PhantomData<A>,
+}
+
+/// The counts of the amino acids in a protein sequence.
+pub type AminoAcidCounts = SymbolCounts<Protein>;
+
+impl<A: Alphabet> SymbolCounts<A> {
+    /// The number of occurrences of `symbol`, ignoring case. Returns 0 for a symbol that is not
+    /// in the alphabet.
+    pub fn get(&self, symbol: u8) -> usize {
+        match A::CODES[symbol as usize] {
+            INVALID => 0,
+            code => self.counts[code as usize],
+        }
+    }
+
+    /// The number of symbols that are in the alphabet.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// The fraction of `symbol` among the symbols in the alphabet, NaN when there are none.
+    pub fn fraction(&self, symbol: u8) -> f64 {
+        self.get(symbol) as f64 / self.total() as f64
+    }
+
+    /// Iterator over the symbols of the alphabet in uppercase and their counts.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, usize)> + '_ {
+        A::SYMBOLS.iter().copied().zip(self.counts.iter().copied())
+    }
+}
+
+impl<A: Alphabet> Default for SymbolCounts<A> {
+    fn default() -> Self {
+        SymbolCounts {
+            counts: vec![0; A::SYMBOLS.len()],
+            other: 0,
+            alphabet: PhantomData,
+        }
+    }
+}
+
+impl<A> Clone for SymbolCounts<A> {
+    fn clone(&self) -> Self {
+        SymbolCounts {
+            counts: self.counts.clone(),
+            other: self.other,
+            alphabet: PhantomData,
+        }
+    }
+}
+
+impl<A> PartialEq for SymbolCounts<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.counts == other.counts && self.other == other.other
+    }
+}
+
+impl<A: Alphabet> fmt::Debug for SymbolCounts<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (symbol, count) in self.iter() {
+            map.entry(&(symbol as char), &count);
+        }
+        map.entry(&"other", &self.other).finish()
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Count the symbols of alphabet `A` in the current view, ignoring case. Bytes that are not
+    /// in the alphabet are counted as other, line breaks are skipped.
+    pub fn count_symbols<A: Alphabet>(&self) -> SymbolCounts<A> {
+        let mut counts = SymbolCounts::<A>::default();
+        for &b in self.0 {
+            match A::CODES[b as usize] {
+                INVALID if b == b'\n' || b == b'\r' => {}
+                INVALID => counts.other += 1,
+                code => counts.counts[code as usize] += 1,
+            }
+        }
+        counts
+    }
+
+    /// Count the amino acids in the current view of a protein sequence.
+    pub fn count_amino_acids(&self) -> AminoAcidCounts {
+        self.count_symbols()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_symbols() {
+        let v = FastaView::new(b"MkT*\r\nX-acgt\n");
+        let aa = v.count_amino_acids();
+        assert_eq!(
+            (aa.get(b'M'), aa.get(b'k'), aa.get(b'*'), aa.get(b'-')),
+            (1, 1, 1, 0)
+        );
+        assert_eq!((aa.total(), aa.other), (9, 1));
+        assert_eq!(aa.fraction(b'T'), 2.0 / 9.0);
+
+        let dna = v.count_symbols::<Dna>();
+        assert_eq!(
+            (dna.get(b'A'), dna.get(b'T'), dna.total(), dna.other),
+            (1, 2, 7, 3)
+        );
+        assert_eq!(dna.iter().count(), Dna::SYMBOLS.len());
+        assert!(SymbolCounts::<Protein>::default().fraction(b'A').is_nan());
+    }
+}
@@ -0,0 +1,136 @@
+//! An async layer for use in tokio based services (feature `tokio`).
+//!
+//! Reading a view of a mapped file can block on page faults, and copying a large region takes a
+//! while, so it shouldn't happen on the threads of an async runtime. `AsyncIndexedFasta` runs
+//! that work on the blocking thread pool of tokio and returns owned sequences. It is cheap to
+//! clone, so it can be shared as the state of a web service.
+//!
+//! # Example
+//! ```
+//! use faimm::async_fasta::AsyncIndexedFasta;
+//! let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+//! rt.block_on(async {
+//!     let fa = AsyncIndexedFasta::open("test/genome.fa").await.expect("Error opening fa");
+//!     let seq = fa.fetch_region("ACGT-25:21-30").await.expect("Cannot fetch region");
+//!     assert_eq!(seq, b"AAAAACCCCC");
+//!     let gc = fa.with(|fa| fa.view_tid(2).map(|v| v.count_bases().g)).await;
+//!     assert_eq!(gc.unwrap(), 25);
+//! });
+//! ```
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{Error, IndexedFasta, Result};
+
+/// An `IndexedFasta` with async methods that run on the blocking thread pool.
+#[derive(Clone)]
+pub struct AsyncIndexedFasta {
+    fasta: Arc<IndexedFasta>,
+}
+
+impl From<IndexedFasta> for AsyncIndexedFasta {
+    fn from(fasta: IndexedFasta) -> Self {
+        AsyncIndexedFasta {
+            fasta: Arc::new(fasta),
+        }
+    }
+}
+
+impl From<Arc<IndexedFasta>> for AsyncIndexedFasta {
+    fn from(fasta: Arc<IndexedFasta>) -> Self {
+        AsyncIndexedFasta { fasta }
+    }
+}
+
+/// Run `f` on the blocking thread pool. A panic in `f` is resumed in the caller.
+async fn blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(Error::Io(io::Error::other(e))),
+    }
+}
+
+impl AsyncIndexedFasta {
+    /// Open a fasta file from path `P` like `IndexedFasta::from_file`, on the blocking thread
+    /// pool.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        blocking(move || IndexedFasta::from_file(path))
+            .await
+            .map(AsyncIndexedFasta::from)
+    }
+
+    /// The wrapped `IndexedFasta`, for the methods that don't read sequence, like `fai`.
+    pub fn fasta(&self) -> &Arc<IndexedFasta> {
+        &self.fasta
+    }
+
+    /// Run `f` with the `IndexedFasta` on the blocking thread pool and return its result.
+    pub async fn with<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&IndexedFasta) -> Result<T> + Send + 'static,
+    {
+        let fasta = Arc::clone(&self.fasta);
+        blocking(move || f(&fasta)).await
+    }
+
+    /// The bases of tid:start-stop, see `IndexedFasta::view`.
+    ///
+    /// Returns the bases if successful, Error otherwise.
+    pub async fn fetch(&self, tid: usize, start: usize, stop: usize) -> Result<Vec<u8>> {
+        self.with(move |fa| Ok(fa.view(tid, start, stop)?.bases().copied().collect()))
+            .await
+    }
+
+    /// The bases of chromosome tid, see `IndexedFasta::view_tid`.
+    ///
+    /// Returns the bases if successful, Error otherwise.
+    pub async fn fetch_tid(&self, tid: usize) -> Result<Vec<u8>> {
+        self.with(move |fa| Ok(fa.view_tid(tid)?.bases().copied().collect()))
+            .await
+    }
+
+    /// The bases of a samtools style region string, see `IndexedFasta::view_region`.
+    ///
+    /// Returns the bases if successful, Error otherwise.
+    pub async fn fetch_region(&self, region: &str) -> Result<Vec<u8>> {
+        let region = region.to_owned();
+        self.with(move |fa| Ok(fa.view_region(&region)?.bases().copied().collect()))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetch() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let fa = AsyncIndexedFasta::open("test/genome.fa").await.unwrap();
+            let shared = fa.clone();
+            assert_eq!(shared.fetch(2, 48, 52).await.unwrap(), b"CCGG");
+            assert_eq!(fa.fetch_tid(0).await.unwrap(), b"AAAAAAAAAA");
+            assert!(matches!(
+                fa.fetch(0, 5, 11).await,
+                Err(Error::OutOfRange { .. })
+            ));
+            assert!(matches!(
+                fa.fetch_region("chr1").await,
+                Err(Error::UnknownName(_))
+            ));
+            assert!(AsyncIndexedFasta::open("test/missing.fa").await.is_err());
+        });
+    }
+}
@@ -0,0 +1,355 @@
+//! Read regions from BED files.
+//!
+//! `BedReader` parses BED3 and BED6 lines into `BedRecord`s that are validated against a `Fai`:
+//! the chromosome has to exist and the interval has to fit on the chromosome. BED coordinates
+//! are zero-based and half-open, like everywhere else in this crate. Empty lines, comments
+//! (`#`) and `track` or `browser` lines are skipped. Columns beyond the sixth are ignored.
+//!
+//! An unknown chromosome gives `Error::UnknownName`, an interval that doesn't fit on its
+//! chromosome `Error::InvalidInterval` or `Error::OutOfRange`, and other problems
+//! `Error::Malformed` with the line number.
+//!
+//! `Hit` is a named, scored and stranded interval, like the motif matches of
+//! `IndexedFasta::scan_pwm`, that can be written as BED6 lines with `write_bed6`.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let bed = "ACGT-25\t0\t25\tfirst\t0\t+\nACGT-25\t25\t50\n";
+//! for r in fa.bed_views(bed.as_bytes()) {
+//!     let (record, view) = r.expect("Invalid BED line");
+//!     println!("{}\t{}", record.name.unwrap_or_default(), view.count_bases().a);
+//! }
+//! ```
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::strand::Strand;
+use crate::{Error, Fai, FastaView, IndexedFasta, Result};
+
+/// A validated BED3 or BED6 record. The optional fields are `None` for BED3 lines or when the
+/// column contains `.`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BedRecord {
+    pub tid: usize,
+    pub start: usize,
+    pub end: usize,
+    pub name: Option<String>,
+    pub score: Option<f64>,
+    pub strand: Option<Strand>,
+}
+
+/// An interval found by a scan such as `IndexedFasta::scan_pwm`, with the name of what was
+/// found, a score and the strand it was found on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub tid: usize,
+    pub start: usize,
+    pub end: usize,
+    pub name: String,
+    pub score: f64,
+    pub strand: Strand,
+}
+
+impl Hit {
+    /// Write the hit to `w` as a single BED6 line. The chromosome name is looked up in `fai`.
+    pub fn write_bed6<W: Write>(&self, fai: &Fai, mut w: W) -> Result<()> {
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            fai.name(self.tid)?,
+            self.start,
+            self.end,
+            self.name,
+            self.score,
+            self.strand
+        )?;
+        Ok(())
+    }
+}
+
+impl From<Hit> for BedRecord {
+    fn from(hit: Hit) -> BedRecord {
+        BedRecord {
+            tid: hit.tid,
+            start: hit.start,
+            end: hit.end,
+            name: Some(hit.name),
+            score: Some(hit.score),
+            strand: Some(hit.strand),
+        }
+    }
+}
+
+/// Write all `hits` to `w` as BED6 lines. The chromosome names are looked up in `fai`.
+pub fn write_bed6<'h, W, I>(fai: &Fai, hits: I, mut w: W) -> Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'h Hit>,
+{
+    for hit in hits {
+        hit.write_bed6(fai, &mut w)?;
+    }
+    Ok(())
+}
+
+/// Iterator over the records of a BED file.
+pub struct BedReader<'a, R> {
+    reader: R,
+    fai: &'a Fai,
+    line: String,
+    line_number: usize,
+}
+
+impl<'a> BedReader<'a, BufReader<File>> {
+    /// Open the BED file at path `P` and validate its records against `fai`.
+    pub fn from_file<P: AsRef<Path>>(path: P, fai: &'a Fai) -> Result<Self> {
+        Ok(BedReader::new(BufReader::new(File::open(path)?), fai))
+    }
+}
+
+impl<'a, R: BufRead> BedReader<'a, R> {
+    /// Read BED records from `reader` and validate them against `fai`.
+    pub fn new(reader: R, fai: &'a Fai) -> Self {
+        BedReader {
+            reader,
+            fai,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+
+    fn parse_line(&self) -> Result<BedRecord> {
+        let invalid = |message: String| Error::Malformed {
+            format: "BED",
+            line: Some(self.line_number),
+            message,
+        };
+
+        let fields: Vec<&str> = self
+            .line
+            .trim_end_matches(['\n', '\r'])
+            .split('\t')
+            .collect();
+        if fields.len() < 3 {
+            return Err(invalid(format!(
+                "Expected at least 3 columns, found {}",
+                fields.len()
+            )));
+        }
+
+        let tid = self
+            .fai
+            .tid(fields[0])
+            .ok_or_else(|| Error::UnknownName(fields[0].to_owned()))?;
+        let start: usize = fields[1]
+            .parse()
+            .map_err(|_| invalid(format!("Invalid start {:?}", fields[1])))?;
+        let end: usize = fields[2]
+            .parse()
+            .map_err(|_| invalid(format!("Invalid end {:?}", fields[2])))?;
+        if end < start {
+            return Err(Error::InvalidInterval { start, stop: end });
+        }
+        let len = self.fai.size(tid)?;
+        if end > len {
+            return Err(Error::OutOfRange {
+                tid,
+                start,
+                stop: end,
+                len,
+            });
+        }
+
+        let column = |i: usize| {
+            fields
+                .get(i)
+                .copied()
+                .filter(|f| *f != "." && !f.is_empty())
+        };
+        let score = match column(4) {
+            Some(s) => Some(
+                s.parse()
+                    .map_err(|_| invalid(format!("Invalid score {:?}", s)))?,
+            ),
+            None => None,
+        };
+        let strand = match column(5) {
+            Some("+") => Some(Strand::Forward),
+            Some("-") => Some(Strand::Reverse),
+            Some(s) => return Err(invalid(format!("Invalid strand {:?}", s))),
+            None => None,
+        };
+
+        Ok(BedRecord {
+            tid,
+            start,
+            end,
+            name: column(3).map(str::to_owned),
+            score,
+            strand,
+        })
+    }
+}
+
+impl<'a, R: BufRead> Iterator for BedReader<'a, R> {
+    type Item = Result<BedRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.line_number += 1,
+                Err(e) => return Some(Err(e.into())),
+            }
+            let line = self.line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+            return Some(self.parse_line());
+        }
+    }
+}
+
+impl IndexedFasta {
+    /// Read BED records from `reader` and return every record with a view of its region.
+    ///
+    /// Returns an iterator of `(BedRecord, FastaView)` tuples. Invalid lines are returned as an
+    /// `Error` and iteration can continue after an error.
+    pub fn bed_views<R: BufRead>(
+        &self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<(BedRecord, FastaView<'_>)>> {
+        BedReader::new(reader, self.fai()).map(move |r| {
+            let r = r?;
+            let view = self.view(r.tid, r.start, r.end)?;
+            Ok((r, view))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let bed = [
+            "track name=test",
+            "# comment",
+            "",
+            "ACGT-25\t0\t25\tfirst\t10\t-\textra\r",
+            "A-10\t2\t4",
+            "A-100\t0\t1\t.\t.\t.",
+        ]
+        .join("\n");
+        let records: Vec<_> = BedReader::new(bed.as_bytes(), fa.fai())
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0],
+            BedRecord {
+                tid: 2,
+                start: 0,
+                end: 25,
+                name: Some("first".to_owned()),
+                score: Some(10.0),
+                strand: Some(Strand::Reverse),
+            }
+        );
+        assert_eq!((records[1].tid, records[1].name.as_ref()), (0, None));
+        assert_eq!((records[2].score, records[2].strand), (None, None));
+    }
+
+    #[test]
+    fn hits() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let hits = vec![
+            Hit {
+                tid: 2,
+                start: 20,
+                end: 30,
+                name: "motif".to_owned(),
+                score: 10.0,
+                strand: Strand::Forward,
+            },
+            Hit {
+                tid: 0,
+                start: 0,
+                end: 4,
+                name: "orf".to_owned(),
+                score: 0.5,
+                strand: Strand::Reverse,
+            },
+        ];
+        let mut out = Vec::new();
+        write_bed6(fa.fai(), &hits, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text,
+            "ACGT-25\t20\t30\tmotif\t10\t+\nA-10\t0\t4\torf\t0.5\t-\n"
+        );
+
+        let parsed: Vec<_> = BedReader::new(text.as_bytes(), fa.fai())
+            .map(|r| r.unwrap())
+            .collect();
+        let expected: Vec<BedRecord> = hits.into_iter().map(BedRecord::from).collect();
+        assert_eq!(parsed, expected);
+
+        let unknown = Hit {
+            tid: 3,
+            start: 0,
+            end: 1,
+            name: String::new(),
+            score: 0.0,
+            strand: Strand::Forward,
+        };
+        assert!(unknown.write_bed6(fa.fai(), std::io::sink()).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let bed = [
+            "chr1\t0\t10",
+            "A-10\t5\t2",
+            "A-10\t0\t11",
+            "A-10\tx\t1",
+            "A-10\t0",
+            "A-10\t0\t1\t.\t.\t*",
+            "A-10\t0\t10",
+        ]
+        .join("\n");
+        let results: Vec<_> = fa.bed_views(bed.as_bytes()).collect();
+        assert_eq!(results.len(), 7);
+        assert!(matches!(&results[0], Err(Error::UnknownName(n)) if n == "chr1"));
+        assert!(matches!(
+            results[1],
+            Err(Error::InvalidInterval { start: 5, stop: 2 })
+        ));
+        assert!(matches!(
+            results[2],
+            Err(Error::OutOfRange {
+                tid: 0,
+                len: 10,
+                ..
+            })
+        ));
+        for (i, r) in results.iter().enumerate().take(6).skip(3) {
+            assert!(
+                matches!(r, Err(Error::Malformed { format: "BED", line: Some(l), .. }) if *l == i + 1)
+            );
+        }
+        assert_eq!(results[6].as_ref().unwrap().1.to_string(), "AAAAAAAAAA");
+    }
+}
@@ -0,0 +1,501 @@
+//! Random access to bgzip compressed fasta files.
+//!
+//! A bgzip compressed fasta file (`.fa.gz`) is a series of independently compressed BGZF blocks.
+//! `samtools faidx` stores the block positions in a `.gzi` file next to the regular `.fai`, which
+//! uses offsets in the uncompressed data. A `BgzfIndexedFasta` uses both to decompress only the
+//! blocks that cover a requested region.
+//!
+//! Because the sequence data has to be decompressed, views are created in a caller supplied
+//! buffer that can be reused between queries. Decompressed blocks are kept in a least recently
+//! used cache that is shared by all queries on the same `BgzfIndexedFasta`, so repeated queries
+//! to nearby regions don't decompress the same blocks over and over.
+//!
+//! A region can also be addressed in the compressed file itself, either as a pair of BGZF
+//! virtual offsets (the compressed offset of a block shifted left by 16 bits, combined with the
+//! offset in the decompressed block) for htslib compatible readers, or as the range of complete
+//! compressed blocks for HTTP range requests and caches.
+//!
+//! # Example
+//! ```
+//! use faimm::bgzf::BgzfIndexedFasta;
+//! let fa = BgzfIndexedFasta::from_file("test/genome.fa.gz").expect("Error opening fa.gz");
+//! let chr_index = fa.fai().tid("ACGT-25").expect("Cannot find chr in index");
+//! let mut buf = Vec::new();
+//! let v = fa.view(chr_index, 20, 30, &mut buf).expect("Cannot get .fa.gz view");
+//! assert_eq!(v.to_string(), "AAAAACCCCC");
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use flate2::bufread::DeflateDecoder;
+use memmap2::{Mmap, MmapOptions};
+
+use crate::limits::Limits;
+use crate::metrics::Metrics;
+use crate::{fai_path, Error, Fai, FastaView, Layout, Result};
+
+/// Size of the fixed part of a BGZF block header.
+const HEADER_LEN: usize = 12;
+/// Size of the CRC32 and ISIZE fields that end every BGZF block.
+const FOOTER_LEN: usize = 8;
+/// Default number of decompressed blocks in the cache (up to 64kb each).
+const DEFAULT_CACHE_BLOCKS: usize = 16;
+
+/// An indexed fasta file that is compressed with bgzip. It needs both the `.fai` and the `.gzi`
+/// index files.
+pub struct BgzfIndexedFasta {
+    mmap: Mmap,
+    blocks: Vec<(usize, usize)>,
+    fasta_index: Fai,
+    cache: Mutex<BlockCache>,
+    limits: Limits,
+    metrics: Option<Metrics>,
+}
+
+impl BgzfIndexedFasta {
+    /// Open a bgzip compressed fasta file from path `P`. The index files are found by appending
+    /// .fai and .gzi to the path.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let fasta_index = Fai::from_file(fai_path(&path))?;
+
+        let mut gzi_path = path.as_ref().as_os_str().to_owned();
+        gzi_path.push(".gzi");
+        let blocks = read_gzi(&gzi_path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => Error::MissingIndex(gzi_path.into()),
+            _ => Error::Io(e),
+        })?;
+
+        let file = File::open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file) }.map_err(Error::Mmap)?;
+        Ok(BgzfIndexedFasta {
+            mmap,
+            blocks,
+            fasta_index,
+            cache: Mutex::new(BlockCache::new(DEFAULT_CACHE_BLOCKS)),
+            limits: Limits::new(),
+            metrics: None,
+        })
+    }
+
+    /// Set the number of decompressed blocks that are kept in the cache. A capacity of zero
+    /// disables caching.
+    pub fn with_cache_capacity(self, blocks: usize) -> Self {
+        self.cache.lock().unwrap().set_capacity(blocks);
+        self
+    }
+
+    /// Limit the size of queries on this fasta file (see `limits::Limits`). Views are checked
+    /// before anything is decompressed.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Enable counting of queries, block cache hits and decompression time (see
+    /// `metrics::Metrics`).
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = Some(Metrics::default());
+        self
+    }
+
+    /// Return the metrics of this fasta file, `None` if they are not enabled.
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Use tid, start and end to decompress a region of the fasta file into `buf`. The previous
+    /// contents of `buf` are discarded.
+    ///
+    /// Returns a FastaView on `buf` for the provided chromsome, start, end if successful,
+    /// Error otherwise.
+    pub fn view<'b>(
+        &self,
+        tid: usize,
+        start: usize,
+        stop: usize,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<FastaView<'b>> {
+        let read =
+            self.fasta_index
+                .base_range(tid, start, stop)
+                .and_then(|(start_byte, stop_byte)| {
+                    self.limits.check_view(stop - start)?;
+                    Ok(self.read_range(start_byte, stop_byte, buf)?)
+                });
+        self.record(read, stop.saturating_sub(start))?;
+        Ok(FastaView::with_layout(
+            &buf[..],
+            stop - start,
+            Layout::new(self.fasta_index.record(tid)?, start),
+        ))
+    }
+
+    /// Use tid to decompress an entire chromosome into `buf`. The previous contents of `buf` are
+    /// discarded.
+    ///
+    /// Returns a FastaView on `buf` for the provided chromsome indicated by tid if successful,
+    /// Error otherwise.
+    pub fn view_tid<'b>(&self, tid: usize, buf: &'b mut Vec<u8>) -> Result<FastaView<'b>> {
+        let read = self
+            .fasta_index
+            .size(tid)
+            .and_then(|len| self.fasta_index.base_range(tid, 0, len))
+            .and_then(|(start_byte, stop_byte)| {
+                self.limits.check_view(self.fasta_index.size(tid)?)?;
+                Ok(self.read_range(start_byte, stop_byte, buf)?)
+            });
+        self.record(read, self.fasta_index.size(tid).unwrap_or(0))?;
+        Ok(FastaView::with_layout(
+            &buf[..],
+            self.fasta_index.size(tid)?,
+            Layout::new(self.fasta_index.record(tid)?, 0),
+        ))
+    }
+
+    /// Count a view request of `bases` bases in the metrics, if they are enabled.
+    fn record(&self, result: Result<()>, bases: usize) -> Result<()> {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_query(&result, bases);
+        }
+        result
+    }
+
+    /// Return a reference to the `Fai` that contains information from the fasta index.
+    ///
+    /// Returns a reference to `Fai`.
+    pub fn fai(&self) -> &Fai {
+        &self.fasta_index
+    }
+
+    /// Calculate the BGZF virtual offsets of the start and end of the region `start..stop` on
+    /// chromosome tid.
+    ///
+    /// Returns the virtual offsets `(start, end)` if successful, Error otherwise.
+    pub fn virtual_offsets(&self, tid: usize, start: usize, stop: usize) -> Result<(u64, u64)> {
+        let (start_byte, stop_byte) = self.fasta_index.offset(tid, start, stop)?;
+        Ok((
+            self.virtual_offset(start_byte),
+            self.virtual_offset(stop_byte),
+        ))
+    }
+
+    /// Calculate the range of compressed bytes with the complete BGZF blocks that contain the
+    /// region `start..stop` on chromosome tid.
+    ///
+    /// Returns the half-open compressed byte range `(start, end)` if successful, Error otherwise.
+    pub fn compressed_range(
+        &self,
+        tid: usize,
+        start: usize,
+        stop: usize,
+    ) -> Result<(usize, usize)> {
+        let (start_byte, stop_byte) = self.fasta_index.offset(tid, start, stop)?;
+        let first = self.block_index(start_byte);
+        let last = self.block_index(stop_byte.saturating_sub(1).max(start_byte));
+        let end = self
+            .blocks
+            .get(last + 1)
+            .map_or(self.mmap.len(), |&(coffset, _)| coffset);
+        Ok((self.blocks[first].0, end))
+    }
+
+    /// The index in `blocks` of the last block that starts at or before uncompressed `offset`.
+    fn block_index(&self, offset: usize) -> usize {
+        self.blocks.partition_point(|&(_, u)| u <= offset) - 1
+    }
+
+    /// The virtual offset of uncompressed `offset`.
+    fn virtual_offset(&self, offset: usize) -> u64 {
+        let (coffset, uoffset) = self.blocks[self.block_index(offset)];
+        ((coffset as u64) << 16) | (offset - uoffset) as u64
+    }
+
+    /// Decompress the uncompressed byte range `start..stop` into `buf`.
+    fn read_range(&self, start: usize, stop: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.clear();
+        if start == stop {
+            return Ok(());
+        }
+
+        let (mut coffset, uoffset) = self.blocks[self.block_index(start)];
+        let mut skip = start - uoffset;
+        let mut remaining = stop - start;
+
+        while remaining > 0 {
+            let block;
+            (block, coffset) = self.block(coffset)?;
+            if block.len() <= skip {
+                if coffset >= self.mmap.len() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "FASTA read interval beyond end of bgzf file",
+                    ));
+                }
+                skip -= block.len();
+                continue;
+            }
+            let take = remaining.min(block.len() - skip);
+            buf.extend_from_slice(&block[skip..skip + take]);
+            remaining -= take;
+            skip = 0;
+        }
+        Ok(())
+    }
+
+    /// Return the decompressed block at compressed offset `coffset` from the cache, or
+    /// decompress and cache it.
+    ///
+    /// Returns the block and the compressed offset of the next block.
+    fn block(&self, coffset: usize) -> io::Result<(Arc<Vec<u8>>, usize)> {
+        let hit = self.cache.lock().unwrap().get(coffset);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_cache(hit.is_some());
+        }
+        if let Some(hit) = hit {
+            return Ok(hit);
+        }
+        let started = Instant::now();
+        let mut block = Vec::new();
+        let next = self.read_block(coffset, &mut block)?;
+        if let Some(metrics) = &self.metrics {
+            metrics.record_decompress(started.elapsed());
+        }
+        let block = Arc::new(block);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(coffset, Arc::clone(&block), next);
+        Ok((block, next))
+    }
+
+    /// Decompress the block starting at compressed offset `coffset` into `out`.
+    ///
+    /// Returns the compressed offset of the next block.
+    fn read_block(&self, coffset: usize, out: &mut Vec<u8>) -> io::Result<usize> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let data = self
+            .mmap
+            .get(coffset..)
+            .filter(|d| d.len() >= HEADER_LEN)
+            .ok_or_else(|| invalid("Truncated bgzf block"))?;
+        if data[..4] != [0x1f, 0x8b, 8, 4] {
+            return Err(invalid("Invalid bgzf block header"));
+        }
+
+        let xlen = u16::from_le_bytes([data[10], data[11]]) as usize;
+        let extra = data
+            .get(HEADER_LEN..HEADER_LEN + xlen)
+            .ok_or_else(|| invalid("Truncated bgzf block"))?;
+        let block_size = bsize(extra).ok_or_else(|| invalid("Missing BSIZE in bgzf block"))? + 1;
+        let block = data
+            .get(..block_size)
+            .filter(|b| b.len() >= HEADER_LEN + xlen + FOOTER_LEN)
+            .ok_or_else(|| invalid("Truncated bgzf block"))?;
+
+        let footer = &block[block_size - 4..];
+        let isize = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]) as usize;
+        out.clear();
+        out.reserve(isize);
+        DeflateDecoder::new(&block[HEADER_LEN + xlen..block_size - FOOTER_LEN]).read_to_end(out)?;
+        if out.len() != isize {
+            return Err(invalid("Unexpected size of decompressed bgzf block"));
+        }
+
+        Ok(coffset + block_size)
+    }
+}
+
+/// A least recently used cache of decompressed blocks keyed by compressed offset. The blocks
+/// are ordered by the tick of their last use, so looking up and evicting a block costs
+/// O(log n).
+#[derive(Debug)]
+struct BlockCache {
+    capacity: usize,
+    tick: u64,
+    blocks: HashMap<usize, (Arc<Vec<u8>>, usize, u64)>,
+    recency: BTreeMap<u64, usize>,
+}
+
+impl BlockCache {
+    fn new(capacity: usize) -> Self {
+        BlockCache {
+            capacity,
+            tick: 0,
+            blocks: HashMap::with_capacity(capacity),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.blocks.len() > capacity {
+            self.evict();
+        }
+    }
+
+    fn get(&mut self, coffset: usize) -> Option<(Arc<Vec<u8>>, usize)> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (block, next, used) = self.blocks.get_mut(&coffset)?;
+        self.recency.remove(used);
+        self.recency.insert(tick, coffset);
+        *used = tick;
+        Some((Arc::clone(block), *next))
+    }
+
+    fn insert(&mut self, coffset: usize, block: Arc<Vec<u8>>, next: usize) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.blocks.len() >= self.capacity && !self.blocks.contains_key(&coffset) {
+            self.evict();
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, coffset);
+        if let Some((_, _, used)) = self.blocks.insert(coffset, (block, next, self.tick)) {
+            self.recency.remove(&used);
+        }
+    }
+
+    fn evict(&mut self) {
+        if let Some((_, oldest)) = self.recency.pop_first() {
+            self.blocks.remove(&oldest);
+        }
+    }
+}
+
+/// Find the BSIZE value in the extra subfields of a BGZF header.
+fn bsize(mut extra: &[u8]) -> Option<usize> {
+    while extra.len() >= 4 {
+        let len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        if extra[..2] == *b"BC" && len == 2 && extra.len() >= 6 {
+            return Some(u16::from_le_bytes([extra[4], extra[5]]) as usize);
+        }
+        extra = extra.get(4 + len..)?;
+    }
+    None
+}
+
+/// Read the `(compressed, uncompressed)` offset pairs from a `.gzi` file. The implicit first
+/// block at offset zero is included.
+fn read_gzi<P: AsRef<Path>>(path: P) -> io::Result<Vec<(usize, usize)>> {
+    let data = std::fs::read(path)?;
+    let u64_at = |i: usize| -> io::Result<usize> {
+        data.get(i..i + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Truncated .gzi file"))
+    };
+
+    let n = u64_at(0)?;
+    if data.len() != 8 + n.saturating_mul(16) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unexpected size of .gzi file",
+        ));
+    }
+    let mut blocks = Vec::with_capacity(n + 1);
+    blocks.push((0, 0));
+    for i in 0..n {
+        blocks.push((u64_at(8 + i * 16)?, u64_at(16 + i * 16)?));
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexedFasta;
+
+    #[test]
+    fn view() {
+        let gz = BgzfIndexedFasta::from_file("test/genome.fa.gz").unwrap();
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut buf = Vec::new();
+        for tid in 0..3 {
+            let len = fa.fai().size(tid).unwrap();
+            assert_eq!(
+                gz.view_tid(tid, &mut buf).unwrap().to_string(),
+                fa.view_tid(tid).unwrap().to_string()
+            );
+            for (start, stop) in [(0, len), (3, 7), (len / 2, len), (len, len)] {
+                assert_eq!(
+                    gz.view(tid, start, stop, &mut buf).unwrap().to_string(),
+                    fa.view(tid, start, stop).unwrap().to_string()
+                );
+            }
+        }
+        assert!(gz.view(0, 0, 11, &mut buf).is_err());
+        assert!(gz.view_tid(3, &mut buf).is_err());
+
+        let gz = gz.with_limits(Limits::new().max_view_len(10));
+        assert!(gz.view_tid(0, &mut buf).is_ok());
+        assert!(matches!(
+            gz.view_tid(1, &mut buf),
+            Err(Error::ViewTooLong { len: 100, max: 10 })
+        ));
+    }
+
+    #[test]
+    fn virtual_offsets() {
+        let gz = BgzfIndexedFasta::from_file("test/genome.fa.gz").unwrap();
+        // A-10 starts after the header line ">A-10\n", the end includes the line ending
+        assert_eq!(gz.virtual_offsets(0, 0, 10).unwrap(), (6, 17));
+        assert!(gz.virtual_offsets(0, 0, 11).is_err());
+
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        for (tid, pos) in [(1, 0), (2, 60), (2, 99)] {
+            let (start, _) = gz.virtual_offsets(tid, pos, pos + 1).unwrap();
+            let (block, _) = gz.block((start >> 16) as usize).unwrap();
+            let expected = fa.view(tid, pos, pos + 1).unwrap().to_string();
+            assert_eq!(block[(start & 0xffff) as usize], expected.as_bytes()[0]);
+
+            let (cstart, cend) = gz.compressed_range(tid, pos, pos + 1).unwrap();
+            assert_eq!(cstart as u64, start >> 16);
+            assert!(cend > cstart && cend <= gz.mmap.len());
+        }
+    }
+
+    #[test]
+    fn block_cache() {
+        let gz = BgzfIndexedFasta::from_file("test/genome.fa.gz")
+            .unwrap()
+            .with_cache_capacity(2);
+        let mut buf = Vec::new();
+        assert_eq!(gz.view(2, 0, 5, &mut buf).unwrap().to_string(), "AAAAA");
+        assert_eq!(gz.cache.lock().unwrap().blocks.len(), 1);
+        gz.view_tid(2, &mut buf).unwrap();
+        assert_eq!(gz.cache.lock().unwrap().blocks.len(), 2);
+
+        let gz = gz.with_metrics();
+        gz.view(2, 0, 5, &mut buf).unwrap();
+        gz.view(2, 0, 5, &mut buf).unwrap();
+        assert!(gz.view(2, 0, 101, &mut buf).is_err());
+        let s = gz.metrics().unwrap().snapshot();
+        assert_eq!((s.queries, s.errors, s.bases), (3, 1, 10));
+        assert_eq!(s.cache_hits + s.cache_misses, 2);
+        assert!(s.cache_hits >= 1);
+
+        let mut cache = BlockCache::new(2);
+        cache.insert(1, Arc::new(vec![1]), 2);
+        cache.insert(2, Arc::new(vec![2]), 3);
+        assert!(cache.get(1).is_some());
+        cache.insert(3, Arc::new(vec![3]), 4);
+        assert!(cache.get(2).is_none());
+        assert_eq!(cache.get(1).unwrap(), (Arc::new(vec![1]), 2));
+        // inserting a cached block again makes it the most recently used
+        cache.insert(3, Arc::new(vec![3]), 4);
+        cache.insert(5, Arc::new(vec![5]), 6);
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(3).is_some());
+        assert_eq!(cache.recency.len(), cache.blocks.len());
+        cache.set_capacity(0);
+        cache.insert(4, Arc::new(vec![4]), 5);
+        assert!(cache.blocks.is_empty());
+        assert!(cache.recency.is_empty());
+    }
+}
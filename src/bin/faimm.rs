@@ -0,0 +1,157 @@
+//! A small faidx-like command line tool on top of the library (feature `cli`).
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+use faimm::writer::FastaWriter;
+use faimm::{BaseCounts, Fai, IndexedFasta};
+
+const USAGE: &str = "\
+Usage: faimm <command> [options]
+
+Commands:
+  faidx <fa>                        Create <fa>.fai
+  extract [-w <width>] <fa> <region>...
+                                    Write the regions (chr, chr:start-end) as FASTA
+  nuc <fa> <bed>                    Base composition of the BED regions (- for stdin)
+  stats <fa>                        Base composition of every sequence";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("faidx") => faidx(&args[1..]),
+        Some("extract") => extract(&args[1..]),
+        Some("nuc") => nuc(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => Err(usage()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // a closed pipe, like `faimm extract ... | head`, is not an error
+        Err(e) if is_broken_pipe(&*e) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("faimm: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn is_broken_pipe(e: &(dyn std::error::Error + 'static)) -> bool {
+    let io = match e.downcast_ref::<faimm::Error>() {
+        Some(faimm::Error::Io(e)) => Some(e),
+        _ => e.downcast_ref::<io::Error>(),
+    };
+    io.is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn usage() -> Box<dyn std::error::Error> {
+    format!("invalid arguments\n\n{}", USAGE).into()
+}
+
+fn faidx(args: &[String]) -> Result<()> {
+    let [fasta] = args else {
+        return Err(usage());
+    };
+    Fai::create_and_write(fasta)?;
+    Ok(())
+}
+
+fn extract(mut args: &[String]) -> Result<()> {
+    let mut line_width = 60;
+    if let [flag, width, rest @ ..] = args {
+        if flag == "-w" {
+            line_width = width.parse().map_err(|_| usage())?;
+            args = rest;
+        }
+    }
+    let [fasta, regions @ ..] = args else {
+        return Err(usage());
+    };
+    if regions.is_empty() {
+        return Err(usage());
+    }
+    let fa = IndexedFasta::from_file(fasta)?;
+    let mut w = FastaWriter::new(BufWriter::new(io::stdout().lock())).line_width(line_width);
+    for region in regions {
+        w.write_view(region, &fa.view_region(region)?)?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Write the counts, the AT and GC fractions and the length as tab separated columns.
+fn write_counts<W: Write>(mut w: W, c: &BaseCounts) -> io::Result<()> {
+    let acgt = c.a + c.c + c.g + c.t;
+    let fraction = |n: usize| {
+        if acgt == 0 {
+            0.0
+        } else {
+            n as f64 / acgt as f64
+        }
+    };
+    writeln!(
+        w,
+        "{:.6}\t{:.6}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        fraction(c.a + c.t),
+        fraction(c.c + c.g),
+        c.a,
+        c.c,
+        c.g,
+        c.t,
+        c.n,
+        c.other,
+        acgt + c.n + c.other
+    )
+}
+
+const COUNT_COLUMNS: &str = "at\tgc\tA\tC\tG\tT\tN\tother\tlen";
+
+fn nuc(args: &[String]) -> Result<()> {
+    let [fasta, bed] = args else {
+        return Err(usage());
+    };
+    let fa = IndexedFasta::from_file(fasta)?;
+    let reader: Box<dyn BufRead> = if bed == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(bed)?))
+    };
+    let mut w = BufWriter::new(io::stdout().lock());
+    writeln!(w, "#chrom\tstart\tend\t{}", COUNT_COLUMNS)?;
+    for r in fa.bed_views(reader) {
+        let (record, view) = r?;
+        write!(
+            w,
+            "{}\t{}\t{}\t",
+            fa.fai().name(record.tid)?,
+            record.start,
+            record.end
+        )?;
+        write_counts(&mut w, &view.count_bases())?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn stats(args: &[String]) -> Result<()> {
+    let [fasta] = args else {
+        return Err(usage());
+    };
+    let fa = IndexedFasta::from_file(fasta)?;
+    let mut w = BufWriter::new(io::stdout().lock());
+    writeln!(w, "#name\t{}", COUNT_COLUMNS)?;
+    for (tid, name) in fa.fai().names().into_iter().enumerate() {
+        write!(w, "{}\t", name)?;
+        write_counts(&mut w, &fa.view_tid(tid)?.count_bases())?;
+    }
+    w.flush()?;
+    Ok(())
+}
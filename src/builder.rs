@@ -0,0 +1,232 @@
+//! Opening an `IndexedFasta` with a custom mapping strategy.
+//!
+//! `IndexedFasta::from_file` maps the fasta file lazily: pages are read from disk when they are
+//! first used. That is the right choice for short runs that read a few regions. A long-running
+//! server that reads all over the genome is better off prefaulting the complete file once, or
+//! locking it in memory. `IndexedFastaBuilder` configures the mapping and how the index is
+//! loaded, and can rebuild an index that is out of date.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::builder("test/genome.fa")
+//!     .populate(true)
+//!     .lazy_index(true)
+//!     .build()
+//!     .expect("Error opening fa");
+//! assert_eq!(fa.view(2, 48, 52).expect("Cannot get view").to_string(), "CCGG");
+//! ```
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use memmap2::MmapOptions;
+
+use crate::{check_modified, fai_path, Error, Fai, IndexedFasta, Result};
+
+/// Options for opening an `IndexedFasta`, created with `IndexedFasta::builder`.
+#[derive(Debug, Clone)]
+pub struct IndexedFastaBuilder {
+    path: PathBuf,
+    index_path: Option<OsString>,
+    lazy_index: bool,
+    populate: bool,
+    huge_pages: bool,
+    lock: bool,
+    rebuild_stale_index: bool,
+}
+
+impl IndexedFasta {
+    /// Configure how the fasta file at path `P` is opened, see `builder::IndexedFastaBuilder`.
+    pub fn builder<P: AsRef<Path>>(path: P) -> IndexedFastaBuilder {
+        IndexedFastaBuilder {
+            path: path.as_ref().to_owned(),
+            index_path: None,
+            lazy_index: false,
+            populate: false,
+            huge_pages: false,
+            lock: false,
+            rebuild_stale_index: false,
+        }
+    }
+}
+
+impl IndexedFastaBuilder {
+    /// Read the index from path `P` instead of the `.fai` next to the fasta file.
+    pub fn index_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.index_path = Some(path.as_ref().as_os_str().to_owned());
+        self
+    }
+
+    /// Load the index lazily, see `Fai::from_file_lazy`.
+    pub fn lazy_index(mut self, lazy: bool) -> Self {
+        self.lazy_index = lazy;
+        self
+    }
+
+    /// Read the complete file into the page cache when it is mapped (`MAP_POPULATE`, only on
+    /// Linux). Opening takes longer, but later views don't wait for the disk.
+    pub fn populate(mut self, populate: bool) -> Self {
+        self.populate = populate;
+        self
+    }
+
+    /// Map the file with huge pages of the default size (`MAP_HUGETLB`, only on Linux). This
+    /// only works for files on a hugetlbfs mount, other files fail to open.
+    pub fn huge_pages(mut self, huge: bool) -> Self {
+        self.huge_pages = huge;
+        self
+    }
+
+    /// Lock the mapped pages in memory (`mlock`, only on Unix), so they are never evicted. This
+    /// needs enough locked memory allowed by `ulimit -l`.
+    pub fn lock(mut self, lock: bool) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Create the index again when it is out of date (see `IndexedFasta::check_index`) and
+    /// replace the index file with it. Without this option an index that is out of date gives
+    /// `Error::StaleIndex`.
+    pub fn rebuild_stale_index(mut self, rebuild: bool) -> Self {
+        self.rebuild_stale_index = rebuild;
+        self
+    }
+
+    /// Open the fasta file and its index.
+    ///
+    /// Returns the `IndexedFasta` if successful, `Error::MissingIndex` if the index doesn't
+    /// exist, `Error::Mmap` if the file cannot be mapped or locked, `Error::StaleIndex` or
+    /// `Error::OutdatedIndex` if the index is out of date, Error otherwise.
+    pub fn build(self) -> Result<IndexedFasta> {
+        let index_path = self.index_path.unwrap_or_else(|| fai_path(&self.path));
+        let fasta_index = if self.lazy_index {
+            Fai::from_file_lazy(&index_path)?
+        } else {
+            Fai::from_file(&index_path)?
+        };
+
+        let file = File::open(&self.path)?;
+        let mut options = MmapOptions::new();
+        if self.populate {
+            options.populate();
+        }
+        if self.huge_pages {
+            options.huge(None);
+        }
+        let mmap = unsafe { options.map(&file) }.map_err(Error::Mmap)?;
+        if self.lock {
+            lock(&mmap)?;
+        }
+        let mut fa = IndexedFasta::from_source(mmap, fasta_index);
+        let checked = check_modified(&self.path, &index_path).and_then(|_| fa.check_index());
+        match checked {
+            Err(Error::StaleIndex { .. } | Error::OutdatedIndex(_)) if self.rebuild_stale_index => {
+                fa.fasta_index = Fai::create_from_bytes(fa.data())?;
+                fa.fasta_index.to_file(&index_path)?;
+            }
+            result => result?,
+        }
+        Ok(fa)
+    }
+}
+
+#[cfg(unix)]
+fn lock(mmap: &memmap2::Mmap) -> Result<()> {
+    mmap.lock().map_err(Error::Mmap)
+}
+
+#[cfg(not(unix))]
+fn lock(_mmap: &memmap2::Mmap) -> Result<()> {
+    Err(Error::Mmap(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Locking the mapped file is only supported on Unix",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let built = IndexedFasta::builder("test/genome.fa")
+            .index_path("test/genome.fa.fai")
+            .populate(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.view_tid(2).unwrap().to_string(),
+            fa.view_tid(2).unwrap().to_string()
+        );
+        assert!(matches!(
+            IndexedFasta::builder("test/genome.fa")
+                .index_path("test/missing.fai")
+                .build(),
+            Err(Error::MissingIndex(_))
+        ));
+    }
+
+    /// Write `contents` to `path` until its modification time is after that of `than`, as the
+    /// file system can give files that are written in quick succession the same time.
+    fn write_newer(path: &Path, contents: &str, than: &Path) {
+        let modified = |p: &Path| std::fs::metadata(p).unwrap().modified().unwrap();
+        loop {
+            std::fs::write(path, contents).unwrap();
+            if modified(path) > modified(than) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn stale_index() {
+        let dir = std::env::temp_dir().join(format!("faimm-stale-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fa = dir.join("stale.fa");
+        std::fs::write(&fa, ">a\nACGT\nAC\n").unwrap();
+        Fai::create_and_write(&fa).unwrap();
+        assert!(IndexedFasta::from_file(&fa).is_ok());
+
+        let fai = dir.join("stale.fa.fai");
+        write_newer(&fa, ">a\nACGT\nAC\n>b\nGG\n", &fai);
+        assert!(matches!(
+            IndexedFasta::from_file(&fa),
+            Err(Error::OutdatedIndex(path)) if path == dir.join("stale.fa.fai")
+        ));
+        let rebuilt = IndexedFasta::builder(&fa)
+            .rebuild_stale_index(true)
+            .build()
+            .unwrap();
+        assert_eq!(rebuilt.view_tid(1).unwrap().to_string(), "GG");
+        assert!(IndexedFasta::from_file_lazy(&fa).is_ok());
+
+        // an index in another order or with a subset of the sequences is valid
+        std::fs::write(&fai, "b\t2\t14\t2\t3\na\t6\t3\t4\t5\n").unwrap();
+        let reordered = IndexedFasta::from_file(&fa).unwrap();
+        assert_eq!(reordered.view_tid(0).unwrap().to_string(), "GG");
+        std::fs::write(&fai, "a\t6\t3\t4\t5\n").unwrap();
+        let subset = IndexedFasta::from_file(&fa).unwrap();
+        assert_eq!(subset.view_tid(0).unwrap().to_string(), "ACGTAC");
+
+        std::fs::write(&fai, "a\t6\t3\t4\t5\nb\t2\t14\t2\t3\n").unwrap();
+        write_newer(&fa, ">a\nACGT\n", &fai);
+        assert!(matches!(
+            IndexedFasta::builder(&fa).build(),
+            Err(Error::OutdatedIndex(_))
+        ));
+        let data = std::fs::read(&fa).unwrap();
+        let shortened = IndexedFasta::from_source(data, Fai::from_file(&fai).unwrap());
+        assert!(matches!(
+            shortened.check_index(),
+            Err(Error::StaleIndex {
+                expected: 16,
+                size: 8
+            })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
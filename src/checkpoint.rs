@@ -0,0 +1,321 @@
+//! Resumable genome-wide scans.
+//!
+//! A scan over a complete genome can take hours. When a cluster job is preempted halfway, it
+//! shouldn't have to start over. `Checkpointed` runs a scan in chunks and regularly saves the
+//! position and the partial result to a checkpoint file. When the scan is started again with the
+//! same checkpoint file, it continues from the saved position.
+//!
+//! The partial result is an accumulator that implements `Resumable`, for instance `BaseCounts`
+//! or `kmer::KmerCounts`. The checkpoint is a small text file with a `#faimm-checkpoint` line,
+//! a `position` line with the chromosome name and position of the next chunk (or a `done` line
+//! for a completed scan) and the lines written by the accumulator. A new checkpoint is written
+//! to a temporary file that replaces the old one, so a job that is killed while saving leaves the
+//! previous checkpoint intact.
+//!
+//! # Example
+//! ```
+//! use faimm::{BaseCounts, IndexedFasta};
+//! use faimm::checkpoint::Checkpointed;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let path = std::env::temp_dir().join(format!("faimm-doc-checkpoint-{}", std::process::id()));
+//! let counts = Checkpointed::new(&fa, &path)
+//!     .chunk_size(64)
+//!     .run(BaseCounts::default())
+//!     .expect("Scan failed");
+//! assert_eq!(counts.a, 135);
+//! // a completed scan returns the saved result without reading the genome again
+//! let again = Checkpointed::new(&fa, &path).run(BaseCounts::default()).unwrap();
+//! assert_eq!(again, counts);
+//! std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{BaseCounts, Error, FastaView, IndexedFasta, Result};
+
+/// An accumulator of a scan that can be saved to and loaded from a checkpoint.
+pub trait Resumable: Sized {
+    /// The number of bases a chunk should overlap with the previous chunk, for instance k - 1
+    /// for k-mers that span the chunk boundary.
+    fn overlap(&self) -> usize {
+        0
+    }
+
+    /// Add the bases of `view`, the chunk of chromosome tid that starts at `start`.
+    fn add(&mut self, tid: usize, start: usize, view: &FastaView);
+
+    /// Write the state to `w`.
+    fn save<W: Write>(&self, w: W) -> Result<()>;
+
+    /// Read the state written by `Resumable::save` from `r`.
+    fn load<R: BufRead>(r: R) -> Result<Self>;
+}
+
+/// A scan over all chromosomes that saves its progress to a checkpoint file.
+pub struct Checkpointed<'a> {
+    fasta: &'a IndexedFasta,
+    path: PathBuf,
+    chunk_size: usize,
+    interval: usize,
+}
+
+/// The position of a scan, as `(tid, pos)`, `None` when the scan is completed.
+type Position = Option<(usize, usize)>;
+
+impl<'a> Checkpointed<'a> {
+    /// Scan `fasta` with checkpoints saved at `path`. By default the chunks are 1 Mb and a
+    /// checkpoint is saved after every 64 chunks.
+    pub fn new<P: AsRef<Path>>(fasta: &'a IndexedFasta, path: P) -> Self {
+        Checkpointed {
+            fasta,
+            path: path.as_ref().to_owned(),
+            chunk_size: 1 << 20,
+            interval: 64,
+        }
+    }
+
+    /// Set the number of bases in a chunk.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size;
+        self
+    }
+
+    /// Save a checkpoint after every `chunks` chunks.
+    pub fn interval(mut self, chunks: usize) -> Self {
+        self.interval = chunks;
+        self
+    }
+
+    /// Run the scan with accumulator `init`, or continue it from the checkpoint file if it
+    /// exists. A checkpoint is also saved at the end of every chromosome and when the scan is
+    /// completed. The checkpoint file is kept, so running a completed scan again returns the
+    /// saved result.
+    ///
+    /// Returns the accumulator if successful, `Error::InvalidArgument` for a chunk size or
+    /// interval of 0 or a checkpoint of a chromosome that is not in the index, Error otherwise.
+    pub fn run<S: Resumable>(&self, init: S) -> Result<S> {
+        if self.chunk_size == 0 || self.interval == 0 {
+            return Err(Error::InvalidArgument(
+                "Chunk size and checkpoint interval should be at least 1".to_owned(),
+            ));
+        }
+        let (mut position, mut acc) = match self.load()? {
+            Some(checkpoint) => checkpoint,
+            None => (Some((0, 0)), init),
+        };
+
+        let chromosomes = self.fasta.fai().names().len();
+        let mut chunks = 0;
+        while let Some((tid, pos)) = position {
+            if tid == chromosomes {
+                position = None;
+                break;
+            }
+            let len = self.fasta.fai().size(tid)?;
+            if pos >= len {
+                position = Some((tid + 1, 0));
+                self.save(position, &acc)?;
+                continue;
+            }
+            let start = pos.saturating_sub(acc.overlap());
+            let stop = len.min(pos + self.chunk_size);
+            acc.add(tid, start, &self.fasta.view(tid, start, stop)?);
+            position = Some((tid, stop));
+            chunks += 1;
+            if chunks % self.interval == 0 {
+                self.save(position, &acc)?;
+            }
+        }
+        self.save(position, &acc)?;
+        Ok(acc)
+    }
+
+    /// Load the checkpoint file, `None` if it doesn't exist.
+    fn load<S: Resumable>(&self) -> Result<Option<(Position, S)>> {
+        let f = match File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let invalid = |line: usize, message: &str| Error::Malformed {
+            format: "checkpoint",
+            line: Some(line),
+            message: message.to_owned(),
+        };
+
+        let mut r = BufReader::new(f);
+        let mut line = String::new();
+        r.read_line(&mut line)?;
+        if line.trim_end() != "#faimm-checkpoint" {
+            return Err(invalid(1, "Missing #faimm-checkpoint header"));
+        }
+        line.clear();
+        r.read_line(&mut line)?;
+        let p: Vec<_> = line.trim_end().split('\t').collect();
+        let position = match p[..] {
+            ["done"] => None,
+            ["position", name, pos] => {
+                let tid = self.fasta.fai().tid(name).ok_or_else(|| {
+                    Error::InvalidArgument(format!(
+                        "Chromosome {} of checkpoint {} is not in the index",
+                        name,
+                        self.path.display()
+                    ))
+                })?;
+                let pos = pos.parse().map_err(|_| invalid(2, "Invalid position"))?;
+                Some((tid, pos))
+            }
+            _ => return Err(invalid(2, "Missing position")),
+        };
+        Ok(Some((position, S::load(r)?)))
+    }
+
+    /// Save a checkpoint at `position`, by replacing the checkpoint file.
+    fn save<S: Resumable>(&self, position: Position, acc: &S) -> Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let mut w = BufWriter::new(File::create(&tmp)?);
+        writeln!(w, "#faimm-checkpoint")?;
+        match position {
+            Some((tid, pos)) if tid < self.fasta.fai().names().len() => {
+                writeln!(w, "position\t{}\t{}", self.fasta.fai().name(tid)?, pos)?
+            }
+            _ => writeln!(w, "done")?,
+        }
+        acc.save(&mut w)?;
+        w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Saved as a single line with the tab separated counts of A, C, G, T, N and other bases.
+impl Resumable for BaseCounts {
+    fn add(&mut self, _tid: usize, _start: usize, view: &FastaView) {
+        *self += view.count_bases();
+    }
+
+    fn save<W: Write>(&self, mut w: W) -> Result<()> {
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.a, self.c, self.g, self.t, self.n, self.other
+        )?;
+        Ok(())
+    }
+
+    fn load<R: BufRead>(r: R) -> Result<Self> {
+        let invalid = || Error::Malformed {
+            format: "checkpoint",
+            line: None,
+            message: "Expected 6 base counts".to_owned(),
+        };
+        let line = r.lines().next().transpose()?.unwrap_or_default();
+        let counts = line
+            .split('\t')
+            .map(|c| c.parse())
+            .collect::<std::result::Result<Vec<usize>, _>>()
+            .map_err(|_| invalid())?;
+        let [a, c, g, t, n, other] = counts[..] else {
+            return Err(invalid());
+        };
+        Ok(BaseCounts {
+            a,
+            c,
+            g,
+            t,
+            n,
+            other,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::KmerCounts;
+    use std::panic::AssertUnwindSafe;
+
+    /// Counts chunks and fails after `fail_after` chunks, to simulate a preempted job.
+    struct Failing {
+        counts: BaseCounts,
+        chunks: usize,
+        fail_after: usize,
+    }
+
+    impl Resumable for Failing {
+        fn add(&mut self, tid: usize, start: usize, view: &FastaView) {
+            self.chunks += 1;
+            assert!(self.chunks <= self.fail_after, "preempted");
+            self.counts.add(tid, start, view);
+        }
+
+        fn save<W: Write>(&self, w: W) -> Result<()> {
+            self.counts.save(w)
+        }
+
+        fn load<R: BufRead>(r: R) -> Result<Self> {
+            Ok(Failing {
+                counts: BaseCounts::load(r)?,
+                chunks: 0,
+                fail_after: usize::MAX,
+            })
+        }
+    }
+
+    #[test]
+    fn resume() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let path = std::env::temp_dir().join(format!("faimm-checkpoint-{}", std::process::id()));
+        let scan = Checkpointed::new(&fa, &path).chunk_size(7).interval(2);
+        let failing = Failing {
+            counts: BaseCounts::default(),
+            chunks: 0,
+            fail_after: 10,
+        };
+        let preempted = std::panic::catch_unwind(AssertUnwindSafe(|| scan.run(failing)));
+        assert!(preempted.is_err());
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.starts_with("#faimm-checkpoint\nposition\tA-100\t56\n"));
+
+        let resumed = scan
+            .run(Failing {
+                counts: BaseCounts::default(),
+                chunks: 0,
+                fail_after: 0,
+            })
+            .unwrap();
+        let mut expected = BaseCounts::default();
+        for tid in 0..3 {
+            expected.add(tid, 0, &fa.view_tid(tid).unwrap());
+        }
+        assert_eq!(resumed.counts, expected);
+        fs::remove_file(&path).unwrap();
+        assert!(Checkpointed::new(&fa, &path)
+            .chunk_size(0)
+            .run(BaseCounts::default())
+            .is_err());
+    }
+
+    #[test]
+    fn kmers() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let path = std::env::temp_dir().join(format!("faimm-checkpoint-k-{}", std::process::id()));
+        let counts = Checkpointed::new(&fa, &path)
+            .chunk_size(9)
+            .run(KmerCounts::new(5).unwrap())
+            .unwrap();
+        let mut expected = KmerCounts::new(5).unwrap();
+        for tid in 0..3 {
+            expected.add_view(&fa.view_tid(tid).unwrap());
+        }
+        assert_eq!(counts, expected);
+        let loaded = Checkpointed::new(&fa, &path)
+            .run(KmerCounts::new(5).unwrap())
+            .unwrap();
+        assert_eq!(loaded, expected);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+//! Sequence checksums for SAM headers and CRAM files (feature `checksum`).
+//!
+//! `IndexedFasta::checksum` calculates the MD5 and the refget (GA4GH sha512t24u) digest of a
+//! sequence. Both are calculated over the uppercased bases without line endings, so they don't
+//! depend on soft-masking or the line width of the fasta file. The MD5 digest is the `M5` tag of
+//! a SAM header and the reference checksum of a CRAM slice, the sha512t24u digest identifies the
+//! sequence in a refget server as `SQ.<digest>`. `Md5Digester` and `RefgetDigester` calculate
+//! the digests separately with `IndexedFasta::digest`.
+//!
+//! A refget server looks up sequences by digest. `IndexedFasta::tid_by_md5` and
+//! `IndexedFasta::tid_by_trunc512` calculate the digests of all sequences on first use and keep
+//! them with the `IndexedFasta`. Calculating them reads the complete genome, so a server can
+//! save them to a sidecar file with `IndexedFasta::write_checksums` and load them on the next
+//! start with `IndexedFasta::load_checksums`. With the `rayon` feature the digests of all
+//! sequences are calculated in parallel, see `IndexedFasta::par_digest_all`.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let checksums = fa.checksum(0).expect("Cannot calculate checksums");
+//! assert_eq!(checksums.md5, "16c52c6e8326c071da771e66dc6e9e57");
+//! assert_eq!(checksums.refget_id(), "SQ.LnXbRf_Bc0oAYIVC2KdjXX9Znkvay_zw");
+//! let tid = fa.tid_by_trunc512("SQ._YyTz0YRRyypmud_rga86Pjj18uAlSGa").unwrap();
+//! assert_eq!(tid, Some(2));
+//! ```
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use md5::Md5;
+use sha2::{Digest, Sha512};
+
+use crate::digest::Digester;
+use crate::{Error, IndexedFasta, Result};
+
+/// A `Digester` for the lowercase hexadecimal MD5 digest.
+#[derive(Debug, Clone, Default)]
+pub struct Md5Digester(Md5);
+
+impl Digester for Md5Digester {
+    type Output = String;
+
+    fn update(&mut self, bases: &[u8]) {
+        self.0.update(bases);
+    }
+
+    fn finish(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+/// A `Digester` for the refget sha512t24u digest.
+#[derive(Debug, Clone, Default)]
+pub struct RefgetDigester(Sha512);
+
+impl Digester for RefgetDigester {
+    type Output = String;
+
+    fn update(&mut self, bases: &[u8]) {
+        self.0.update(bases);
+    }
+
+    fn finish(self) -> String {
+        base64url(&self.0.finalize()[..24])
+    }
+}
+
+/// The digests of a single sequence.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checksums {
+    /// The lowercase hexadecimal MD5 digest.
+    pub md5: String,
+    /// The base64url encoded first 24 bytes of the SHA-512 digest.
+    pub sha512t24u: String,
+}
+
+impl Checksums {
+    /// The refget identifier of the sequence, `SQ.` followed by the sha512t24u digest.
+    pub fn refget_id(&self) -> String {
+        format!("SQ.{}", self.sha512t24u)
+    }
+}
+
+/// Encode `bytes` with the URL and filename safe base64 alphabet. Padding is not needed for
+/// the 24 bytes of a sha512t24u digest.
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+        }
+    }
+    encoded
+}
+
+/// The checksums of all sequences of an `IndexedFasta` with maps back to the tid.
+#[derive(Debug)]
+pub(crate) struct ChecksumIndex {
+    checksums: Vec<Checksums>,
+    by_md5: HashMap<String, usize>,
+    by_sha512t24u: HashMap<String, usize>,
+}
+
+impl ChecksumIndex {
+    fn new(checksums: Vec<Checksums>) -> Self {
+        let mut by_md5 = HashMap::new();
+        let mut by_sha512t24u = HashMap::new();
+        // the first of identical sequences is found
+        for (tid, c) in checksums.iter().enumerate().rev() {
+            by_md5.insert(c.md5.clone(), tid);
+            by_sha512t24u.insert(c.sha512t24u.clone(), tid);
+        }
+        ChecksumIndex {
+            checksums,
+            by_md5,
+            by_sha512t24u,
+        }
+    }
+}
+
+/// Convert a hexadecimal TRUNC512 digest of refget v1 to the base64url sha512t24u digest.
+fn trunc512_to_sha512t24u(hex: &str) -> Option<String> {
+    if hex.len() != 48 || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..48)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some(base64url(&bytes))
+}
+
+impl IndexedFasta {
+    /// Calculate the MD5 and sha512t24u digests of chromosome tid. The digests are taken from
+    /// the checksums that were calculated or loaded before, if available.
+    ///
+    /// Returns the `Checksums` if successful, `Error::UnknownTid` for an unknown tid and
+    /// `Error::Truncated` if the chromosome extends beyond the end of the fasta data.
+    pub fn checksum(&self, tid: usize) -> Result<Checksums> {
+        if let Some(index) = self.checksums.get() {
+            return index
+                .checksums
+                .get(tid)
+                .cloned()
+                .ok_or(Error::UnknownTid(tid));
+        }
+        let (md5, sha512t24u) =
+            self.digest(tid, (Md5Digester::default(), RefgetDigester::default()))?;
+        Ok(Checksums { md5, sha512t24u })
+    }
+
+    /// Calculate the digests of all chromosomes. They are kept for the lookups by digest.
+    ///
+    /// Returns the `Checksums` in index order if successful, Error otherwise.
+    pub fn checksums(&self) -> Result<Vec<Checksums>> {
+        Ok(self.checksum_index()?.checksums.clone())
+    }
+
+    /// Return the index of the chromosome with MD5 digest `md5` (in hexadecimal, ignoring
+    /// case). The digests are calculated on first use.
+    ///
+    /// Returns the tid if successful, `None` if no chromosome matches.
+    pub fn tid_by_md5(&self, md5: &str) -> Result<Option<usize>> {
+        let index = self.checksum_index()?;
+        Ok(index.by_md5.get(&md5.to_ascii_lowercase()).copied())
+    }
+
+    /// Return the index of the chromosome with the truncated SHA-512 digest `digest`. This can
+    /// be the sha512t24u digest with or without the `SQ.` or `ga4gh:SQ.` prefix, or the
+    /// 48 character hexadecimal TRUNC512 digest of refget v1. The digests are calculated on
+    /// first use.
+    ///
+    /// Returns the tid if successful, `None` if no chromosome matches.
+    pub fn tid_by_trunc512(&self, digest: &str) -> Result<Option<usize>> {
+        let index = self.checksum_index()?;
+        let digest = digest.strip_prefix("ga4gh:").unwrap_or(digest);
+        let digest = digest.strip_prefix("SQ.").unwrap_or(digest);
+        if let Some(tid) = index.by_sha512t24u.get(digest) {
+            return Ok(Some(*tid));
+        }
+        Ok(trunc512_to_sha512t24u(digest).and_then(|d| index.by_sha512t24u.get(&d).copied()))
+    }
+
+    /// Write the digests of all chromosomes to `w` as tab separated lines with the name, MD5 and
+    /// sha512t24u digest, to be loaded with `IndexedFasta::load_checksums`.
+    pub fn write_checksums<W: Write>(&self, mut w: W) -> Result<()> {
+        let index = self.checksum_index()?;
+        for (name, c) in self.fai().names().into_iter().zip(&index.checksums) {
+            writeln!(w, "{}\t{}\t{}", name, c.md5, c.sha512t24u)?;
+        }
+        Ok(())
+    }
+
+    /// Load the digests written by `IndexedFasta::write_checksums` from `r`, instead of
+    /// calculating them. Digests that were calculated or loaded before are kept.
+    ///
+    /// Returns `Ok` if successful, `Error::InvalidArgument` if the chromosomes differ from the
+    /// index, `Error::Malformed` for a line without 3 columns, Error otherwise.
+    pub fn load_checksums<R: BufRead>(&self, r: R) -> Result<()> {
+        let names = self.fai().names();
+        let mut checksums = Vec::with_capacity(names.len());
+        for (i, line) in r.lines().enumerate() {
+            let line = line?;
+            let [name, md5, sha512t24u] = line.split('\t').collect::<Vec<_>>()[..] else {
+                return Err(Error::Malformed {
+                    format: "checksums",
+                    line: Some(i + 1),
+                    message: "Expected 3 columns".to_owned(),
+                });
+            };
+            if names.get(i) != Some(&name) {
+                return Err(Error::InvalidArgument(format!(
+                    "Checksum of {} on line {} doesn't match the index",
+                    name,
+                    i + 1
+                )));
+            }
+            checksums.push(Checksums {
+                md5: md5.to_owned(),
+                sha512t24u: sha512t24u.to_owned(),
+            });
+        }
+        if checksums.len() != names.len() {
+            return Err(Error::InvalidArgument(format!(
+                "Found checksums of {} chromosomes, the index has {}",
+                checksums.len(),
+                names.len()
+            )));
+        }
+        let _ = self.checksums.set(ChecksumIndex::new(checksums));
+        Ok(())
+    }
+
+    /// The checksums of all chromosomes, calculated on first use.
+    fn checksum_index(&self) -> Result<&ChecksumIndex> {
+        if let Some(index) = self.checksums.get() {
+            return Ok(index);
+        }
+        let new = || (Md5Digester::default(), RefgetDigester::default());
+        #[cfg(feature = "rayon")]
+        let digests = self.par_digest_all(new)?;
+        #[cfg(not(feature = "rayon"))]
+        let digests = self.digest_all(new)?;
+        let checksums = digests
+            .into_iter()
+            .map(|(md5, sha512t24u)| Checksums { md5, sha512t24u })
+            .collect();
+        // another thread can have been first, which gives the same result
+        let _ = self.checksums.set(ChecksumIndex::new(checksums));
+        Ok(self.checksums.get().expect("Checksums are set"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limits;
+
+    #[test]
+    fn checksums() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let all = fa.checksums().unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[2].md5, "2f488dab379d6a0c424926f54dc8a333");
+        assert_eq!(all[2].sha512t24u, "_YyTz0YRRyypmud_rga86Pjj18uAlSGa");
+        let limited = IndexedFasta::from_file("test/genome.fa")
+            .unwrap()
+            .with_limits(Limits::new().max_view_len(7));
+        assert_eq!(limited.checksum(2).unwrap(), all[2]);
+        assert!(fa.checksum(3).is_err());
+
+        let data = std::fs::read("test/genome.fa").unwrap();
+        let truncated =
+            IndexedFasta::from_source(data[..data.len() - 60].to_vec(), fa.fai().clone());
+        assert!(matches!(
+            truncated.checksum(2),
+            Err(Error::Truncated { .. })
+        ));
+        assert!(truncated.tid_by_md5(&all[2].md5).is_err());
+        // the failure is not cached as an index of wrong checksums
+        assert!(truncated.checksums.get().is_none());
+    }
+
+    #[test]
+    fn lookup() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert_eq!(
+            fa.tid_by_md5("2F488DAB379D6A0C424926F54DC8A333").unwrap(),
+            Some(2)
+        );
+        assert_eq!(fa.tid_by_md5("0").unwrap(), None);
+        let sha = "LnXbRf_Bc0oAYIVC2KdjXX9Znkvay_zw";
+        assert_eq!(fa.tid_by_trunc512(sha).unwrap(), Some(0));
+        assert_eq!(
+            fa.tid_by_trunc512(&format!("ga4gh:SQ.{}", sha)).unwrap(),
+            Some(0)
+        );
+        let hex = "2e75db45ffc1734a00608542d8a7635d7f599e4bdacbfcf0";
+        assert_eq!(trunc512_to_sha512t24u(hex).unwrap(), sha);
+        assert_eq!(fa.tid_by_trunc512(hex).unwrap(), Some(0));
+        assert_eq!(fa.tid_by_trunc512("SQ.unknown").unwrap(), None);
+    }
+
+    #[test]
+    fn sidecar() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut sidecar = Vec::new();
+        fa.write_checksums(&mut sidecar).unwrap();
+        let loaded = IndexedFasta::from_file("test/genome.fa").unwrap();
+        loaded.load_checksums(&sidecar[..]).unwrap();
+        assert_eq!(
+            loaded.checksums.get().unwrap().checksums,
+            fa.checksums().unwrap()
+        );
+
+        let fresh = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let truncated: Vec<_> = sidecar.split(|&b| b == b'\n').next().unwrap().to_vec();
+        assert!(fresh.load_checksums(&truncated[..]).is_err());
+        assert!(fresh.load_checksums("A-100\ta\tb\n".as_bytes()).is_err());
+        assert!(fresh.load_checksums("A-10\ta\n".as_bytes()).is_err());
+        assert!(fresh.checksums.get().is_none());
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(base64url(b"\xfb\xff"), "-_8");
+        assert_eq!(base64url(b"abc"), "YWJj");
+        assert_eq!(base64url(b"a"), "YQ");
+    }
+}
@@ -0,0 +1,251 @@
+//! Several indexed fasta files as one genome.
+//!
+//! Pipelines often combine a primary assembly with decoy sequences or a panel of viral genomes.
+//! A `FastaCollection` opens each file separately and numbers all sequences in one tid space,
+//! in the order the files were added, so the files don't have to be concatenated and indexed
+//! again. A sequence name that occurs in more than one file is resolved with a `NameClash`
+//! policy.
+//!
+//! # Example
+//! ```
+//! use faimm::collection::{FastaCollection, NameClash};
+//! use faimm::IndexedFasta;
+//! let mut genome = FastaCollection::new().name_clash(NameClash::Prefix);
+//! genome.add("primary", "test/genome.fa").expect("Error opening fa");
+//! let decoy = IndexedFasta::index_source(b">A-10\nCCCC\n>decoy1\nGGGG\n".to_vec()).unwrap();
+//! genome.add_fasta("decoy", decoy).expect("Name clash");
+//! assert_eq!(genome.names(), ["A-10", "A-100", "ACGT-25", "decoy_A-10", "decoy1"]);
+//! let v = genome.view_by_name("decoy_A-10", 0, 4).expect("Cannot get view");
+//! assert_eq!(v.to_string(), "CCCC");
+//! assert_eq!(genome.source(4).unwrap(), ("decoy", 1));
+//! ```
+
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// How a `FastaCollection` handles a sequence name that is already used by an earlier file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameClash {
+    /// Adding the file fails with `Error::InvalidArgument`.
+    #[default]
+    Error,
+    /// The sequence of the earlier file is kept, the new sequence is left out.
+    KeepFirst,
+    /// The sequence of the new file replaces the earlier sequence at the same tid.
+    KeepLast,
+    /// The new sequence is added with the name `<label>_<name>`, where label is the label of the
+    /// file.
+    Prefix,
+}
+
+/// Indexed fasta files with a shared tid and name space, see the module documentation.
+#[derive(Default)]
+pub struct FastaCollection {
+    files: Vec<(String, IndexedFasta)>,
+    /// The file and the tid within the file of every sequence, by name in tid order.
+    sequences: IndexMap<String, (usize, usize)>,
+    clash: NameClash,
+}
+
+impl FastaCollection {
+    /// Create an empty collection that fails on name clashes.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the policy for sequence names that occur in more than one file.
+    pub fn name_clash(mut self, clash: NameClash) -> Self {
+        self.clash = clash;
+        self
+    }
+
+    /// Open the fasta file at path `P` with its index and add its sequences with file label
+    /// `label`.
+    ///
+    /// Returns `Ok(())` if successful, `Error::InvalidArgument` for a name clash with
+    /// `NameClash::Error` or a prefixed name that is in use as well, and Error if the file
+    /// cannot be opened. Nothing is added when adding fails.
+    pub fn add<P: AsRef<Path>>(&mut self, label: &str, path: P) -> Result<()> {
+        self.add_fasta(label, IndexedFasta::from_file(path)?)
+    }
+
+    /// Add the sequences of an opened `IndexedFasta` with file label `label`, like `add`.
+    pub fn add_fasta(&mut self, label: &str, fasta: IndexedFasta) -> Result<()> {
+        let file = self.files.len();
+        let mut sequences = self.sequences.clone();
+        for (tid, name) in fasta.fai().names().into_iter().enumerate() {
+            let name = match sequences.get_mut(name) {
+                None => name.to_owned(),
+                Some(_) if self.clash == NameClash::KeepFirst => continue,
+                Some(location) if self.clash == NameClash::KeepLast => {
+                    *location = (file, tid);
+                    continue;
+                }
+                Some(_) if self.clash == NameClash::Prefix => {
+                    let prefixed = format!("{}_{}", label, name);
+                    if sequences.contains_key(&prefixed) {
+                        return Err(clash(&prefixed, label));
+                    }
+                    prefixed
+                }
+                Some(_) => return Err(clash(name, label)),
+            };
+            sequences.insert(name, (file, tid));
+        }
+        self.sequences = sequences;
+        self.files.push((label.to_owned(), fasta));
+        Ok(())
+    }
+
+    /// The names of all sequences in tid order.
+    pub fn names(&self) -> Vec<&str> {
+        self.sequences.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// The tid of sequence `name`, `None` if it is not in the collection.
+    pub fn tid(&self, name: &str) -> Option<usize> {
+        self.sequences.get_index_of(name)
+    }
+
+    /// The number of sequences in the collection.
+    pub fn len(&self) -> usize {
+        self.sequences.len()
+    }
+
+    /// Whether the collection has no sequences.
+    pub fn is_empty(&self) -> bool {
+        self.sequences.is_empty()
+    }
+
+    /// The label of the file of sequence tid and its tid within that file.
+    ///
+    /// Returns `Error::UnknownTid` if tid is not in the collection.
+    pub fn source(&self, tid: usize) -> Result<(&str, usize)> {
+        let (file, file_tid) = self.locate(tid)?;
+        Ok((&self.files[file].0, file_tid))
+    }
+
+    /// The labels and files of the collection in the order they were added.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &IndexedFasta)> {
+        self.files
+            .iter()
+            .map(|(label, fasta)| (label.as_str(), fasta))
+    }
+
+    /// The length of sequence tid.
+    pub fn size(&self, tid: usize) -> Result<usize> {
+        let (file, file_tid) = self.locate(tid)?;
+        self.files[file].1.fai().size(file_tid)
+    }
+
+    /// A view of the zero-based, half-open range start-stop of sequence tid.
+    ///
+    /// Returns the view if successful, Error otherwise. Errors refer to the tid in the
+    /// collection.
+    pub fn view(&self, tid: usize, start: usize, stop: usize) -> Result<FastaView<'_>> {
+        let (file, file_tid) = self.locate(tid)?;
+        self.files[file]
+            .1
+            .view(file_tid, start, stop)
+            .map_err(|e| with_tid(e, tid))
+    }
+
+    /// A view of the complete sequence tid.
+    pub fn view_tid(&self, tid: usize) -> Result<FastaView<'_>> {
+        let (file, file_tid) = self.locate(tid)?;
+        self.files[file]
+            .1
+            .view_tid(file_tid)
+            .map_err(|e| with_tid(e, tid))
+    }
+
+    /// A view of the range start-stop of sequence `name`.
+    ///
+    /// Returns `Error::UnknownName` if the name is not in the collection.
+    pub fn view_by_name(&self, name: &str, start: usize, stop: usize) -> Result<FastaView<'_>> {
+        let tid = self
+            .tid(name)
+            .ok_or_else(|| Error::UnknownName(name.to_owned()))?;
+        self.view(tid, start, stop)
+    }
+
+    fn locate(&self, tid: usize) -> Result<(usize, usize)> {
+        self.sequences
+            .get_index(tid)
+            .map(|(_, &location)| location)
+            .ok_or(Error::UnknownTid(tid))
+    }
+}
+
+fn clash(name: &str, label: &str) -> Error {
+    Error::InvalidArgument(format!(
+        "Sequence name {} of {} is already in the collection",
+        name, label
+    ))
+}
+
+/// Replace the tid of a file in an error by the tid in the collection.
+fn with_tid(e: Error, tid: usize) -> Error {
+    match e {
+        Error::OutOfRange {
+            start, stop, len, ..
+        } => Error::OutOfRange {
+            tid,
+            start,
+            stop,
+            len,
+        },
+        Error::Truncated { offset, size, .. } => Error::Truncated { tid, offset, size },
+        Error::UnknownTid(_) => Error::UnknownTid(tid),
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoys() -> IndexedFasta {
+        IndexedFasta::index_source(b">ACGT-25\nTTTT\n>chrEBV\nGATC\n".to_vec()).unwrap()
+    }
+
+    #[test]
+    fn name_clash() {
+        let mut genome = FastaCollection::new();
+        genome.add("primary", "test/genome.fa").unwrap();
+        assert!(matches!(
+            genome.add_fasta("decoy", decoys()),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!((genome.len(), genome.files().count()), (3, 1));
+
+        let mut first = FastaCollection::new().name_clash(NameClash::KeepFirst);
+        first.add("primary", "test/genome.fa").unwrap();
+        first.add_fasta("decoy", decoys()).unwrap();
+        assert_eq!(first.names(), ["A-10", "A-100", "ACGT-25", "chrEBV"]);
+        assert_eq!(first.size(2).unwrap(), 100);
+        assert_eq!(first.view_tid(3).unwrap().to_string(), "GATC");
+
+        let mut last = FastaCollection::new().name_clash(NameClash::KeepLast);
+        last.add("primary", "test/genome.fa").unwrap();
+        last.add_fasta("decoy", decoys()).unwrap();
+        assert_eq!(last.tid("ACGT-25"), Some(2));
+        assert_eq!(
+            last.view_by_name("ACGT-25", 0, 4).unwrap().to_string(),
+            "TTTT"
+        );
+        assert_eq!(last.source(2).unwrap(), ("decoy", 0));
+        assert!(matches!(
+            last.view(2, 0, 5),
+            Err(Error::OutOfRange { tid: 2, len: 4, .. })
+        ));
+        assert!(matches!(last.view(4, 0, 1), Err(Error::UnknownTid(4))));
+        assert!(matches!(
+            last.view_by_name("chr1", 0, 1),
+            Err(Error::UnknownName(_))
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+//! Sequence complexity metrics, for instance to filter low complexity regions.
+//!
+//! `FastaView::entropy` is the Shannon entropy of the A, C, G and T composition in bits, from 0
+//! for a single base repeat to 2 for equal amounts of all bases. `FastaView::complexity` also
+//! calculates the linguistic complexity in the same pass: the product over k = 1 up to `max_k`
+//! of the number of distinct k-mers divided by the largest possible number of distinct k-mers
+//! (`4^k`, or the number of k-mers when that is smaller). It is 1 for a sequence that contains
+//! as many different k-mers as possible and approaches 0 for repeats.
+//!
+//! Both metrics ignore case, and k-mers that contain N or other bases are skipped.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! assert_eq!(fa.view_tid(1).unwrap().entropy(), 0.0);
+//! assert_eq!(fa.view_tid(2).unwrap().entropy(), 2.0);
+//! let low: Vec<_> = fa
+//!     .windows(2, 20, 20)
+//!     .unwrap()
+//!     .filter(|(_, _, v)| v.entropy() < 0.5)
+//!     .map(|(start, end, _)| (start, end))
+//!     .collect();
+//! assert_eq!(low, [(0, 20), (80, 100)]);
+//! let c = fa.view(2, 40, 60).unwrap().complexity(3).expect("Invalid k");
+//! assert_eq!(c.entropy, 1.0);
+//! assert!(c.linguistic < 0.1);
+//! ```
+
+use crate::oligo::{CODE, MAX_K, OTHER, SKIPPED};
+use crate::{BaseCounts, Error, FastaView, Result};
+
+/// The complexity metrics of a view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complexity {
+    /// The Shannon entropy of the A, C, G and T composition in bits, NaN without these bases.
+    pub entropy: f64,
+    /// The linguistic complexity, between 0 and 1, NaN without A, C, G or T bases.
+    pub linguistic: f64,
+}
+
+/// The Shannon entropy in bits of the A, C, G and T counts.
+fn entropy(acgt: [usize; 4]) -> f64 {
+    let total: usize = acgt.iter().sum();
+    if total == 0 {
+        return f64::NAN;
+    }
+    acgt.iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum::<f64>()
+        // a single base gives -0.0
+        .abs()
+}
+
+impl<'a> FastaView<'a> {
+    /// The Shannon entropy of the A, C, G and T bases of the current view in bits.
+    ///
+    /// Returns NaN when there are no A, C, G or T bases.
+    pub fn entropy(&self) -> f64 {
+        let BaseCounts { a, c, g, t, .. } = self.count_bases();
+        entropy([a, c, g, t])
+    }
+
+    /// Calculate the entropy and the linguistic complexity with k-mers up to `max_k` (1 up to
+    /// `oligo::MAX_K`) in one pass over the current view.
+    ///
+    /// Returns the `Complexity` if successful, `Error::InvalidArgument` for an unsupported
+    /// `max_k`.
+    pub fn complexity(&self, max_k: usize) -> Result<Complexity> {
+        if max_k == 0 || max_k > MAX_K {
+            return Err(Error::InvalidArgument(format!(
+                "Complexity k-mer size should be between 1 and {}",
+                MAX_K
+            )));
+        }
+        // one flag for every k-mer of every size, the k-mers of size k start at (4^k - 4) / 3
+        let mut seen = vec![false; ((1 << (2 * max_k + 2)) - 4) / 3];
+        let mut distinct = vec![0usize; max_k];
+        let mut kmers = vec![0usize; max_k];
+        let mut acgt = [0; 4];
+        let mut index = 0;
+        let mut valid = 0;
+        for &b in self.0 {
+            match CODE[b as usize] {
+                SKIPPED => {}
+                OTHER => valid = 0,
+                v => {
+                    acgt[v as usize] += 1;
+                    index = (index << 2 | v as usize) & ((1 << (2 * max_k)) - 1);
+                    valid += 1;
+                    for k in 1..=valid.min(max_k) {
+                        let flag =
+                            &mut seen[((1 << (2 * k)) - 4) / 3 + (index & ((1 << (2 * k)) - 1))];
+                        if !*flag {
+                            *flag = true;
+                            distinct[k - 1] += 1;
+                        }
+                        kmers[k - 1] += 1;
+                    }
+                }
+            }
+        }
+
+        let linguistic = if kmers[0] == 0 {
+            f64::NAN
+        } else {
+            (1..=max_k)
+                .filter(|&k| kmers[k - 1] > 0)
+                .map(|k| distinct[k - 1] as f64 / kmers[k - 1].min(1 << (2 * k)) as f64)
+                .product()
+        };
+        Ok(Complexity {
+            entropy: entropy(acgt),
+            linguistic,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complexity() {
+        let repeat = FastaView::new(b"AAAA\naaaa");
+        assert_eq!(repeat.entropy(), 0.0);
+        let c = repeat.complexity(2).unwrap();
+        assert_eq!(c.entropy, 0.0);
+        // 1 of 4 possible 1-mers, 1 of 7 2-mers
+        assert_eq!(c.linguistic, 1.0 / 4.0 / 7.0);
+
+        let all = FastaView::new(b"ACGT\nNacgt");
+        assert_eq!(all.entropy(), 2.0);
+        let c = all.complexity(3).unwrap();
+        assert_eq!(c.entropy, all.entropy());
+        // 4 of 4 1-mers, AC CG GT of 6 2-mers, ACG CGT of 4 3-mers
+        assert_eq!(c.linguistic, 3.0 / 6.0 * 2.0 / 4.0);
+
+        assert!(FastaView::new(b"NNN")
+            .complexity(1)
+            .unwrap()
+            .linguistic
+            .is_nan());
+        assert!(FastaView::new(b"NNN").entropy().is_nan());
+        assert!(all.complexity(0).is_err());
+        assert!(all.complexity(MAX_K + 1).is_err());
+    }
+}
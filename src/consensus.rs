@@ -0,0 +1,359 @@
+//! Consensus sequences with variants applied to the reference.
+//!
+//! A `Consensus` applies a set of `Variant`s to the sequences of an `IndexedFasta`, like
+//! `bcftools consensus`. The modified sequence of a region or chromosome is streamed to a writer:
+//! the reference bases between variants are written directly from the mapped file, so only the
+//! variants are held in memory. The reference allele of every variant is checked against the
+//! fasta before it is applied.
+//!
+//! Variants are applied in order of position. A variant that overlaps an earlier applied variant
+//! is skipped, and only variants with a reference allele that lies completely in the requested
+//! region are applied. With `Consensus::iupac` heterozygous SNVs are written as the IUPAC code of
+//! the reference and the alternative base, other heterozygous variants are applied like
+//! homozygous variants.
+//!
+//! With the `vcf` feature `read_vcf` reads the variants of a VCF file, using the genotype of the
+//! first sample.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::consensus::Variant;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let variants = vec![
+//!     Variant::new(2, 22, "A", "G").heterozygous(true),
+//!     Variant::new(2, 25, "CC", "C"),
+//!     Variant::new(2, 28, "C", "CTT"),
+//! ];
+//! let consensus = fa.consensus(variants);
+//! assert_eq!(consensus.region(2, 20, 30).unwrap(), b"AAGAACCCTTC");
+//! let consensus = consensus.iupac(true);
+//! assert_eq!(consensus.region(2, 20, 30).unwrap(), b"AARAACCCTTC");
+//! ```
+
+#[cfg(feature = "vcf")]
+use std::io::BufRead;
+use std::io::Write;
+
+#[cfg(feature = "vcf")]
+use crate::Fai;
+use crate::{Error, IndexedFasta, Result};
+
+/// A variant replacing the reference allele at a position by an alternative allele.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    /// The chromosome of the variant.
+    pub tid: usize,
+    /// The zero-based position of the first base of the reference allele.
+    pub pos: usize,
+    /// The reference allele, checked against the fasta before the variant is applied.
+    pub reference: Vec<u8>,
+    /// The alternative allele that replaces the reference allele.
+    pub alt: Vec<u8>,
+    /// Whether the variant is heterozygous, used for IUPAC coding of SNVs.
+    pub heterozygous: bool,
+}
+
+impl Variant {
+    /// A homozygous variant replacing `reference` at zero-based position `pos` of chromosome
+    /// `tid` by `alt`.
+    pub fn new<R: AsRef<[u8]>, A: AsRef<[u8]>>(
+        tid: usize,
+        pos: usize,
+        reference: R,
+        alt: A,
+    ) -> Self {
+        Variant {
+            tid,
+            pos,
+            reference: reference.as_ref().to_vec(),
+            alt: alt.as_ref().to_vec(),
+            heterozygous: false,
+        }
+    }
+
+    /// Set whether the variant is heterozygous.
+    pub fn heterozygous(mut self, heterozygous: bool) -> Self {
+        self.heterozygous = heterozygous;
+        self
+    }
+
+    /// The end of the reference allele (exclusive).
+    pub fn end(&self) -> usize {
+        self.pos + self.reference.len()
+    }
+
+    fn is_snv(&self) -> bool {
+        self.reference.len() == 1 && self.alt.len() == 1
+    }
+}
+
+/// The IUPAC code of two bases, `None` for identical or non ACGT bases.
+fn iupac(a: u8, b: u8) -> Option<u8> {
+    let code = match (a.to_ascii_uppercase(), b.to_ascii_uppercase()) {
+        (b'A', b'C') | (b'C', b'A') => b'M',
+        (b'A', b'G') | (b'G', b'A') => b'R',
+        (b'A', b'T') | (b'T', b'A') => b'W',
+        (b'C', b'G') | (b'G', b'C') => b'S',
+        (b'C', b'T') | (b'T', b'C') => b'Y',
+        (b'G', b'T') | (b'T', b'G') => b'K',
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Variants to apply to the sequences of an `IndexedFasta`, see the module documentation.
+pub struct Consensus<'a> {
+    fasta: &'a IndexedFasta,
+    variants: Vec<Variant>,
+    iupac: bool,
+}
+
+impl<'a> Consensus<'a> {
+    /// Apply `variants` to the sequences of `fasta`. The variants don't need to be sorted.
+    pub fn new(fasta: &'a IndexedFasta, mut variants: Vec<Variant>) -> Self {
+        variants.sort_by_key(|v| (v.tid, v.pos));
+        Consensus {
+            fasta,
+            variants,
+            iupac: false,
+        }
+    }
+
+    /// Write heterozygous SNVs as the IUPAC code of both bases (default false).
+    pub fn iupac(mut self, iupac: bool) -> Self {
+        self.iupac = iupac;
+        self
+    }
+
+    /// The sorted variants.
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    /// Write the consensus sequence of the interval `start..stop` of chromosome `tid` to `w`,
+    /// without line breaks.
+    ///
+    /// Returns the number of bases written if successful, `Error::InvalidArgument` when the
+    /// reference allele of a variant doesn't match the fasta and the errors of
+    /// `IndexedFasta::view` for an invalid interval.
+    pub fn write_region<W: Write>(
+        &self,
+        tid: usize,
+        start: usize,
+        stop: usize,
+        mut w: W,
+    ) -> Result<usize> {
+        // check the interval before looking at the variants
+        self.fasta.view(tid, start, stop)?;
+        let first = self
+            .variants
+            .partition_point(|v| (v.tid, v.pos) < (tid, start));
+        let mut pos = start;
+        let mut written = 0;
+        for v in self.variants[first..]
+            .iter()
+            .take_while(|v| v.tid == tid && v.pos < stop)
+        {
+            if v.pos < pos || v.end() > stop {
+                continue;
+            }
+            let reference = self.fasta.view(tid, v.pos, v.end())?;
+            if !reference
+                .bases()
+                .zip(&v.reference)
+                .all(|(a, b)| a.eq_ignore_ascii_case(b))
+            {
+                return Err(Error::InvalidArgument(format!(
+                    "Reference allele {} of the variant at {}:{} doesn't match the fasta ({})",
+                    String::from_utf8_lossy(&v.reference),
+                    tid,
+                    v.pos,
+                    reference
+                )));
+            }
+
+            self.fasta.view(tid, pos, v.pos)?.write_to(&mut w)?;
+            written += v.pos - pos;
+            let code = if self.iupac && v.heterozygous && v.is_snv() {
+                iupac(v.reference[0], v.alt[0])
+            } else {
+                None
+            };
+            match code {
+                Some(code) => {
+                    w.write_all(&[code])?;
+                    written += 1;
+                }
+                None => {
+                    w.write_all(&v.alt)?;
+                    written += v.alt.len();
+                }
+            }
+            pos = v.end();
+        }
+        self.fasta.view(tid, pos, stop)?.write_to(&mut w)?;
+        Ok(written + stop - pos)
+    }
+
+    /// Write the consensus sequence of chromosome `tid` to `w`, see `Consensus::write_region`.
+    pub fn write_tid<W: Write>(&self, tid: usize, w: W) -> Result<usize> {
+        let size = self.fasta.fai().size(tid)?;
+        self.write_region(tid, 0, size, w)
+    }
+
+    /// Returns the consensus sequence of the interval `start..stop` of chromosome `tid`, see
+    /// `Consensus::write_region`.
+    pub fn region(&self, tid: usize, start: usize, stop: usize) -> Result<Vec<u8>> {
+        let mut seq = Vec::with_capacity(stop.saturating_sub(start));
+        self.write_region(tid, start, stop, &mut seq)?;
+        Ok(seq)
+    }
+}
+
+impl IndexedFasta {
+    /// Apply `variants` to the sequences of this fasta, see `consensus::Consensus`.
+    pub fn consensus(&self, variants: Vec<Variant>) -> Consensus<'_> {
+        Consensus::new(self, variants)
+    }
+}
+
+/// Read the variants of a VCF file from `reader`, resolving the chromosomes in `fai`.
+///
+/// The genotype (GT) of the first sample selects the alternative allele and whether the variant
+/// is heterozygous. Records without samples are applied as homozygous variants of the first
+/// alternative allele. Records with a reference or missing genotype and symbolic alleles such as
+/// `<DEL>` and `*` are skipped.
+///
+/// Returns the variants if successful, `Error::UnknownName` for a chromosome that is not in the
+/// index and `Error::Malformed` for an invalid record.
+#[cfg(feature = "vcf")]
+pub fn read_vcf<R: BufRead>(reader: R, fai: &Fai) -> Result<Vec<Variant>> {
+    let mut variants = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |message: String| Error::Malformed {
+            format: "VCF",
+            line: Some(i + 1),
+            message,
+        };
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 5 {
+            return Err(invalid(format!(
+                "Expected at least 5 columns, found {}",
+                fields.len()
+            )));
+        }
+        let tid = fai
+            .tid(fields[0])
+            .ok_or_else(|| Error::UnknownName(fields[0].to_owned()))?;
+        let pos: usize = match fields[1].parse() {
+            Ok(p) if p > 0 => p,
+            _ => return Err(invalid(format!("Invalid position {:?}", fields[1]))),
+        };
+        let alts: Vec<&str> = fields[4].split(',').collect();
+
+        let (allele, heterozygous) = match (fields.get(8), fields.get(9)) {
+            (Some(format), Some(sample)) => {
+                let Some(gt) = format
+                    .split(':')
+                    .position(|f| f == "GT")
+                    .and_then(|i| sample.split(':').nth(i))
+                else {
+                    return Err(invalid("Missing GT of the first sample".to_owned()));
+                };
+                let Ok(alleles) = gt
+                    .split(['/', '|'])
+                    .filter(|a| *a != ".")
+                    .map(str::parse::<usize>)
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                else {
+                    return Err(invalid(format!("Invalid genotype {:?}", gt)));
+                };
+                match alleles.iter().find(|&&a| a > 0) {
+                    Some(&a) => (a, alleles.iter().any(|&b| b != a)),
+                    None => continue,
+                }
+            }
+            _ => (1, false),
+        };
+        let alt = alts
+            .get(allele - 1)
+            .ok_or_else(|| invalid(format!("Unknown allele {}", allele)))?;
+        if alt.starts_with('<') || *alt == "*" || *alt == "." || alt.contains(['[', ']']) {
+            continue;
+        }
+        variants.push(Variant::new(tid, pos - 1, fields[3], alt).heterozygous(heterozygous));
+    }
+    Ok(variants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consensus() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let variants = vec![
+            Variant::new(2, 75, "T", "A"),
+            Variant::new(2, 24, "AC", "A"),
+            // overlaps the deletion and is skipped
+            Variant::new(2, 25, "C", "G"),
+            Variant::new(2, 49, "C", "T").heterozygous(true),
+            Variant::new(0, 0, "A", "AAAA"),
+        ];
+        let consensus = fa.consensus(variants).iupac(true);
+        assert_eq!(consensus.variants()[0].tid, 0);
+        assert_eq!(consensus.region(2, 20, 30).unwrap(), b"AAAAACCCC");
+        assert_eq!(consensus.region(2, 48, 52).unwrap(), b"CYGG");
+        // the deletion extends beyond the region and is not applied
+        assert_eq!(consensus.region(2, 20, 25).unwrap(), b"AAAAA");
+        let mut seq = Vec::new();
+        assert_eq!(consensus.write_tid(2, &mut seq).unwrap(), 99);
+        assert_eq!(&seq[72..76], b"GGAT");
+        assert_eq!(consensus.region(0, 0, 10).unwrap(), b"AAAAAAAAAAAAA");
+
+        let wrong = fa.consensus(vec![Variant::new(2, 30, "A", "T")]);
+        assert!(matches!(
+            wrong.region(2, 0, 100),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(wrong.region(2, 0, 101).is_err());
+    }
+
+    #[cfg(feature = "vcf")]
+    #[test]
+    fn vcf() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let vcf = "##fileformat=VCFv4.2\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n\
+            ACGT-25\t23\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n\
+            ACGT-25\t26\t.\tCC\tC,CA\t.\tPASS\t.\tGT:DP\t2|2:10\n\
+            ACGT-25\t30\t.\tC\tG\t.\tPASS\t.\tGT\t0/0\n\
+            ACGT-25\t31\t.\tC\t<DEL>\t.\tPASS\t.\tGT\t1/1\n\
+            A-10\t1\t.\tA\tT\t.\t.\t.\n";
+        let variants = read_vcf(vcf.as_bytes(), fa.fai()).unwrap();
+        assert_eq!(
+            variants,
+            [
+                Variant::new(2, 22, "A", "G").heterozygous(true),
+                Variant::new(2, 25, "CC", "CA"),
+                Variant::new(0, 0, "A", "T"),
+            ]
+        );
+        let consensus = fa.consensus(variants).iupac(true);
+        assert_eq!(consensus.region(2, 20, 30).unwrap(), b"AARAACACCC");
+
+        assert!(matches!(
+            read_vcf("chrX\t1\t.\tA\tT\n".as_bytes(), fa.fai()),
+            Err(Error::UnknownName(_))
+        ));
+        assert!(read_vcf("A-10\t0\t.\tA\tT\n".as_bytes(), fa.fai()).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Typed intervals for the common coordinate conventions.
+//!
+//! faimm and the BED format use zero-based, half-open intervals: the first base of a chromosome
+//! is 0 and `end` is the position after the last base. GFF, VCF and samtools regions use
+//! one-based, closed intervals: the first base is 1 and `end` is the last base. Mixing the two
+//! gives off-by-one errors that are hard to spot. `ZeroBased` and `OneBased` keep the convention
+//! in the type, so the compiler catches a mix-up, and convert between them with checks for
+//! underflow and overflow.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::coords::{OneBased, ZeroBased};
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! // a GFF feature on bases 21 to 30
+//! let feature = OneBased::new(21, 30).expect("Invalid interval");
+//! let bed = ZeroBased::try_from(feature).expect("Invalid interval");
+//! assert_eq!((bed.start(), bed.end()), (20, 30));
+//! assert_eq!(fa.view_interval(2, bed).unwrap().to_string(), "AAAAACCCCC");
+//! ```
+
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// A zero-based, half-open interval, the convention of BED files and this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ZeroBased {
+    start: usize,
+    end: usize,
+}
+
+/// A one-based, closed interval, the convention of GFF, VCF and samtools regions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OneBased {
+    start: usize,
+    end: usize,
+}
+
+impl ZeroBased {
+    /// Create the interval `start..end`.
+    ///
+    /// Returns the interval, or `Error::InvalidArgument` if `start` is larger than `end`.
+    pub fn new(start: usize, end: usize) -> Result<Self> {
+        if start > end {
+            return Err(Error::InvalidArgument(format!(
+                "Interval start {} is larger than end {}",
+                start, end
+            )));
+        }
+        Ok(ZeroBased { start, end })
+    }
+
+    /// The position of the first base.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The position after the last base.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The number of bases in the interval.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the interval has no bases.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl OneBased {
+    /// Create the interval of bases `start` up to and including `end`. An empty interval, like
+    /// the insertion point of a GFF feature, is written with `start` one larger than `end`.
+    ///
+    /// Returns the interval, or `Error::InvalidArgument` if `start` is 0 or more than one
+    /// larger than `end`.
+    pub fn new(start: usize, end: usize) -> Result<Self> {
+        if start == 0 {
+            return Err(Error::InvalidArgument(
+                "One-based interval starts at 0".to_owned(),
+            ));
+        }
+        if start - 1 > end {
+            return Err(Error::InvalidArgument(format!(
+                "Interval start {} is larger than end {}",
+                start, end
+            )));
+        }
+        Ok(OneBased { start, end })
+    }
+
+    /// The interval of the single base at one-based `pos`, like a VCF position.
+    pub fn point(pos: usize) -> Result<Self> {
+        OneBased::new(pos, pos)
+    }
+
+    /// The position of the first base.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The position of the last base.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The number of bases in the interval.
+    pub fn len(&self) -> usize {
+        self.end + 1 - self.start
+    }
+
+    /// Returns `true` if the interval has no bases.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Convert a one-based interval. This can't fail, because a one-based start is never 0.
+impl From<OneBased> for ZeroBased {
+    fn from(i: OneBased) -> Self {
+        ZeroBased {
+            start: i.start - 1,
+            end: i.end,
+        }
+    }
+}
+
+/// Convert a zero-based interval. Fails with `Error::InvalidArgument` when the start position
+/// overflows.
+impl TryFrom<ZeroBased> for OneBased {
+    type Error = Error;
+
+    fn try_from(i: ZeroBased) -> Result<Self> {
+        let start = i.start.checked_add(1).ok_or_else(|| {
+            Error::InvalidArgument("Interval start overflows as one-based position".to_owned())
+        })?;
+        Ok(OneBased { start, end: i.end })
+    }
+}
+
+impl IndexedFasta {
+    /// Return a view of `interval` on chromosome tid. Use `ZeroBased::from` to view a
+    /// `OneBased` interval.
+    ///
+    /// Returns FastaView for the interval if successful, Error otherwise.
+    pub fn view_interval(&self, tid: usize, interval: ZeroBased) -> Result<FastaView<'_>> {
+        self.view(tid, interval.start, interval.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let one = OneBased::new(1, 10).unwrap();
+        let zero = ZeroBased::from(one);
+        assert_eq!((zero.start(), zero.end(), zero.len()), (0, 10, 10));
+        assert_eq!(OneBased::try_from(zero).unwrap(), one);
+        assert_eq!(one.len(), 10);
+
+        let insertion = OneBased::new(5, 4).unwrap();
+        assert!(insertion.is_empty());
+        assert!(ZeroBased::from(insertion).is_empty());
+        assert_eq!(ZeroBased::from(OneBased::point(7).unwrap()).start(), 6);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(OneBased::new(0, 10).is_err());
+        assert!(OneBased::point(0).is_err());
+        assert!(OneBased::new(12, 10).is_err());
+        assert!(ZeroBased::new(11, 10).is_err());
+        let max = ZeroBased::new(usize::MAX, usize::MAX).unwrap();
+        assert!(matches!(
+            OneBased::try_from(max),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
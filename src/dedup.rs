@@ -0,0 +1,198 @@
+//! Duplicate sequence detection.
+//!
+//! `Duplicates` hashes the bases of fixed-length windows along all chromosomes, or of a list of
+//! regions, and reports the groups of windows with the same sequence. This finds collapsed
+//! segmental duplications in an assembly and duplicated windows in a set of training data.
+//!
+//! The 128-bit FNV-1a hash of the bases is used as the identity of a window, so the sequences
+//! don't have to be kept in memory. With 128 bits the chance of a collision between two different
+//! sequences is negligible, even for billions of windows. By default soft-masking is ignored and
+//! windows without A, C, G or T bases, like the windows in a gap, are skipped.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::dedup::Duplicates;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let groups = Duplicates::new(10).find(&fa).expect("Cannot hash windows");
+//! // the windows of A-10 and A-100 and the homopolymer windows of ACGT-25
+//! assert_eq!(groups.len(), 4);
+//! assert_eq!(groups[0].regions.len(), 13);
+//! assert_eq!(groups[0].regions[0], (0, 0, 10));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+const FNV_OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// Settings for the detection of duplicate windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Duplicates {
+    size: usize,
+    step: usize,
+    ignore_case: bool,
+}
+
+/// Regions with the same sequence, as `(tid, start, stop)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub hash: u128,
+    pub regions: Vec<(usize, usize, usize)>,
+}
+
+/// Hash the bases of `view`, uppercased if `ignore_case` is set. Returns `None` for a view
+/// without A, C, G or T bases.
+fn hash_view(view: &FastaView, ignore_case: bool) -> Option<u128> {
+    let mut hash = FNV_OFFSET;
+    let mut acgt = false;
+    for &b in view.bases() {
+        let b = if ignore_case {
+            b.to_ascii_uppercase()
+        } else {
+            b
+        };
+        acgt |= matches!(b.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T');
+        hash ^= b as u128;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    acgt.then_some(hash)
+}
+
+impl<'a> FastaView<'a> {
+    /// The 128-bit FNV-1a hash of the bases in the current view. Views with the same bases
+    /// have the same hash, independent of the line breaks.
+    pub fn hash128(&self) -> u128 {
+        self.bases().fold(FNV_OFFSET, |hash, &b| {
+            (hash ^ b as u128).wrapping_mul(FNV_PRIME)
+        })
+    }
+}
+
+impl Duplicates {
+    /// Compare windows of `size` bases. By default the windows don't overlap and soft-masking is
+    /// ignored.
+    pub fn new(size: usize) -> Self {
+        Duplicates {
+            size,
+            step: size,
+            ignore_case: true,
+        }
+    }
+
+    /// Start a window every `step` bases.
+    pub fn step(mut self, step: usize) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Set whether lowercase bases are the same as uppercase bases.
+    pub fn ignore_case(mut self, ignore: bool) -> Self {
+        self.ignore_case = ignore;
+        self
+    }
+
+    /// Find the duplicate windows on all chromosomes of `fasta`. The last window of a chromosome
+    /// is truncated, like `IndexedFasta::windows`.
+    ///
+    /// Returns the groups of duplicates in the order of their first window if successful,
+    /// `Error::InvalidArgument` for a window size or step of 0.
+    pub fn find(&self, fasta: &IndexedFasta) -> Result<Vec<DuplicateGroup>> {
+        if self.size == 0 || self.step == 0 {
+            return Err(Error::InvalidArgument(
+                "Window size and step should be at least 1".to_owned(),
+            ));
+        }
+        let mut hashes = HashMap::new();
+        for tid in 0..fasta.fai().names().len() {
+            for (start, stop, view) in fasta.windows(tid, self.size, self.step)? {
+                self.insert(&mut hashes, &view, (tid, start, stop));
+            }
+        }
+        Ok(groups(hashes))
+    }
+
+    /// Find the duplicates in `regions` given as `(tid, start, stop)`. The window size and step
+    /// are not used.
+    ///
+    /// Returns the groups of duplicates in the order of their first region if successful, Error
+    /// otherwise.
+    pub fn find_regions(
+        &self,
+        fasta: &IndexedFasta,
+        regions: &[(usize, usize, usize)],
+    ) -> Result<Vec<DuplicateGroup>> {
+        let mut hashes = HashMap::new();
+        for &(tid, start, stop) in regions {
+            let view = fasta.view(tid, start, stop)?;
+            self.insert(&mut hashes, &view, (tid, start, stop));
+        }
+        Ok(groups(hashes))
+    }
+
+    fn insert(
+        &self,
+        hashes: &mut HashMap<u128, Vec<(usize, usize, usize)>>,
+        view: &FastaView,
+        region: (usize, usize, usize),
+    ) {
+        if let Some(hash) = hash_view(view, self.ignore_case) {
+            hashes.entry(hash).or_default().push(region);
+        }
+    }
+}
+
+/// Collect the hashes with more than one region.
+fn groups(hashes: HashMap<u128, Vec<(usize, usize, usize)>>) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<_> = hashes
+        .into_iter()
+        .filter(|(_, regions)| regions.len() > 1)
+        .map(|(hash, regions)| DuplicateGroup { hash, regions })
+        .collect();
+    groups.sort_by_key(|g| g.regions[0]);
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn hash128() {
+        let v = FastaView::new(b"ACGT\nTGCA");
+        assert_eq!(v.hash128(), FastaView::new(b"ACGTTG\r\nCA").hash128());
+        assert_ne!(v.hash128(), FastaView::new(b"ACGT\nTGCa").hash128());
+        assert_eq!(
+            hash_view(&FastaView::new(b"acgtTGCA"), true),
+            Some(v.hash128())
+        );
+        assert_eq!(hash_view(&FastaView::new(b"NNNN"), true), None);
+    }
+
+    #[test]
+    fn duplicates() {
+        let dir = std::env::temp_dir().join(format!("faimm-dedup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dup.fa");
+        fs::write(&path, ">a\nACGTTGCA\nNNNNNNNN\n>b\nacgttgca\nACGTTGCA\n").unwrap();
+        crate::Fai::create_and_write(&path).unwrap();
+        let fa = IndexedFasta::from_file(&path).unwrap();
+
+        let groups = Duplicates::new(8).find(&fa).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].regions, vec![(0, 0, 8), (1, 0, 8), (1, 8, 16)]);
+        let exact = Duplicates::new(8).ignore_case(false).find(&fa).unwrap();
+        assert_eq!(exact[0].regions, vec![(0, 0, 8), (1, 8, 16)]);
+        assert!(Duplicates::new(4).step(0).find(&fa).is_err());
+
+        let regions = Duplicates::new(1)
+            .find_regions(&fa, &[(0, 4, 8), (1, 0, 4), (1, 12, 16)])
+            .unwrap();
+        assert_eq!(regions[0].regions, vec![(0, 4, 8), (1, 12, 16)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+//! Picard-style sequence dictionaries (feature `checksum`).
+//!
+//! GATK and Picard expect a `.dict` file next to a reference: a SAM header with a `@HD` line and
+//! an `@SQ` line for every sequence with its name (`SN`), length (`LN`), MD5 digest (`M5`) and
+//! optionally the location of the reference (`UR`). `create_dict` writes the dictionary next to
+//! the fasta file, replacing the `.fa`, `.fasta` or `.fna` extension (after an optional `.gz`)
+//! by `.dict`, like `picard CreateSequenceDictionary`. `read_dict` reads the `@SQ` lines of a
+//! dictionary or SAM header back as `DictSequence`s.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::dict::write_dict;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let mut dict = Vec::new();
+//! write_dict(&fa, None, &mut dict).expect("Cannot write dictionary");
+//! let dict = String::from_utf8(dict).unwrap();
+//! assert!(dict.starts_with("@HD\tVN:1.0\tSO:unsorted\n@SQ\tSN:A-10\tLN:10\tM5:"));
+//! ```
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{Error, IndexedFasta, Result};
+
+/// A sequence of a dictionary, read from an `@SQ` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictSequence {
+    /// The name (`SN`).
+    pub name: String,
+    /// The length (`LN`).
+    pub len: usize,
+    /// The MD5 digest (`M5`) in lowercase, if present.
+    pub md5: Option<String>,
+    /// The alternative names (`AN`), if present.
+    pub alt_names: Vec<String>,
+}
+
+/// Read the `@SQ` lines of a sequence dictionary or SAM header from `r`. Other lines are
+/// skipped.
+///
+/// Returns the sequences in the order of the dictionary if successful, `Error::Malformed` for
+/// an `@SQ` line without a name or a valid length, Error otherwise.
+pub fn read_dict<R: BufRead>(r: R) -> Result<Vec<DictSequence>> {
+    let mut sequences = Vec::new();
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        let Some(tags) = line.trim_end_matches('\r').strip_prefix("@SQ\t") else {
+            continue;
+        };
+        let (mut name, mut len, mut md5, mut alt_names) = (None, None, None, Vec::new());
+        for tag in tags.split('\t') {
+            match tag.split_once(':') {
+                Some(("SN", v)) => name = Some(v.to_owned()),
+                Some(("LN", v)) => len = v.parse().ok(),
+                Some(("M5", v)) => md5 = Some(v.to_ascii_lowercase()),
+                Some(("AN", v)) => alt_names = v.split(',').map(str::to_owned).collect(),
+                _ => {}
+            }
+        }
+        let (Some(name), Some(len)) = (name, len) else {
+            return Err(Error::Malformed {
+                format: "dict",
+                line: Some(i + 1),
+                message: "Expected a SN and LN tag".to_owned(),
+            });
+        };
+        sequences.push(DictSequence {
+            name,
+            len,
+            md5,
+            alt_names,
+        });
+    }
+    Ok(sequences)
+}
+
+/// Write the sequence dictionary of `fasta` to `w`. If `uri` is given, it is added as the `UR`
+/// tag of every sequence. The MD5 digests are taken from `IndexedFasta::checksums`, which
+/// calculates them in parallel with the `rayon` feature.
+pub fn write_dict<W: Write>(fasta: &IndexedFasta, uri: Option<&str>, mut w: W) -> Result<()> {
+    fasta.checksums()?;
+    write_header(&mut w)?;
+    for tid in 0..fasta.fai().names().len() {
+        write_sequence(&mut w, fasta, tid, uri)?;
+    }
+    Ok(())
+}
+
+/// Write the `@HD` line of a dictionary.
+pub(crate) fn write_header<W: Write>(mut w: W) -> Result<()> {
+    writeln!(w, "@HD\tVN:1.0\tSO:unsorted")?;
+    Ok(())
+}
+
+/// Write the `@SQ` line of chromosome tid.
+pub(crate) fn write_sequence<W: Write>(
+    mut w: W,
+    fasta: &IndexedFasta,
+    tid: usize,
+    uri: Option<&str>,
+) -> Result<()> {
+    write!(
+        w,
+        "@SQ\tSN:{}\tLN:{}\tM5:{}",
+        fasta.fai().name(tid)?,
+        fasta.fai().size(tid)?,
+        fasta.checksum(tid)?.md5
+    )?;
+    if let Some(uri) = uri {
+        write!(w, "\tUR:{}", uri)?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+/// The path of the sequence dictionary of the fasta file at `fasta`.
+pub fn dict_path<P: AsRef<Path>>(fasta: P) -> PathBuf {
+    let fasta = fasta.as_ref();
+    let mut path = fasta.to_owned();
+    if path.extension().is_some_and(|e| e == "gz") {
+        path.set_extension("");
+    }
+    if path
+        .extension()
+        .is_some_and(|e| e == "fa" || e == "fasta" || e == "fna")
+    {
+        path.set_extension("");
+    }
+    let mut name = path.into_os_string();
+    name.push(".dict");
+    name.into()
+}
+
+/// Create the sequence dictionary of the indexed fasta file at `fasta`, with the absolute path
+/// of the fasta file as `file:` URI.
+///
+/// Returns the path of the new dictionary if successful, Error otherwise.
+pub fn create_dict<P: AsRef<Path>>(fasta: P) -> Result<PathBuf> {
+    let fa = IndexedFasta::from_file(&fasta)?;
+    let uri = format!("file:{}", fs::canonicalize(&fasta)?.display());
+    let path = dict_path(&fasta);
+    let mut w = BufWriter::new(File::create(&path)?);
+    write_dict(&fa, Some(&uri), &mut w)?;
+    w.flush()?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dict() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut dict = Vec::new();
+        write_dict(&fa, Some("file:/ref/genome.fa"), &mut dict).unwrap();
+        let dict = String::from_utf8(dict).unwrap();
+        let lines: Vec<_> = dict.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[3],
+            "@SQ\tSN:ACGT-25\tLN:100\tM5:2f488dab379d6a0c424926f54dc8a333\tUR:file:/ref/genome.fa"
+        );
+
+        let sequences = read_dict(dict.as_bytes()).unwrap();
+        assert_eq!(sequences.len(), 3);
+        assert_eq!(sequences[2].name, "ACGT-25");
+        assert_eq!(sequences[2].len, 100);
+        assert_eq!(
+            sequences[2].md5.as_deref(),
+            Some("2f488dab379d6a0c424926f54dc8a333")
+        );
+        let header = "@HD\tVN:1.6\n@SQ\tSN:chr1\tLN:248956422\tAN:1,NC_000001.11\n";
+        let sequences = read_dict(header.as_bytes()).unwrap();
+        assert_eq!(sequences[0].alt_names, ["1", "NC_000001.11"]);
+        assert_eq!(sequences[0].md5, None);
+        assert!(matches!(
+            read_dict("@SQ\tSN:chr1\tLN:x\n".as_bytes()),
+            Err(Error::Malformed { line: Some(1), .. })
+        ));
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(dict_path("ref/hg38.fa"), Path::new("ref/hg38.dict"));
+        assert_eq!(dict_path("hg38.fasta.gz"), Path::new("hg38.dict"));
+        assert_eq!(dict_path("hg38.seq"), Path::new("hg38.seq.dict"));
+
+        let dir = std::env::temp_dir().join(format!("faimm-dict-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fasta = dir.join("small.fasta");
+        fs::write(&fasta, ">one\nACGT\n").unwrap();
+        crate::Fai::create_and_write(&fasta).unwrap();
+        let path = create_dict(&fasta).unwrap();
+        assert_eq!(path, dir.join("small.dict"));
+        assert!(fs::read_to_string(&path).unwrap().contains("\tUR:file:/"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+//! Streaming digests of normalized sequences.
+//!
+//! `IndexedFasta::digest` feeds the bases of a chromosome to a `Digester` in chunks. The bases
+//! are uppercased and the line endings are skipped, so the digest only depends on the sequence
+//! and not on soft-masking or the line width of the fasta file. Implement `Digester` for any hash
+//! function, like BLAKE3 or SHA-256, to get the same normalization as the MD5 and refget digests
+//! of the `checksum` module. A tuple of two digesters calculates both digests in a single pass.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::digest::Digester;
+//!
+//! /// The 64-bit FNV-1a hash.
+//! struct Fnv(u64);
+//!
+//! impl Digester for Fnv {
+//!     type Output = u64;
+//!
+//!     fn update(&mut self, bases: &[u8]) {
+//!         for &b in bases {
+//!             self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+//!         }
+//!     }
+//!
+//!     fn finish(self) -> u64 {
+//!         self.0
+//!     }
+//! }
+//!
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let digests = fa.digest_all(|| Fnv(0xcbf29ce484222325)).expect("Cannot digest");
+//! assert_eq!(digests.len(), 3);
+//! assert_ne!(digests[0], digests[1]);
+//! ```
+
+use crate::{IndexedFasta, Result};
+
+/// The number of bases that are digested at once.
+const CHUNK: usize = 1 << 20;
+
+/// A hash function that digests a sequence in chunks.
+pub trait Digester {
+    /// The digest.
+    type Output;
+
+    /// Add the next chunk of uppercased bases.
+    fn update(&mut self, bases: &[u8]);
+
+    /// Return the digest of all bases.
+    fn finish(self) -> Self::Output;
+}
+
+/// Calculates both digests in a single pass.
+impl<A: Digester, B: Digester> Digester for (A, B) {
+    type Output = (A::Output, B::Output);
+
+    fn update(&mut self, bases: &[u8]) {
+        self.0.update(bases);
+        self.1.update(bases);
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.0.finish(), self.1.finish())
+    }
+}
+
+impl IndexedFasta {
+    /// Digest the uppercased bases of chromosome tid with `digester`.
+    ///
+    /// Returns the digest if successful, `Error::UnknownTid` for an unknown tid and
+    /// `Error::Truncated` if the chromosome extends beyond the end of the fasta data.
+    pub fn digest<D: Digester>(&self, tid: usize, mut digester: D) -> Result<D::Output> {
+        let chunk = self.limits().max_view_len.unwrap_or(CHUNK).clamp(1, CHUNK);
+        let mut buf = Vec::with_capacity(chunk);
+        for (_, _, view) in self.windows(tid, chunk, chunk)? {
+            buf.clear();
+            buf.extend(view.bases().map(u8::to_ascii_uppercase));
+            digester.update(&buf);
+        }
+        Ok(digester.finish())
+    }
+
+    /// Digest all chromosomes, with a new digester from `new` for every chromosome.
+    ///
+    /// Returns the digests in index order if successful, Error otherwise.
+    pub fn digest_all<D, F>(&self, mut new: F) -> Result<Vec<D::Output>>
+    where
+        D: Digester,
+        F: FnMut() -> D,
+    {
+        (0..self.fai().names().len())
+            .map(|tid| self.digest(tid, new()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limits;
+    use crate::Error;
+
+    #[derive(Default)]
+    struct Collect(Vec<u8>, usize);
+
+    impl Digester for Collect {
+        type Output = (Vec<u8>, usize);
+
+        fn update(&mut self, bases: &[u8]) {
+            self.0.extend_from_slice(bases);
+            self.1 += 1;
+        }
+
+        fn finish(self) -> Self::Output {
+            (self.0, self.1)
+        }
+    }
+
+    #[test]
+    fn digest() {
+        let fa = IndexedFasta::from_file("test/genome.fa")
+            .unwrap()
+            .with_limits(Limits::new().max_view_len(30));
+        let (bases, chunks) = fa.digest(2, Collect::default()).unwrap();
+        let expected = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert_eq!(
+            bases,
+            expected.view_tid(2).unwrap().to_string().into_bytes()
+        );
+        assert_eq!(chunks, 4);
+        let ((a, _), (b, _)) = fa
+            .digest(0, (Collect::default(), Collect::default()))
+            .unwrap();
+        assert_eq!(a, b"AAAAAAAAAA");
+        assert_eq!(a, b);
+        assert!(fa.digest(3, Collect::default()).is_err());
+
+        let data = std::fs::read("test/genome.fa").unwrap();
+        let truncated =
+            IndexedFasta::from_source(data[..data.len() - 60].to_vec(), fa.fai().clone());
+        assert!(matches!(
+            truncated.digest(2, Collect::default()),
+            Err(Error::Truncated { tid: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+//! Base composition drift between two builds of a reference.
+//!
+//! Minor releases of a reference often patch or mask a few regions while keeping the contig
+//! names. `Drift` compares the GC and N fractions of every window of the contigs that both
+//! references share and reports the windows where a difference exceeds a threshold. Contigs that
+//! differ in length are compared up to the length of the shorter contig.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::drift::Drift;
+//! let old = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let new = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let flagged = Drift::new(50).max_gc_diff(0.05).compare(&old, &new).expect("Cannot compare");
+//! for window in &flagged {
+//!     println!("{}", window);
+//! }
+//! assert!(flagged.is_empty());
+//! ```
+
+use std::fmt;
+
+use crate::{BaseCounts, Error, IndexedFasta, Result};
+
+/// Thresholds for the comparison of two references.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drift {
+    window: usize,
+    max_gc_diff: f64,
+    max_n_diff: f64,
+}
+
+/// A window with a composition difference between two references. Fractions are NaN when a
+/// window has no bases to calculate them from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftWindow {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    pub gc_a: f64,
+    pub gc_b: f64,
+    pub n_a: f64,
+    pub n_b: f64,
+}
+
+impl DriftWindow {
+    fn from_counts(name: &str, start: usize, end: usize, a: &BaseCounts, b: &BaseCounts) -> Self {
+        DriftWindow {
+            name: name.to_owned(),
+            start,
+            end,
+            gc_a: a.gc_fraction(),
+            gc_b: b.gc_fraction(),
+            n_a: a.n_fraction(),
+            n_b: b.n_fraction(),
+        }
+    }
+
+    /// The absolute difference of the GC fractions, NaN if one of them is NaN.
+    pub fn gc_diff(&self) -> f64 {
+        (self.gc_a - self.gc_b).abs()
+    }
+
+    /// The absolute difference of the N fractions.
+    pub fn n_diff(&self) -> f64 {
+        (self.n_a - self.n_b).abs()
+    }
+}
+
+/// Formats the window as a tab separated line with zero-based coordinates, like a BED file.
+impl fmt::Display for DriftWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}",
+            self.name, self.start, self.end, self.gc_a, self.gc_b, self.n_a, self.n_b
+        )
+    }
+}
+
+impl Drift {
+    /// Compare windows of `window` bases. By default a window is flagged for a GC or N
+    /// difference of more than 0.01.
+    pub fn new(window: usize) -> Self {
+        Drift {
+            window,
+            max_gc_diff: 0.01,
+            max_n_diff: 0.01,
+        }
+    }
+
+    /// Set the largest GC fraction difference that is not flagged.
+    pub fn max_gc_diff(mut self, diff: f64) -> Self {
+        self.max_gc_diff = diff;
+        self
+    }
+
+    /// Set the largest N fraction difference that is not flagged.
+    pub fn max_n_diff(mut self, diff: f64) -> Self {
+        self.max_n_diff = diff;
+        self
+    }
+
+    /// Compare the contigs that are in both `a` and `b`, in the order of `a`.
+    ///
+    /// Returns the flagged windows if successful, `Error::InvalidArgument` for a window size of
+    /// 0.
+    pub fn compare(&self, a: &IndexedFasta, b: &IndexedFasta) -> Result<Vec<DriftWindow>> {
+        if self.window == 0 {
+            return Err(Error::InvalidArgument(
+                "Window size should be at least 1".to_owned(),
+            ));
+        }
+
+        let mut flagged = Vec::new();
+        for (tid_a, name) in a.fai().names().into_iter().enumerate() {
+            let Some(tid_b) = b.fai().tid(name) else {
+                continue;
+            };
+            let len = a.fai().size(tid_a)?.min(b.fai().size(tid_b)?);
+            for start in (0..len).step_by(self.window) {
+                let end = len.min(start + self.window);
+                let counts_a = a.view(tid_a, start, end)?.count_bases();
+                let counts_b = b.view(tid_b, start, end)?.count_bases();
+                let window = DriftWindow::from_counts(name, start, end, &counts_a, &counts_b);
+                if window.gc_diff() > self.max_gc_diff || window.n_diff() > self.max_n_diff {
+                    flagged.push(window);
+                }
+            }
+        }
+        Ok(flagged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn compare() {
+        let dir = std::env::temp_dir().join(format!("faimm-drift-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.fa");
+        let b = dir.join("b.fa");
+        fs::write(&a, ">chr1\nACGTACGTAC\nGTACGTACGT\n>chr2\nAAAA\n").unwrap();
+        fs::write(&b, ">chr1\nACGTACGTAC\nGTNNNNACGT\n>chr3\nAAAA\n").unwrap();
+        crate::Fai::create_and_write(&a).unwrap();
+        crate::Fai::create_and_write(&b).unwrap();
+        let fa = IndexedFasta::from_file(&a).unwrap();
+        let fb = IndexedFasta::from_file(&b).unwrap();
+
+        let flagged = Drift::new(5).compare(&fa, &fb).unwrap();
+        assert_eq!(flagged.len(), 2);
+        assert_eq!((flagged[0].start, flagged[0].end), (10, 15));
+        assert_eq!(flagged[0].n_b, 0.6);
+        assert!(flagged[0].gc_diff() > 0.05);
+        assert_eq!(
+            flagged[1].to_string(),
+            "chr1\t15\t20\t0.4000\t0.5000\t0.0000\t0.2000"
+        );
+        assert!(Drift::new(5)
+            .max_gc_diff(1.0)
+            .max_n_diff(1.0)
+            .compare(&fa, &fb)
+            .unwrap()
+            .is_empty());
+        assert!(Drift::new(0).compare(&fa, &fb).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
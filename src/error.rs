@@ -0,0 +1,212 @@
+//! The error type of this crate.
+//!
+//! `Error` tells apart the ways a lookup can fail, so callers can handle an unknown chromosome
+//! differently from an interval that is out of range or a failing disk. It converts to and from
+//! `io::Error`, which keeps `?` working in functions that return `io::Result`. The converted
+//! `io::Error` has a matching `ErrorKind` and wraps the original `Error`.
+//!
+//! # Example
+//! ```
+//! use faimm::{Error, IndexedFasta};
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! match fa.view_by_name("chrUn", 0, 10) {
+//!     Err(Error::UnknownName(name)) => println!("{} is not in the index", name),
+//!     Err(e) => panic!("Unexpected error {}", e),
+//!     Ok(v) => println!("{}", v),
+//! }
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// The errors returned by this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// Memory mapping a file failed.
+    Mmap(io::Error),
+    /// The `.fai` index of a fasta file doesn't exist.
+    MissingIndex(PathBuf),
+    /// A line of a `.fai` file could not be parsed. `line` is one-based.
+    MalformedFai { line: usize, message: String },
+    /// The fasta file could not be indexed. `offset` is the byte offset of the problem.
+    MalformedFasta { offset: usize, message: String },
+    /// Another input file, like a BED or JASPAR file, could not be parsed. `line` is one-based,
+    /// `None` for binary files like `.2bit`.
+    Malformed {
+        format: &'static str,
+        line: Option<usize>,
+        message: String,
+    },
+    /// There is no chromosome with this index (tid).
+    UnknownTid(usize),
+    /// There is no chromosome with this name.
+    UnknownName(String),
+    /// The interval `start..stop` doesn't fit on chromosome `tid` of length `len`.
+    OutOfRange {
+        tid: usize,
+        start: usize,
+        stop: usize,
+        len: usize,
+    },
+    /// The start of an interval is larger than its stop.
+    InvalidInterval { start: usize, stop: usize },
+    /// The fasta data of `size` bytes ends before byte `offset` of chromosome `tid`. The fasta
+    /// file is shorter than its index expects, for instance because it was truncated.
+    Truncated {
+        tid: usize,
+        offset: usize,
+        size: usize,
+    },
+    /// The fasta data of `size` bytes ends before byte `expected`, the end of the record that
+    /// ends last in the index, so the index is out of date.
+    StaleIndex { expected: usize, size: usize },
+    /// The fasta file was modified after its index at this path was written, so the index is
+    /// out of date.
+    OutdatedIndex(PathBuf),
+    /// A region string could not be parsed.
+    InvalidRegion(String),
+    /// An argument, like a window size, has an invalid value.
+    InvalidArgument(String),
+    /// A view of `len` bases is longer than the configured maximum.
+    ViewTooLong { len: usize, max: usize },
+    /// A batch of `size` regions is larger than the configured maximum.
+    BatchTooLarge { size: usize, max: usize },
+    /// Strict reading found a byte that is not a valid base or symbol at base `position`.
+    InvalidBase { position: usize, base: u8 },
+    /// A quality character is outside the range of its phred encoding.
+    InvalidQuality(u8),
+}
+
+/// Result type with the error of this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Mmap(e) => write!(f, "Cannot map file: {}", e),
+            Error::MissingIndex(path) => write!(f, "Missing index file {}", path.display()),
+            Error::MalformedFai { line, message } => {
+                write!(f, "{} on line {} of the .fai file", message, line)
+            }
+            Error::MalformedFasta { offset, message } => {
+                write!(f, "{} at byte {} of the fasta file", message, offset)
+            }
+            Error::Malformed {
+                format,
+                line: Some(line),
+                message,
+            } => write!(f, "{} on line {} of the {} file", message, line, format),
+            Error::Malformed {
+                format,
+                line: None,
+                message,
+            } => write!(f, "{} in the {} file", message, format),
+            Error::UnknownTid(tid) => write!(f, "Chromosome tid {} was out of bounds", tid),
+            Error::UnknownName(name) => write!(f, "Unknown chromosome {}", name),
+            Error::OutOfRange {
+                tid,
+                start,
+                stop,
+                len,
+            } => write!(
+                f,
+                "Interval {}-{} out of bounds for chromosome tid {} with length {}",
+                start, stop, tid, len
+            ),
+            Error::InvalidInterval { start, stop } => {
+                write!(f, "Interval start {} is larger than stop {}", start, stop)
+            }
+            Error::Truncated { tid, offset, size } => write!(
+                f,
+                "Chromosome tid {} extends to byte {} beyond the end of the fasta data of {} bytes",
+                tid, offset, size
+            ),
+            Error::StaleIndex { expected, size } => write!(
+                f,
+                "The index is out of date, it expects {} bytes of fasta data instead of {}",
+                expected, size
+            ),
+            Error::OutdatedIndex(path) => write!(
+                f,
+                "The index {} is out of date, the fasta file was modified after it",
+                path.display()
+            ),
+            Error::InvalidRegion(msg) | Error::InvalidArgument(msg) => write!(f, "{}", msg),
+            Error::ViewTooLong { len, max } => {
+                write!(f, "View of {} bases exceeds the limit of {}", len, max)
+            }
+            Error::BatchTooLarge { size, max } => {
+                write!(f, "Batch of {} regions exceeds the limit of {}", size, max)
+            }
+            Error::InvalidBase { position, base } => {
+                write!(
+                    f,
+                    "Invalid base {:?} at position {}",
+                    *base as char, position
+                )
+            }
+            Error::InvalidQuality(q) => write!(f, "Invalid quality character {:?}", *q as char),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) | Error::Mmap(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e {
+            Error::Io(e) | Error::Mmap(e) => return e,
+            Error::MissingIndex(_) | Error::UnknownTid(_) | Error::UnknownName(_) => {
+                io::ErrorKind::NotFound
+            }
+            Error::MalformedFai { .. }
+            | Error::MalformedFasta { .. }
+            | Error::Malformed { .. }
+            | Error::InvalidBase { .. }
+            | Error::InvalidQuality(_)
+            | Error::Truncated { .. }
+            | Error::StaleIndex { .. }
+            | Error::OutdatedIndex(_) => io::ErrorKind::InvalidData,
+            Error::OutOfRange { .. }
+            | Error::InvalidInterval { .. }
+            | Error::InvalidRegion(_)
+            | Error::InvalidArgument(_) => io::ErrorKind::InvalidInput,
+            Error::ViewTooLong { .. } | Error::BatchTooLarge { .. } => io::ErrorKind::InvalidInput,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_conversion() {
+        let e: io::Error = Error::UnknownName("chr1".to_owned()).into();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        let inner = e.get_ref().and_then(|e| e.downcast_ref::<Error>());
+        assert!(matches!(inner, Some(Error::UnknownName(n)) if n == "chr1"));
+
+        let e: io::Error = Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "eof")).into();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(matches!(Error::from(e), Error::Io(_)));
+    }
+}
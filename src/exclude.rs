@@ -0,0 +1,293 @@
+//! Sequence access that leaves out excluded regions, like the ENCODE blacklist or assembly gaps.
+//!
+//! An `ExcludingFasta` wraps an `IndexedFasta` with a `RegionSet` of excluded intervals. All
+//! fetch, window and sampling operations skip or trim the excluded intervals and report what was
+//! left out, so every step of a pipeline handles the exclusions in the same way.
+//!
+//! `ExcludingFasta::without_gaps` excludes the runs of N of the fasta file itself. Its windows
+//! are the callable windows that binning pipelines need: `WindowPolicy::Skip` drops windows
+//! that overlap a gap and `WindowPolicy::Trim` splits them around the gap.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::exclude::ExcludingFasta;
+//! use faimm::region::RegionSet;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let mut blacklist = RegionSet::new(fa.fai());
+//! blacklist.insert(2, 20, 30).unwrap();
+//!
+//! let ex = ExcludingFasta::new(&fa, &blacklist);
+//! let trimmed = ex.view(2, 0, 50).unwrap();
+//! assert_eq!(trimmed.excluded, vec![(20, 30)]);
+//! assert_eq!(trimmed.to_string(), "AAAAAAAAAAAAAAAAAAAACCCCCCCCCCCCCCCCCCCC");
+//! ```
+
+use std::fmt;
+
+use crate::region::RegionSet;
+use crate::sample::Reservoir;
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// What to do with windows that overlap an excluded interval.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WindowPolicy {
+    /// Leave out windows that overlap an excluded interval.
+    Skip,
+    /// Keep the windows, but remove the excluded parts.
+    Trim,
+}
+
+/// The parts of a requested region that are not excluded.
+pub struct Trimmed<'a> {
+    /// Start of the requested region.
+    pub start: usize,
+    /// End of the requested region.
+    pub stop: usize,
+    /// The remaining pieces as `(start, stop, FastaView)`.
+    pub pieces: Vec<(usize, usize, FastaView<'a>)>,
+    /// The excluded intervals that were removed, clipped to the requested region.
+    pub excluded: Vec<(usize, usize)>,
+}
+
+impl<'a> Trimmed<'a> {
+    /// Returns true if no part of the requested region was excluded.
+    pub fn is_complete(&self) -> bool {
+        self.excluded.is_empty()
+    }
+
+    /// Iterator over the bases of all remaining pieces.
+    pub fn bases(&self) -> impl Iterator<Item = &'a u8> + '_ {
+        self.pieces.iter().flat_map(|(_, _, v)| v.bases())
+    }
+}
+
+/// Writes the remaining sequence data of all pieces.
+impl<'a> fmt::Display for Trimmed<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (_, _, view) in &self.pieces {
+            view.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// An `IndexedFasta` that leaves out a set of excluded intervals.
+pub struct ExcludingFasta<'a> {
+    fasta: &'a IndexedFasta,
+    excluded: RegionSet,
+}
+
+impl<'a> ExcludingFasta<'a> {
+    /// Wrap `fasta` and exclude all intervals in `excluded`. The set should be created from the
+    /// index of the same fasta file.
+    pub fn new(fasta: &'a IndexedFasta, excluded: &RegionSet) -> Self {
+        let mut excluded = excluded.clone();
+        excluded.merge();
+        ExcludingFasta { fasta, excluded }
+    }
+
+    /// Wrap `fasta` and exclude all runs of at least `min_gap` N bases (see
+    /// `IndexedFasta::gaps`). The complete fasta file is scanned for gaps.
+    pub fn without_gaps(fasta: &'a IndexedFasta, min_gap: usize) -> Result<Self> {
+        let mut excluded = RegionSet::new(fasta.fai());
+        for tid in 0..fasta.fai().names().len() {
+            for (start, end) in fasta.gaps(tid, min_gap)? {
+                excluded.insert(tid, start, end)?;
+            }
+        }
+        Ok(ExcludingFasta { fasta, excluded })
+    }
+
+    /// Return the merged set of excluded intervals.
+    pub fn excluded(&self) -> &RegionSet {
+        &self.excluded
+    }
+
+    /// Return a reference to the wrapped `IndexedFasta`.
+    pub fn fasta(&self) -> &'a IndexedFasta {
+        self.fasta
+    }
+
+    /// Return the parts of `start..stop` on chromosome `tid` that are not excluded, together
+    /// with the excluded intervals that were trimmed.
+    pub fn view(&self, tid: usize, start: usize, stop: usize) -> Result<Trimmed<'a>> {
+        // validates the coordinates
+        self.fasta.view(tid, start, stop)?;
+
+        let mut pieces = Vec::new();
+        let mut excluded = Vec::new();
+        let mut pos = start;
+        for &(s, e) in self.excluded.overlapping(tid, start, stop) {
+            let (s, e) = (s.max(start), e.min(stop));
+            if s > pos {
+                pieces.push((pos, s, self.fasta.view(tid, pos, s)?));
+            }
+            excluded.push((s, e));
+            pos = e;
+        }
+        if pos < stop {
+            pieces.push((pos, stop, self.fasta.view(tid, pos, stop)?));
+        }
+
+        Ok(Trimmed {
+            start,
+            stop,
+            pieces,
+            excluded,
+        })
+    }
+
+    /// Iterate over windows of `size` bases on chromosome tid, starting every `step` bases (see
+    /// `IndexedFasta::windows`). Windows that overlap an excluded interval are skipped or
+    /// trimmed depending on `policy`. Windows that are completely excluded are always skipped.
+    pub fn windows(
+        &self,
+        tid: usize,
+        size: usize,
+        step: usize,
+        policy: WindowPolicy,
+    ) -> Result<impl Iterator<Item = Trimmed<'a>> + '_> {
+        let windows = self.fasta.windows(tid, size, step)?;
+        Ok(windows.filter_map(move |(start, stop, _)| {
+            let trimmed = self.view(tid, start, stop).ok()?;
+            let keep = match policy {
+                WindowPolicy::Skip => trimmed.is_complete(),
+                WindowPolicy::Trim => !trimmed.pieces.is_empty(),
+            };
+            if keep {
+                Some(trimmed)
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Draw a seeded, uniform random sample of `n` positions that are not excluded (see
+    /// `IndexedFasta::sample_positions`).
+    ///
+    /// Returns `(tid, position)` tuples sorted by tid and position.
+    pub fn sample_positions(&self, n: usize, seed: u64) -> Vec<(usize, usize)> {
+        self.sample_starts(1, n, seed)
+    }
+
+    /// Draw a seeded, uniform random sample of `n` k-mers that do not overlap an excluded
+    /// interval (see `IndexedFasta::sample_kmers`).
+    ///
+    /// Returns `(tid, position, FastaView)` tuples sorted by tid and position.
+    pub fn sample_kmers(
+        &self,
+        k: usize,
+        n: usize,
+        seed: u64,
+    ) -> Result<Vec<(usize, usize, FastaView<'a>)>> {
+        if k == 0 {
+            return Err(Error::InvalidArgument(
+                "k-mer size should be at least 1".to_owned(),
+            ));
+        }
+        self.sample_starts(k, n, seed)
+            .into_iter()
+            .map(|(tid, pos)| Ok((tid, pos, self.fasta.view(tid, pos, pos + k)?)))
+            .collect()
+    }
+
+    fn sample_starts(&self, k: usize, n: usize, seed: u64) -> Vec<(usize, usize)> {
+        let mut reservoir = Reservoir::new(n, seed);
+        for (tid, start, end) in self.excluded.complement().iter() {
+            if end - start >= k {
+                reservoir.push_range((end - start - k + 1) as u64, |i| (tid, start + i as usize));
+            }
+        }
+        let mut sample = reservoir.into_vec();
+        sample.sort_unstable();
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fai;
+
+    fn blacklist(fa: &IndexedFasta) -> RegionSet {
+        let mut r = RegionSet::new(fa.fai());
+        r.insert(2, 10, 20).unwrap();
+        r.insert(2, 15, 30).unwrap();
+        r.insert(2, 60, 100).unwrap();
+        r.insert(0, 0, 10).unwrap();
+        r
+    }
+
+    #[test]
+    fn view() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let ex = ExcludingFasta::new(&fa, &blacklist(&fa));
+        let t = ex.view(2, 5, 70).unwrap();
+        assert_eq!(t.excluded, vec![(10, 30), (60, 70)]);
+        let pieces: Vec<_> = t.pieces.iter().map(|p| (p.0, p.1)).collect();
+        assert_eq!(pieces, vec![(5, 10), (30, 60)]);
+        assert_eq!(t.bases().count(), 35);
+        assert!(ex.view(1, 0, 100).unwrap().is_complete());
+        assert!(ex.view(0, 0, 10).unwrap().pieces.is_empty());
+        assert!(ex.view(0, 0, 11).is_err());
+    }
+
+    #[test]
+    fn windows() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let ex = ExcludingFasta::new(&fa, &blacklist(&fa));
+        let skip: Vec<_> = ex
+            .windows(2, 10, 10, WindowPolicy::Skip)
+            .unwrap()
+            .map(|t| t.start)
+            .collect();
+        assert_eq!(skip, vec![0, 30, 40, 50]);
+        let trim: Vec<_> = ex
+            .windows(2, 20, 20, WindowPolicy::Trim)
+            .unwrap()
+            .map(|t| (t.start, t.to_string().len()))
+            .collect();
+        assert_eq!(trim, vec![(0, 10), (20, 10), (40, 20)]);
+    }
+
+    #[test]
+    fn gaps() {
+        let dir = std::env::temp_dir().join(format!("faimm-gaps-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gaps.fa");
+        std::fs::write(&path, ">gaps\nACGTACGTAC\nGTNNNNNNAC\nGTACGTACGN\n").unwrap();
+        Fai::create_and_write(&path).unwrap();
+        let fa = IndexedFasta::from_file(&path).unwrap();
+        let ex = ExcludingFasta::without_gaps(&fa, 3).unwrap();
+        assert_eq!(ex.excluded().intervals(0), &[(12, 18)]);
+        let skip: Vec<_> = ex
+            .windows(0, 10, 5, WindowPolicy::Skip)
+            .unwrap()
+            .map(|t| t.start)
+            .collect();
+        assert_eq!(skip, vec![0, 20]);
+        let trim: Vec<_> = ex
+            .windows(0, 10, 10, WindowPolicy::Trim)
+            .unwrap()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(trim, vec!["ACGTACGTAC", "GTAC", "GTACGTACGN"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sample() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let ex = ExcludingFasta::new(&fa, &blacklist(&fa));
+        let pos = ex.sample_positions(1000, 1);
+        // 100 on A-100 and 10 + 30 on ACGT-25
+        assert_eq!(pos.len(), 140);
+        assert!(pos
+            .iter()
+            .all(|&(tid, p)| tid == 1 || (tid == 2 && (p < 10 || (30..60).contains(&p)))));
+        let kmers = ex.sample_kmers(20, 1000, 1).unwrap();
+        // 81 on A-100 and 11 on ACGT-25
+        assert_eq!(kmers.len(), 92);
+    }
+}
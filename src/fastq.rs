@@ -0,0 +1,181 @@
+//! Random access to fastq files indexed with `samtools fqidx`.
+//!
+//! The index of a fastq file has a sixth column with the offset of the qualities of every read.
+//! `IndexedFastq` returns the bases of a read as a `FastaView`, so all functions of views can be
+//! used, and the qualities as a `QualityView`. Reads can span multiple lines as long as the
+//! qualities are wrapped the same way as the bases.
+//!
+//! # Example
+//! ```
+//! use faimm::fastq::IndexedFastq;
+//! use faimm::quality::PhredOffset;
+//! let fq = IndexedFastq::from_file("test/reads.fq").expect("Error opening fq");
+//! let tid = fq.fai().tid("r2").expect("Cannot find read in index");
+//! assert_eq!(fq.view(tid, 3, 7).unwrap().to_string(), "TNac");
+//! let quals = fq.quality(tid, 3, 7).unwrap();
+//! assert_eq!(quals.decode(PhredOffset::Sanger).unwrap(), [0, 0, 2, 2]);
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use crate::quality::{self, PhredOffset, QualityStats};
+use crate::{Fai, FastaView, IndexedFasta, Result};
+
+/// An indexed fastq file.
+pub struct IndexedFastq {
+    fasta: IndexedFasta,
+}
+
+impl IndexedFastq {
+    /// Open a fastq file from path `P`, the index is expected at `P.fai`.
+    ///
+    /// Returns an IndexedFastq if successful. `Error::MalformedFai` if the index doesn't have
+    /// the quality offsets of a fastq index.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        IndexedFastq::try_from(IndexedFasta::from_file(path)?)
+    }
+
+    /// The index of the fastq file.
+    pub fn fai(&self) -> &Fai {
+        self.fasta.fai()
+    }
+
+    /// The fastq file as an `IndexedFasta`, to use the functions that work on sequences.
+    pub fn fasta(&self) -> &IndexedFasta {
+        &self.fasta
+    }
+
+    /// The bases of the zero-based, half-open range start-stop of read tid.
+    pub fn view(&self, tid: usize, start: usize, stop: usize) -> Result<FastaView<'_>> {
+        self.fasta.view(tid, start, stop)
+    }
+
+    /// The bases of read tid.
+    pub fn view_tid(&self, tid: usize) -> Result<FastaView<'_>> {
+        self.fasta.view_tid(tid)
+    }
+
+    /// The qualities of the zero-based, half-open range start-stop of read tid.
+    pub fn quality(&self, tid: usize, start: usize, stop: usize) -> Result<QualityView<'_>> {
+        let range = self.fai().quality_offset(tid, start, stop)?;
+        let (start_byte, stop_byte) = self.fasta.check_size(tid, range)?;
+        Ok(QualityView {
+            data: &self.fasta.data()[start_byte..stop_byte],
+            len: stop - start,
+        })
+    }
+
+    /// The qualities of read tid.
+    pub fn quality_tid(&self, tid: usize) -> Result<QualityView<'_>> {
+        self.quality(tid, 0, self.fai().size(tid)?)
+    }
+}
+
+/// Fails with `Error::MalformedFai` if a record of the index has no quality offset.
+impl TryFrom<IndexedFasta> for IndexedFastq {
+    type Error = crate::Error;
+
+    fn try_from(fasta: IndexedFasta) -> Result<Self> {
+        for tid in 0..fasta.fai().names().len() {
+            fasta.fai().quality_offset(tid, 0, 0)?;
+        }
+        Ok(IndexedFastq { fasta })
+    }
+}
+
+/// The encoded qualities of a range of a read.
+pub struct QualityView<'a> {
+    data: &'a [u8],
+    len: usize,
+}
+
+impl<'a> QualityView<'a> {
+    /// The number of qualities in the view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the view has no qualities.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterator over the encoded qualities, without line breaks.
+    pub fn quals(&self) -> impl Iterator<Item = &'a u8> {
+        self.data.iter().filter(|&&q| q != b'\n' && q != b'\r')
+    }
+
+    /// Decode the qualities into phred scores.
+    ///
+    /// Returns `Error::InvalidQuality` for characters that are invalid with `offset`.
+    pub fn decode(&self, offset: PhredOffset) -> Result<Vec<u8>> {
+        self.quals().map(|&q| offset.decode(q)).collect()
+    }
+
+    /// The summary statistics of the qualities, `None` when the view is empty.
+    ///
+    /// Returns `Error::InvalidQuality` for characters that are invalid with `offset`.
+    pub fn stats(&self, offset: PhredOffset) -> Result<Option<QualityStats>> {
+        QualityStats::from_bytes(self.quals(), offset)
+    }
+
+    /// The mean probability that a base call in the view is wrong, NaN when the view is empty.
+    ///
+    /// Returns `Error::InvalidQuality` for characters that are invalid with `offset`.
+    pub fn mean_error_probability(&self, offset: PhredOffset) -> Result<f64> {
+        let phred = self.decode(offset)?;
+        let sum: f64 = phred.iter().map(|&q| quality::error_probability(q)).sum();
+        Ok(sum / phred.len() as f64)
+    }
+}
+
+/// Writes the encoded qualities without line breaks.
+impl<'a> fmt::Display for QualityView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.data.split(|&q| q == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            f.write_str(std::str::from_utf8(line).map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn reads() {
+        let fq = IndexedFastq::from_file("test/reads.fq").unwrap();
+        assert_eq!(fq.fai().names(), ["r1", "r2"]);
+        assert_eq!(fq.view_tid(0).unwrap().to_string(), "ACGTACGTAC");
+        assert_eq!(fq.quality_tid(0).unwrap().to_string(), "IIIIIHHHHH");
+        let quals = fq.quality_tid(1).unwrap();
+        assert_eq!(
+            (quals.len(), quals.to_string()),
+            (9, "!!!!!####".to_owned())
+        );
+        let stats = quals.stats(PhredOffset::Sanger).unwrap().unwrap();
+        assert_eq!((stats.min, stats.max), (0, 2));
+        assert!(fq.quality(0, 5, 5).unwrap().is_empty());
+        assert!(matches!(
+            fq.quality(1, 5, 10),
+            Err(Error::OutOfRange { .. })
+        ));
+
+        let mut fai = Vec::new();
+        fq.fai().write_to(&mut fai).unwrap();
+        assert_eq!(fai, std::fs::read("test/reads.fq.fai").unwrap());
+    }
+
+    #[test]
+    fn fasta_index() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert!(matches!(
+            IndexedFastq::try_from(fa),
+            Err(Error::MalformedFai { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,126 @@
+//! Detection of runs of N, like the gaps in a genome assembly, and of runs of soft-masked
+//! (lowercase) bases, like the repeats masked by RepeatMasker.
+//!
+//! Runs are reported as zero-based, half-open `(start, end)` base coordinates, so line breaks in
+//! the fasta file are already accounted for. Both `N` and `n` are part of a run of N.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! for (start, end) in fa.gaps(2, 100).expect("Unknown chromosome") {
+//!     println!("ACGT-25\t{}\t{}", start, end);
+//! }
+//! ```
+
+use crate::{FastaView, IndexedFasta, Result};
+
+/// Iterator over runs of bases in a `FastaView`, created with `FastaView::n_runs` or
+/// `FastaView::masked_runs`.
+pub struct Runs<'a> {
+    iter: std::slice::Iter<'a, u8>,
+    pos: usize,
+    in_run: fn(u8) -> bool,
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let mut start = None;
+        for &b in &mut self.iter {
+            if b & 192 != 64 {
+                continue;
+            }
+            self.pos += 1;
+            match ((self.in_run)(b), start) {
+                (true, None) => start = Some(self.pos - 1),
+                (false, Some(s)) => return Some((s, self.pos - 1)),
+                _ => {}
+            }
+        }
+        start.map(|s| (s, self.pos))
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Iterator over the runs of N in the current view. The coordinates are relative to the
+    /// start of the view.
+    pub fn n_runs(&self) -> Runs<'a> {
+        Runs {
+            iter: self.0.iter(),
+            pos: 0,
+            in_run: |b| b == b'N' || b == b'n',
+        }
+    }
+
+    /// Iterator over the runs of soft-masked (lowercase) bases in the current view. The
+    /// coordinates are relative to the start of the view.
+    pub fn masked_runs(&self) -> Runs<'a> {
+        Runs {
+            iter: self.0.iter(),
+            pos: 0,
+            in_run: |b| b.is_ascii_lowercase(),
+        }
+    }
+}
+
+impl IndexedFasta {
+    /// Iterator over the runs of at least `min_len` N bases on chromosome tid, as `(start, end)`
+    /// chromosome coordinates.
+    ///
+    /// Returns the iterator if successful, Error if the tid is unknown.
+    pub fn gaps(
+        &self,
+        tid: usize,
+        min_len: usize,
+    ) -> Result<impl Iterator<Item = (usize, usize)> + '_> {
+        Ok(self
+            .view_tid(tid)?
+            .n_runs()
+            .filter(move |(start, end)| end - start >= min_len))
+    }
+
+    /// Iterator over the runs of at least `min_len` soft-masked (lowercase) bases on chromosome
+    /// tid, as `(start, end)` chromosome coordinates.
+    ///
+    /// Returns the iterator if successful, Error if the tid is unknown.
+    pub fn masked_intervals(
+        &self,
+        tid: usize,
+        min_len: usize,
+    ) -> Result<impl Iterator<Item = (usize, usize)> + '_> {
+        Ok(self
+            .view_tid(tid)?
+            .masked_runs()
+            .filter(move |(start, end)| end - start >= min_len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn n_runs() {
+        let runs: Vec<_> = FastaView::new(b"NNAC\nnNNA\nNTN").n_runs().collect();
+        assert_eq!(runs, vec![(0, 2), (4, 7), (8, 9), (10, 11)]);
+        assert_eq!(FastaView::new(b"AC\nGT").n_runs().count(), 0);
+        assert_eq!(FastaView::new(b"").n_runs().count(), 0);
+    }
+
+    #[test]
+    fn masked_runs() {
+        let runs: Vec<_> = FastaView::new(b"acGT\ngNNt\ntt").masked_runs().collect();
+        assert_eq!(runs, vec![(0, 2), (4, 5), (7, 10)]);
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert_eq!(fa.masked_intervals(1, 1).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn gaps() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert_eq!(fa.gaps(2, 1).unwrap().count(), 0);
+        assert!(fa.gaps(3, 1).is_err());
+    }
+}
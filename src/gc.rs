@@ -0,0 +1,238 @@
+//! Sliding window GC tracks.
+//!
+//! `IndexedFasta::gc_windows` calculates the GC fraction of windows along a chromosome in a
+//! single pass. The chromosome is counted in bins of the greatest common divisor of the window
+//! size and step, and every window is the sum of a few bins, so overlapping windows don't count
+//! the same bases again. The GC fraction is calculated over the A, C, G and T bases of a window
+//! and is NaN for a window without any, like a gap of N.
+//!
+//! The windows can be written as a bedGraph or fixedStep wiggle track for a genome browser or
+//! for the GC normalization of coverage data. Windows with a NaN fraction are left out.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::gc::write_bedgraph;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let windows = fa.gc_windows(2, 50, 25).expect("Invalid window");
+//! let mut track = Vec::new();
+//! write_bedgraph(&mut track, "ACGT-25", windows).expect("Cannot write track");
+//! assert!(String::from_utf8(track).unwrap().starts_with("ACGT-25\t0\t50\t0.5\n"));
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use crate::{gc_fraction, Error, IndexedFasta, Result};
+
+/// Iterator over the GC fraction of windows, created with `IndexedFasta::gc_windows`.
+pub struct GcWindows<'a> {
+    fasta: &'a IndexedFasta,
+    tid: usize,
+    len: usize,
+    window: usize,
+    step: usize,
+    bin: usize,
+    pos: usize,
+    /// The counted bins as `(gc, at)`, starting at `bins_start`.
+    bins: VecDeque<(usize, usize)>,
+    bins_start: usize,
+    bins_end: usize,
+    gc: usize,
+    at: usize,
+}
+
+impl<'a> GcWindows<'a> {
+    /// Count the bases in `start..stop` as `(gc, at)`.
+    fn count_bin(&self, start: usize, stop: usize) -> (usize, usize) {
+        let counts = self
+            .fasta
+            .view(self.tid, start, stop)
+            .expect("Bins are checked by IndexedFasta::gc_windows")
+            .count_bases();
+        (counts.c + counts.g, counts.a + counts.t)
+    }
+}
+
+impl<'a> Iterator for GcWindows<'a> {
+    type Item = (usize, usize, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.len {
+            return None;
+        }
+        let start = self.pos;
+        let stop = self.len.min(start + self.window);
+
+        if self.bins_end <= start {
+            // the step is larger than the window, nothing to reuse
+            self.bins.clear();
+            self.bins_start = start;
+            self.bins_end = start;
+            self.gc = 0;
+            self.at = 0;
+        }
+        while self.bins_start < start {
+            let (gc, at) = self.bins.pop_front()?;
+            self.gc -= gc;
+            self.at -= at;
+            self.bins_start += self.bin;
+        }
+        while self.bins_end < stop {
+            let end = self.len.min(self.bins_end + self.bin);
+            let (gc, at) = self.count_bin(self.bins_end, end);
+            self.bins.push_back((gc, at));
+            self.gc += gc;
+            self.at += at;
+            self.bins_end = end;
+        }
+
+        // no more windows after the one that reaches the end of the chromosome
+        self.pos = if stop == self.len {
+            self.len
+        } else {
+            start + self.step
+        };
+        Some((start, stop, gc_fraction(self.gc, self.at)))
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl IndexedFasta {
+    /// Calculate the GC fraction of windows of `size` bases on chromosome tid, starting every
+    /// `step` bases. The last window is truncated at the end of the chromosome, like
+    /// `IndexedFasta::windows`.
+    ///
+    /// Returns an iterator of `(start, stop, gc_fraction)` if successful, Error otherwise.
+    pub fn gc_windows(&self, tid: usize, size: usize, step: usize) -> Result<GcWindows<'_>> {
+        if size == 0 || step == 0 {
+            return Err(Error::InvalidArgument(
+                "Window size and step should be at least 1".to_owned(),
+            ));
+        }
+        let len = self.fai().size(tid)?;
+        self.limits().check_view(size.min(len))?;
+        // check the whole chromosome once, so the views of the bins can't fail
+        self.checked_offset(tid, 0, len)?;
+        Ok(GcWindows {
+            fasta: self,
+            tid,
+            len,
+            window: size,
+            step,
+            bin: gcd(size, step),
+            pos: 0,
+            bins: VecDeque::new(),
+            bins_start: 0,
+            bins_end: 0,
+            gc: 0,
+            at: 0,
+        })
+    }
+}
+
+/// Write `windows` of chromosome `name` as bedGraph lines. Windows with a NaN value are skipped.
+pub fn write_bedgraph<W, I>(mut w: W, name: &str, windows: I) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = (usize, usize, f64)>,
+{
+    for (start, stop, value) in windows {
+        if !value.is_nan() {
+            writeln!(w, "{}\t{}\t{}\t{}", name, start, stop, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `windows` of chromosome `name` as a fixedStep wiggle track with the given `step` and
+/// `span`. A new declaration line is written after windows with a NaN value, which are skipped.
+pub fn write_wiggle<W, I>(
+    mut w: W,
+    name: &str,
+    step: usize,
+    span: usize,
+    windows: I,
+) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = (usize, usize, f64)>,
+{
+    let mut next = None;
+    for (start, _, value) in windows {
+        if value.is_nan() {
+            continue;
+        }
+        if next != Some(start) {
+            writeln!(
+                w,
+                "fixedStep chrom={} start={} step={} span={}",
+                name,
+                start + 1,
+                step,
+                span
+            )?;
+        }
+        writeln!(w, "{}", value)?;
+        next = Some(start + step);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gc_windows() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        for (size, step) in [(30, 20), (10, 10), (7, 15), (100, 3)] {
+            let windows: Vec<_> = fa.gc_windows(2, size, step).unwrap().collect();
+            let expected: Vec<_> = fa
+                .windows(2, size, step)
+                .unwrap()
+                .map(|(start, stop, v)| (start, stop, v.count_bases().gc_fraction()))
+                .collect();
+            assert_eq!(windows, expected);
+        }
+        assert!(fa.gc_windows(2, 0, 1).is_err());
+        assert!(fa.gc_windows(3, 1, 1).is_err());
+    }
+
+    #[test]
+    fn tracks() {
+        let windows = vec![
+            (0, 10, 0.5),
+            (10, 20, f64::NAN),
+            (20, 30, 0.25),
+            (30, 35, 1.0),
+        ];
+        let mut bedgraph = Vec::new();
+        write_bedgraph(&mut bedgraph, "chr1", windows.clone()).unwrap();
+        assert_eq!(
+            String::from_utf8(bedgraph).unwrap(),
+            "chr1\t0\t10\t0.5\nchr1\t20\t30\t0.25\nchr1\t30\t35\t1\n"
+        );
+        let mut wiggle = Vec::new();
+        write_wiggle(&mut wiggle, "chr1", 10, 10, windows).unwrap();
+        assert_eq!(
+            String::from_utf8(wiggle).unwrap(),
+            [
+                "fixedStep chrom=chr1 start=1 step=10 span=10",
+                "0.5",
+                "fixedStep chrom=chr1 start=21 step=10 span=10",
+                "0.25",
+                "1",
+                ""
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Templates for the fasta headers of extracted subsequences.
+//!
+//! A `HeaderTemplate` is a string with placeholders that are filled in for every extracted
+//! region: `{name}`, `{start}`, `{end}`, `{len}` and `{strand}` (`+` or `-`). Use `{{` and `}}`
+//! for literal braces. Coordinates are one-based and inclusive by default, like samtools
+//! regions, and can be switched to zero-based, half-open coordinates. The default template is
+//! `{name}:{start}-{end}`.
+//!
+//! # Example
+//! ```
+//! use faimm::header::HeaderTemplate;
+//! use faimm::strand::Strand;
+//! let template: HeaderTemplate = "sample1_{name}:{start}-{end}({strand})".parse().unwrap();
+//! assert_eq!(template.render("chr1", 99, 200, Strand::Reverse), "sample1_chr1:100-200(-)");
+//! let zero_based = template.one_based(false);
+//! assert_eq!(zero_based.render("chr1", 99, 200, Strand::Forward), "sample1_chr1:99-200(+)");
+//! ```
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::strand::Strand;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Name,
+    Start,
+    End,
+    Len,
+    Strand,
+}
+
+/// A template for the header of an extracted region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderTemplate {
+    parts: Vec<Part>,
+    one_based: bool,
+}
+
+impl Default for HeaderTemplate {
+    fn default() -> Self {
+        HeaderTemplate {
+            parts: vec![
+                Part::Name,
+                Part::Literal(":".to_owned()),
+                Part::Start,
+                Part::Literal("-".to_owned()),
+                Part::End,
+            ],
+            one_based: true,
+        }
+    }
+}
+
+impl FromStr for HeaderTemplate {
+    type Err = Error;
+
+    /// Parse a template. Unknown placeholders and unmatched braces are an error.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |msg: &str| Error::InvalidArgument(format!("{} in template {:?}", msg, s));
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = s;
+        while let Some(i) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..i]);
+            rest = &rest[i..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let end = match (rest.starts_with('{'), rest.find('}')) {
+                (true, Some(end)) => end,
+                _ => return Err(invalid("Unmatched brace")),
+            };
+            let part = match &rest[1..end] {
+                "name" => Part::Name,
+                "start" => Part::Start,
+                "end" => Part::End,
+                "len" => Part::Len,
+                "strand" => Part::Strand,
+                _ => return Err(invalid("Unknown placeholder")),
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+            rest = &rest[end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(HeaderTemplate {
+            parts,
+            one_based: true,
+        })
+    }
+}
+
+impl HeaderTemplate {
+    /// Create the default template `{name}:{start}-{end}` with one-based coordinates.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Choose one-based, inclusive (`true`, the default) or zero-based, half-open (`false`)
+    /// coordinates for `{start}`.
+    pub fn one_based(mut self, one_based: bool) -> Self {
+        self.one_based = one_based;
+        self
+    }
+
+    /// Fill in the template for the zero-based, half-open region `start..end` of chromosome
+    /// `name` on `strand`. The result doesn't include the leading `>`.
+    pub fn render(&self, name: &str, start: usize, end: usize, strand: Strand) -> String {
+        let mut header = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => header.push_str(s),
+                Part::Name => header.push_str(name),
+                Part::Start => write!(header, "{}", start + self.one_based as usize).unwrap(),
+                Part::End => write!(header, "{}", end).unwrap(),
+                Part::Len => write!(header, "{}", end - start).unwrap(),
+                Part::Strand => write!(header, "{}", strand).unwrap(),
+            }
+        }
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let t = HeaderTemplate::new();
+        assert_eq!(t.render("chr1", 0, 10, Strand::Forward), "chr1:1-10");
+        let t: HeaderTemplate = "{{{name}}} len={len}".parse().unwrap();
+        assert_eq!(t.render("chr2", 5, 10, Strand::Forward), "{chr2} len=5");
+        let t: HeaderTemplate = "plain".parse().unwrap();
+        assert_eq!(t.render("chr2", 5, 10, Strand::Forward), "plain");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("{nam}".parse::<HeaderTemplate>().is_err());
+        assert!("{name".parse::<HeaderTemplate>().is_err());
+        assert!("name}".parse::<HeaderTemplate>().is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Several derived files from a single pass over a reference.
+//!
+//! A pipeline often needs a few files derived from the same reference: the sequences of a set
+//! of regions, a GC track and a sequence dictionary. Producing them one after the other reads
+//! the genome several times. A `Job` lists the outputs and writes them in a single pass over the
+//! chromosomes: all outputs are updated for a chromosome before the next chromosome is read, so
+//! the bases are read from disk once and are still in the page cache for the other outputs.
+//!
+//! Region sequences are written as FASTA records in the order of the chromosomes in the index,
+//! and in the given order within a chromosome. All regions are validated before anything is
+//! written.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::header::HeaderTemplate;
+//! use faimm::job::Job;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let mut fasta = Vec::new();
+//! let mut gc = Vec::new();
+//! Job::new()
+//!     .regions(vec![(2, 20, 30), (0, 0, 5)], HeaderTemplate::new(), &mut fasta)
+//!     .gc_bedgraph(50, 50, &mut gc)
+//!     .run(&fa)
+//!     .expect("Job failed");
+//! let fasta = String::from_utf8(fasta).unwrap();
+//! assert_eq!(fasta, ">A-10:1-5\nAAAAA\n>ACGT-25:21-30\nAAAAACCCCC\n");
+//! assert!(String::from_utf8(gc).unwrap().starts_with("A-10\t0\t10\t0\n"));
+//! ```
+
+use std::io::Write;
+
+use crate::gc::write_bedgraph;
+use crate::header::HeaderTemplate;
+use crate::strand::Strand;
+use crate::writer::FastaWriter;
+use crate::{Error, IndexedFasta, Result};
+
+/// An output of a job.
+enum Output<'w> {
+    Regions {
+        regions: Vec<(usize, usize, usize)>,
+        template: HeaderTemplate,
+        w: Box<dyn Write + 'w>,
+    },
+    GcBedGraph {
+        size: usize,
+        step: usize,
+        w: Box<dyn Write + 'w>,
+    },
+    #[cfg(feature = "checksum")]
+    Dict { w: Box<dyn Write + 'w> },
+}
+
+/// A list of outputs that are written in a single pass over a reference.
+pub struct Job<'w> {
+    outputs: Vec<Output<'w>>,
+    line_width: usize,
+}
+
+impl<'w> Default for Job<'w> {
+    fn default() -> Self {
+        Job::new()
+    }
+}
+
+impl<'w> Job<'w> {
+    /// Create a job without outputs. FASTA records are written with 60 bases per line.
+    pub fn new() -> Self {
+        Job {
+            outputs: Vec::new(),
+            line_width: 60,
+        }
+    }
+
+    /// Set the number of bases per line of the FASTA outputs.
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.line_width = width;
+        self
+    }
+
+    /// Write the sequences of `regions`, given as `(tid, start, stop)`, as FASTA records to `w`,
+    /// with headers rendered from `template`.
+    pub fn regions<W: Write + 'w>(
+        mut self,
+        regions: Vec<(usize, usize, usize)>,
+        template: HeaderTemplate,
+        w: W,
+    ) -> Self {
+        self.outputs.push(Output::Regions {
+            regions,
+            template,
+            w: Box::new(w),
+        });
+        self
+    }
+
+    /// Write the GC fraction of windows of `size` bases, starting every `step` bases, as a
+    /// bedGraph track to `w`, see `IndexedFasta::gc_windows`.
+    pub fn gc_bedgraph<W: Write + 'w>(mut self, size: usize, step: usize, w: W) -> Self {
+        self.outputs.push(Output::GcBedGraph {
+            size,
+            step,
+            w: Box::new(w),
+        });
+        self
+    }
+
+    /// Write the sequence dictionary to `w`, see `dict::write_dict` (feature `checksum`).
+    #[cfg(feature = "checksum")]
+    pub fn dict<W: Write + 'w>(mut self, w: W) -> Self {
+        self.outputs.push(Output::Dict { w: Box::new(w) });
+        self
+    }
+
+    /// Write all outputs in a single pass over `fasta`.
+    ///
+    /// Returns `Ok` if successful, `Error::InvalidArgument` for a line width, window size or
+    /// step of 0, `Error::OutOfRange` or `Error::UnknownTid` for an invalid region, Error
+    /// otherwise.
+    pub fn run(mut self, fasta: &IndexedFasta) -> Result<()> {
+        if self.line_width == 0 {
+            return Err(Error::InvalidArgument(
+                "Line width should be at least 1".to_owned(),
+            ));
+        }
+        for output in &mut self.outputs {
+            match output {
+                Output::Regions { regions, .. } => {
+                    for &(tid, start, stop) in regions.iter() {
+                        fasta.fai().offset(tid, start, stop)?;
+                    }
+                    // stable, so the order within a chromosome is kept
+                    regions.sort_by_key(|r| r.0);
+                }
+                Output::GcBedGraph { size, step, .. } => {
+                    if *size == 0 || *step == 0 {
+                        return Err(Error::InvalidArgument(
+                            "Window size and step should be at least 1".to_owned(),
+                        ));
+                    }
+                }
+                #[cfg(feature = "checksum")]
+                Output::Dict { w } => crate::dict::write_header(w)?,
+            }
+        }
+
+        // the next region of every region output
+        let mut next = vec![0; self.outputs.len()];
+        for tid in 0..fasta.fai().names().len() {
+            let name = fasta.fai().name(tid)?;
+            for (output, next) in self.outputs.iter_mut().zip(next.iter_mut()) {
+                match output {
+                    Output::Regions {
+                        regions,
+                        template,
+                        w,
+                    } => {
+                        while let Some(&(_, start, stop)) =
+                            regions.get(*next).filter(|r| r.0 == tid)
+                        {
+                            let header = template.render(name, start, stop, Strand::Forward);
+                            let view = fasta.view(tid, start, stop)?;
+                            FastaWriter::new(&mut *w)
+                                .line_width(self.line_width)
+                                .write_view(&header, &view)?;
+                            *next += 1;
+                        }
+                    }
+                    Output::GcBedGraph { size, step, w } => {
+                        write_bedgraph(w, name, fasta.gc_windows(tid, *size, *step)?)?;
+                    }
+                    #[cfg(feature = "checksum")]
+                    Output::Dict { w } => crate::dict::write_sequence(w, fasta, tid, None)?,
+                }
+            }
+        }
+
+        for output in &mut self.outputs {
+            match output {
+                Output::Regions { w, .. } | Output::GcBedGraph { w, .. } => w.flush()?,
+                #[cfg(feature = "checksum")]
+                Output::Dict { w } => w.flush()?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_pass() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut fasta = Vec::new();
+        let mut gc = Vec::new();
+        Job::new()
+            .line_width(4)
+            .regions(
+                vec![(2, 45, 55), (1, 0, 3), (2, 0, 2)],
+                "{name}".parse().unwrap(),
+                &mut fasta,
+            )
+            .gc_bedgraph(100, 100, &mut gc)
+            .run(&fa)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(fasta).unwrap(),
+            ">A-100\nAAA\n>ACGT-25\nCCCC\nCGGG\nGG\n>ACGT-25\nAA\n"
+        );
+        assert_eq!(
+            String::from_utf8(gc).unwrap(),
+            "A-10\t0\t10\t0\nA-100\t0\t100\t0\nACGT-25\t0\t100\t0.5\n"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut fasta = Vec::new();
+        let result = Job::new()
+            .regions(
+                vec![(0, 0, 5), (0, 5, 50)],
+                HeaderTemplate::new(),
+                &mut fasta,
+            )
+            .run(&fa);
+        assert!(matches!(result, Err(Error::OutOfRange { .. })));
+        assert!(fasta.is_empty());
+        assert!(Job::new().gc_bedgraph(0, 1, Vec::new()).run(&fa).is_err());
+        assert!(Job::new().line_width(0).run(&fa).is_err());
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn dict() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut dict = Vec::new();
+        Job::new().dict(&mut dict).run(&fa).unwrap();
+        let mut expected = Vec::new();
+        crate::dict::write_dict(&fa, None, &mut expected).unwrap();
+        assert_eq!(dict, expected);
+    }
+}
@@ -0,0 +1,313 @@
+//! Fixed-length k-mers that span line breaks, and canonical k-mer counts.
+//!
+//! `FastaView::kmers` yields every k-mer of a view, overlapping and in order. A k-mer that lies
+//! on a single line is borrowed from the mmapped file, a k-mer that spans a line break is copied
+//! into an owned buffer without the line ending. Case is preserved and no bases are skipped.
+//!
+//! `KmerCounts` counts canonical k-mers (the smallest of a k-mer and its reverse complement) up
+//! to k = `MAX_K`, for instance to compare the k-mer spectrum of references. Counting ignores
+//! case and skips k-mers with other bases than A, C, G or T. For dense counts of short
+//! oligomers on the forward strand see the `oligo` module.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let view = fa.view(2, 46, 54).expect("Cannot get view");
+//! let kmers: Vec<_> = view.kmers(4).expect("Invalid k").collect();
+//! assert_eq!(kmers.len(), 5);
+//! assert_eq!(&kmers[2][..], b"CCGG");
+//!
+//! let counts = fa.view_tid(2).unwrap().count_kmers(21).expect("Invalid k");
+//! // the A and T runs both count as AAAAAAAAAAAAAAAAAAAAA
+//! assert_eq!(counts.get(b"AAAAAAAAAAAAAAAAAAAAA"), Some(10));
+//! ```
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+
+use crate::checkpoint::Resumable;
+use crate::oligo::encode;
+use crate::{Error, FastaView, Result};
+
+/// The largest k-mer size that can be counted.
+pub const MAX_K: usize = 32;
+
+#[inline]
+fn is_base(b: u8) -> bool {
+    b & 192 == 64
+}
+
+/// Iterator over the k-mers of a `FastaView`, created with `FastaView::kmers`.
+pub struct Kmers<'a> {
+    data: &'a [u8],
+    k: usize,
+    /// Byte offset of the first base of the current k-mer.
+    start: usize,
+    /// Byte offset after the last base of the current k-mer.
+    end: usize,
+    /// Number of bases in `start..end`.
+    bases: usize,
+}
+
+impl<'a> Iterator for Kmers<'a> {
+    type Item = Cow<'a, [u8]>;
+
+    fn next(&mut self) -> Option<Cow<'a, [u8]>> {
+        while self.bases < self.k {
+            let &b = self.data.get(self.end)?;
+            self.end += 1;
+            if is_base(b) {
+                self.bases += 1;
+            } else if self.bases == 0 {
+                self.start = self.end;
+            }
+        }
+
+        let window = &self.data[self.start..self.end];
+        let kmer = if window.len() == self.k {
+            Cow::Borrowed(window)
+        } else {
+            Cow::Owned(window.iter().copied().filter(|&b| is_base(b)).collect())
+        };
+
+        self.bases -= 1;
+        self.start += 1;
+        while self.start < self.end && !is_base(self.data[self.start]) {
+            self.start += 1;
+        }
+        Some(kmer)
+    }
+}
+
+/// Counts of canonical k-mers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KmerCounts {
+    k: usize,
+    counts: HashMap<u64, usize>,
+}
+
+impl KmerCounts {
+    /// Create empty counts for k-mers of length `k` (1 up to `MAX_K`).
+    pub fn new(k: usize) -> Result<Self> {
+        if k == 0 || k > MAX_K {
+            return Err(Error::InvalidArgument(format!(
+                "k-mer size should be between 1 and {}",
+                MAX_K
+            )));
+        }
+        Ok(KmerCounts {
+            k,
+            counts: HashMap::new(),
+        })
+    }
+
+    /// The k-mer length.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The number of distinct canonical k-mers.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns `true` if no k-mers were counted.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The total number of counted k-mers.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// The count of `kmer` and its reverse complement. Returns `None` if the length differs
+    /// from k or it contains other bases than A, C, G or T.
+    pub fn get(&self, kmer: &[u8]) -> Option<usize> {
+        if kmer.len() != self.k {
+            return None;
+        }
+        let mut fwd = 0;
+        for &b in kmer {
+            fwd = (fwd << 2) | encode(b)? as u64;
+        }
+        let canonical = fwd.min(self.revcomp(fwd));
+        Some(self.counts.get(&canonical).copied().unwrap_or(0))
+    }
+
+    /// Iterator over the canonical k-mers and their counts, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, usize)> + '_ {
+        self.counts.iter().map(move |(&code, &count)| {
+            let kmer = (0..self.k)
+                .rev()
+                .map(|i| b"ACGT"[(code >> (2 * i)) as usize & 3])
+                .collect();
+            (kmer, count)
+        })
+    }
+
+    /// The k-mer spectrum: for every count, the number of distinct canonical k-mers with that
+    /// count.
+    pub fn spectrum(&self) -> BTreeMap<usize, usize> {
+        let mut spectrum = BTreeMap::new();
+        for &count in self.counts.values() {
+            *spectrum.entry(count).or_insert(0) += 1;
+        }
+        spectrum
+    }
+
+    /// Add the k-mers of `view` to the counts.
+    pub fn add_view(&mut self, view: &FastaView) {
+        let mask = if self.k == 32 {
+            u64::MAX
+        } else {
+            (1 << (2 * self.k)) - 1
+        };
+        let shift = 2 * (self.k - 1);
+        let mut fwd = 0u64;
+        let mut rev = 0u64;
+        let mut valid = 0;
+        for &b in view.bases() {
+            match encode(b) {
+                Some(v) => {
+                    fwd = ((fwd << 2) | v as u64) & mask;
+                    rev = (rev >> 2) | ((3 - v as u64) << shift);
+                    valid += 1;
+                    if valid >= self.k {
+                        *self.counts.entry(fwd.min(rev)).or_insert(0) += 1;
+                    }
+                }
+                None => valid = 0,
+            }
+        }
+    }
+
+    /// The encoded reverse complement of the encoded k-mer `code`.
+    fn revcomp(&self, code: u64) -> u64 {
+        let mut rev = 0;
+        for i in 0..self.k {
+            rev = (rev << 2) | (3 - ((code >> (2 * i)) & 3));
+        }
+        rev
+    }
+}
+
+/// Saved as a line with k, followed by a line with every encoded k-mer and its count.
+impl Resumable for KmerCounts {
+    fn overlap(&self) -> usize {
+        self.k - 1
+    }
+
+    fn add(&mut self, _tid: usize, _start: usize, view: &FastaView) {
+        self.add_view(view);
+    }
+
+    fn save<W: Write>(&self, mut w: W) -> Result<()> {
+        writeln!(w, "k\t{}", self.k)?;
+        for (code, count) in &self.counts {
+            writeln!(w, "{}\t{}", code, count)?;
+        }
+        Ok(())
+    }
+
+    fn load<R: BufRead>(r: R) -> Result<Self> {
+        let invalid = |message: &str| Error::Malformed {
+            format: "checkpoint",
+            line: None,
+            message: message.to_owned(),
+        };
+        let mut lines = r.lines();
+        let k = lines
+            .next()
+            .transpose()?
+            .and_then(|l| l.strip_prefix("k\t")?.parse().ok())
+            .ok_or_else(|| invalid("Missing k-mer size"))?;
+        let mut kmers = KmerCounts::new(k)?;
+        for line in lines {
+            let line = line?;
+            let (code, count) = line
+                .split_once('\t')
+                .and_then(|(code, count)| Some((code.parse().ok()?, count.parse().ok()?)))
+                .ok_or_else(|| invalid("Invalid k-mer count"))?;
+            kmers.counts.insert(code, count);
+        }
+        Ok(kmers)
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Count the canonical k-mers of length `k` (1 up to `kmer::MAX_K`) in the current view.
+    ///
+    /// Returns a `KmerCounts` object, or `Error::InvalidArgument` for an unsupported k.
+    pub fn count_kmers(&self, k: usize) -> Result<KmerCounts> {
+        let mut counts = KmerCounts::new(k)?;
+        counts.add_view(self);
+        Ok(counts)
+    }
+
+    /// Iterator over all overlapping k-mers of length `k` in the current view.
+    ///
+    /// Returns the iterator, or `Error::InvalidArgument` if k is 0.
+    pub fn kmers(&self, k: usize) -> Result<Kmers<'a>> {
+        if k == 0 {
+            return Err(Error::InvalidArgument(
+                "k-mer size should be at least 1".to_owned(),
+            ));
+        }
+        Ok(Kmers {
+            data: self.0,
+            k,
+            start: 0,
+            end: 0,
+            bases: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kmers() {
+        let v = FastaView::new(b"ACg\nTA\r\nC");
+        let kmers: Vec<_> = v.kmers(3).unwrap().collect();
+        assert_eq!(kmers, vec![&b"ACg"[..], b"CgT", b"gTA", b"TAC"]);
+        assert!(matches!(kmers[0], Cow::Borrowed(_)));
+        assert!(matches!(kmers[1], Cow::Owned(_)));
+        assert_eq!(v.kmers(1).unwrap().count(), 6);
+        assert_eq!(v.kmers(6).unwrap().count(), 1);
+        assert_eq!(v.kmers(7).unwrap().count(), 0);
+        assert_eq!(FastaView::new(b"").kmers(1).unwrap().count(), 0);
+        assert!(v.kmers(0).is_err());
+    }
+
+    #[test]
+    fn count_kmers() {
+        let c = FastaView::new(
+            b"ACGg
+TTNAC",
+        )
+        .count_kmers(2)
+        .unwrap();
+        // AC CG GG GT TT AC -> AC(2) CG(1) CC(GG, 1) AC(GT, 1) AA(TT, 1)
+        assert_eq!(c.total(), 6);
+        assert_eq!(c.len(), 4);
+        assert_eq!(c.get(b"AC"), Some(3));
+        assert_eq!(c.get(b"gt"), Some(3));
+        assert_eq!(c.get(b"CC"), Some(1));
+        assert_eq!(c.get(b"CA"), Some(0));
+        assert_eq!(c.get(b"AN"), None);
+        let mut kmers: Vec<_> = c.iter().collect();
+        kmers.sort();
+        assert_eq!(kmers[0], (b"AA".to_vec(), 1));
+        assert_eq!(c.spectrum(), BTreeMap::from([(1, 3), (3, 1)]));
+
+        let c = FastaView::new(&[b'A'; 40]).count_kmers(MAX_K).unwrap();
+        assert_eq!(c.get(&[b'T'; MAX_K]), Some(9));
+        assert!(KmerCounts::new(0).is_err());
+        assert!(KmerCounts::new(MAX_K + 1).is_err());
+    }
+}
@@ -20,21 +20,21 @@
 //! //count the bases
 //! let counts = v.count_bases();
 //! //or print the sequence
-//! println!("{}", v.to_string());
+//! println!("{}", v);
 //! ```
 //! # Limitations
 //! The parser uses a simple ascii mask for allowable characters (64..128), does not apply any
 //! IUPAC converson or validation. Anything outside this range is silently skipped. This means that
 //! also invalid `fasta` will be parsed. The mere presence of an accompanying `.fai` provides the
-//! assumption of a valid fasta.
-//! Requires Rust >=1.64
+//! assumption of a valid fasta. Use `FastaView::strict_bases` to fail on invalid bases instead.
+//! Requires Rust >=1.74. Optional features can require a newer Rust for their dependencies.
 //!
 //! # Alternatives
 //! [Rust-bio](https://crates.io/crates/bio) provides a competent indexed fasta reader. The major
 //! difference is that it has an internal buffer an therefore needs to be mutable when performing
 //! read operations. faimm is also faster. If you want record based access (without an .fai index
 //! file) [rust-bio](https://crates.io/crates/bio) or [seq_io](https://crates.io/crates/seq_io)
-//! provide this.
+//! provide this. For simple one-pass scans the `records` module reads records sequentially.
 //!
 //! # Performance
 //! Calculating the gc content of target regions of an exome (231_410 regions) on the Human
@@ -42,130 +42,438 @@
 //! sound implementation) and rust-bio (1.3s same implementation as example)
 //! Some tests show counting can also be improved using simd, but nothing has been released.
 
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Index};
 use std::path::Path;
+use std::sync::Arc;
 
 use indexmap::IndexSet;
 use memmap2::{Mmap, MmapOptions};
 
+use crate::alias::Aliases;
+pub use crate::error::{Error, Result};
+use crate::limits::Limits;
+use crate::metrics::Metrics;
+use crate::records::FastaRecords;
+use crate::source::SequenceSource;
+
+pub mod advise;
+pub mod alias;
+pub mod alphabet;
+#[cfg(feature = "tokio")]
+pub mod async_fasta;
+pub mod bed;
+#[cfg(feature = "bgzf")]
+pub mod bgzf;
+pub mod builder;
+pub mod checkpoint;
+#[cfg(feature = "checksum")]
+pub mod checksum;
+pub mod collection;
+pub mod complexity;
+pub mod consensus;
+pub mod coords;
+pub mod dedup;
+#[cfg(feature = "checksum")]
+pub mod dict;
+pub mod digest;
+pub mod drift;
+pub mod error;
+pub mod exclude;
+pub mod fastq;
+pub mod gaps;
+pub mod gc;
+pub mod header;
+pub mod job;
+pub mod kmer;
+pub mod limits;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+pub mod mask;
+pub mod metrics;
+pub mod motif;
+#[cfg(feature = "noodles")]
+pub mod noodles;
+pub mod oligo;
+pub mod orf;
+pub mod owned;
+pub mod packed;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod pool;
+pub mod pwm;
+pub mod quality;
+pub mod records;
+pub mod region;
+pub mod report;
+#[cfg(feature = "checksum")]
+pub mod rescue;
+#[cfg(feature = "bio")]
+pub mod rust_bio;
+pub mod sample;
+pub mod schedule;
+pub mod search;
+pub mod source;
+pub mod stats;
+pub mod strand;
+pub mod strict;
+pub mod tandem;
+pub mod transform;
+pub mod translate;
+pub mod twobit;
+pub mod validate;
+pub mod warmup;
+pub mod writer;
+
 /// The object that stores the parsed fasta index file. You can use it to map chromosome names to
 /// indexes and lookup offsets for chr-start:end coordinates
 #[derive(Debug, Clone)]
 pub struct Fai {
-    chromosomes: Vec<FaiRecord>,
+    chromosomes: Records,
     name_map: IndexSet<String>,
+    aliases: Option<Arc<Aliases>>,
+}
+
+/// The records of a `Fai`, either parsed up front or parsed on demand from the mmapped file.
+#[derive(Debug, Clone)]
+enum Records {
+    Parsed(Vec<FaiRecord>),
+    Lazy { data: Arc<Mmap>, lines: Vec<usize> },
 }
 
 impl Fai {
     /// Open a fasta index file from path `P`.
-    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let f = File::open(path)?;
-        let br = BufReader::new(f);
+    ///
+    /// Returns `Error::MissingIndex` if the file doesn't exist and `Error::MalformedFai` for
+    /// lines that cannot be parsed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Fai::read(path.as_ref(), false)
+    }
+
+    /// Open a fasta index file from path `P` that was edited by hand. The columns can be
+    /// separated by any whitespace instead of a single tab, and empty lines are skipped. The
+    /// chromosome names can't contain whitespace.
+    ///
+    /// Returns `Error::MissingIndex` if the file doesn't exist and `Error::MalformedFai` for
+    /// lines that cannot be parsed.
+    pub fn from_file_lenient<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Fai::read(path.as_ref(), true)
+    }
+
+    /// Read a fasta index in the 5 column `.fai` format from `r`. The 6 column index of a fastq
+    /// file is read as well, see `fastq::IndexedFastq`.
+    ///
+    /// Returns `Error::MalformedFai` for lines that cannot be parsed.
+    pub fn from_reader<R: BufRead>(r: R) -> Result<Self> {
+        Fai::parse(r, false)
+    }
+
+    fn read(path: &Path, lenient: bool) -> Result<Self> {
+        let f = open_index(path)?;
+        Fai::parse(BufReader::new(f), lenient)
+    }
 
+    fn parse<R: BufRead>(br: R, lenient: bool) -> Result<Self> {
         let mut name_map = IndexSet::new();
         let mut chromosomes = Vec::new();
 
-        for l in br.lines() {
+        for (i, l) in br.lines().enumerate() {
             let line = l?;
-            let p: Vec<_> = line.split('\t').collect();
-
-            if p.len() != 5 {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "Expected 5 columns in .fai file.",
-                ));
+            let p: Vec<_> = if lenient {
+                line.split_whitespace().collect()
+            } else {
+                line.split('\t').collect()
+            };
+            if lenient && p.is_empty() {
+                continue;
             }
+            let record = parse_record(&p, i + 1)?;
 
             name_map.insert(p[0].to_owned());
+            chromosomes.push(record);
+        }
 
-            let ioerr =
-                |e, msg| io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}", msg, e));
-            chromosomes.push(FaiRecord {
-                len: p[1]
-                    .parse()
-                    .map_err(|e| ioerr(e, "Error parsing chr len in .fai"))?,
-                offset: p[2]
-                    .parse()
-                    .map_err(|e| ioerr(e, "Error parsing chr offset in .fai"))?,
-                line_bases: p[3]
-                    .parse()
-                    .map_err(|e| ioerr(e, "Error parsing chr line_bases in .fai"))?,
-                line_width: p[4]
-                    .parse()
-                    .map_err(|e| ioerr(e, "Error parsing chr line_width in .fai"))?,
-            });
+        Ok(Fai {
+            chromosomes: Records::Parsed(chromosomes),
+            name_map,
+            aliases: None,
+        })
+    }
+
+    /// Open a fasta index file from path `P` without parsing the records. The index file is
+    /// memory mapped and only the chromosome names are read up front. The record of a chromosome
+    /// is parsed every time it is used. This makes opening an index with millions of records
+    /// much faster, which pays off for short-lived lookups.
+    ///
+    /// Malformed records are only reported when they are accessed.
+    pub fn from_file_lazy<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = open_index(path.as_ref())?;
+        let data = unsafe { MmapOptions::new().map(&file) }.map_err(Error::Mmap)?;
+
+        let mut name_map = IndexSet::new();
+        let mut lines = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let end = data[pos..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(data.len(), |p| pos + p);
+            let name = data[pos..end]
+                .iter()
+                .position(|&b| b == b'\t')
+                .and_then(|p| std::str::from_utf8(&data[pos..pos + p]).ok())
+                .ok_or_else(|| Error::MalformedFai {
+                    line: lines.len() + 1,
+                    message: "Expected 5 or 6 tab separated columns".to_owned(),
+                })?;
+            name_map.insert(name.to_owned());
+            lines.push(pos);
+            pos = end + 1;
+        }
+
+        Ok(Fai {
+            chromosomes: Records::Lazy {
+                data: Arc::new(data),
+                lines,
+            },
+            name_map,
+            aliases: None,
+        })
+    }
+
+    /// Return the record of the chromosome at index tid. Lazily loaded indexes parse the record
+    /// from the index file.
+    ///
+    /// Returns the `FaiRecord` if successful, `Error::UnknownTid` if the tid is unknown and
+    /// `Error::MalformedFai` for a lazily loaded record that cannot be parsed.
+    #[inline]
+    pub fn record(&self, tid: usize) -> Result<FaiRecord> {
+        match &self.chromosomes {
+            Records::Parsed(records) => records.get(tid).copied().ok_or(Error::UnknownTid(tid)),
+            Records::Lazy { data, lines } => {
+                let start = *lines.get(tid).ok_or(Error::UnknownTid(tid))?;
+                let line = data[start..].split(|&b| b == b'\n').next().unwrap_or(&[]);
+                let line = std::str::from_utf8(line).map_err(|e| Error::MalformedFai {
+                    line: tid + 1,
+                    message: e.to_string(),
+                })?;
+                parse_record(&line.split('\t').collect::<Vec<_>>(), tid + 1)
+            }
+        }
+    }
+
+    /// Create the index by scanning the fasta file at path `P`. This follows the rules of
+    /// `samtools faidx`: the name of a record is the header up to the first whitespace, and
+    /// all sequence lines of a record, except the last one, must have the same length.
+    ///
+    /// Returns the new `Fai` if successful. `Error::MalformedFasta` if the fasta file cannot be
+    /// indexed.
+    pub fn create<P: AsRef<Path>>(fasta: P) -> Result<Self> {
+        let fa = FastaRecords::from_file(fasta)?;
+        Fai::index(fa.records())
+    }
+
+    /// Create the index by scanning the fasta data in `data`, like `Fai::create`. The offsets
+    /// are relative to the start of `data`.
+    pub fn create_from_bytes(data: &[u8]) -> Result<Self> {
+        Fai::index(records::Records::new(data))
+    }
+
+    fn index(records: records::Records) -> Result<Self> {
+        let mut name_map = IndexSet::new();
+        let mut chromosomes = Vec::new();
+
+        for record in records {
+            let record = record?;
+            let name = record.name();
+            if name.is_empty() {
+                return Err(Error::MalformedFasta {
+                    offset: record.header_offset(),
+                    message: "Empty record name".to_owned(),
+                });
+            }
+            if !name_map.insert(name.to_owned()) {
+                return Err(Error::MalformedFasta {
+                    offset: record.header_offset(),
+                    message: format!("Duplicate record name {}", name),
+                });
+            }
+
+            let mut builder = RecordBuilder::new(record.offset());
+            let mut pos = record.offset();
+            for line in record.raw_sequence().split_inclusive(|&b| b == b'\n') {
+                builder
+                    .add_line(line)
+                    .map_err(|message| Error::MalformedFasta {
+                        offset: pos,
+                        message: message.to_owned(),
+                    })?;
+                pos += line.len();
+            }
+            chromosomes.push(builder.finish());
         }
 
         Ok(Fai {
-            chromosomes,
+            chromosomes: Records::Parsed(chromosomes),
             name_map,
+            aliases: None,
         })
     }
 
+    /// Create the index by scanning the fasta file at path `P` and write it to a `.fai` file
+    /// next to the fasta file. `IndexedFasta::from_file` can open the fasta afterwards.
+    ///
+    /// Returns the new `Fai` if successful. `Error` otherwise.
+    pub fn create_and_write<P: AsRef<Path>>(fasta: P) -> Result<Self> {
+        let fai = Fai::create(&fasta)?;
+        fai.to_file(fai_path(fasta))?;
+        Ok(fai)
+    }
+
+    /// Write the index to a new file at path `P` in the 5 column `.fai` format.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let f = File::create(path)?;
+        let mut bw = BufWriter::new(f);
+        self.write_to(&mut bw)?;
+        Ok(bw.flush()?)
+    }
+
+    /// Write the index to `w` in the 5 column `.fai` format, one line per chromosome in index
+    /// order.
+    pub fn write_to<W: Write>(&self, mut w: W) -> Result<()> {
+        for (tid, name) in self.name_map.iter().enumerate() {
+            let chr = self.record(tid)?;
+            write!(
+                w,
+                "{}\t{}\t{}\t{}\t{}",
+                name, chr.len, chr.offset, chr.line_bases, chr.line_width
+            )?;
+            match chr.qual_offset {
+                Some(qual_offset) => writeln!(w, "\t{}", qual_offset)?,
+                None => writeln!(w)?,
+            }
+        }
+        Ok(())
+    }
+
     /// Calculate the slice coordinates (byte offsets).
     /// tid is the index of the chromosome (lookup with `Fai::tid` if necessary.
     /// start, end: zero based coordinates of the requested range.
     ///
-    /// Returns an tuple (start, end) if successful. `io::Error` otherwise.
+    /// Returns an tuple (start, end) if successful. `Error::InvalidInterval` if start is larger
+    /// than stop and `Error::OutOfRange` if the interval doesn't fit on the chromosome. The
+    /// offsets are not checked against the size of the fasta file, `IndexedFasta` does that.
     #[inline]
-    pub fn offset(&self, tid: usize, start: usize, stop: usize) -> io::Result<(usize, usize)> {
-        let chr = &self.chromosomes.get(tid).ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Chromomsome tid was out of bounds")
-        })?;
-        if stop > chr.len {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "FASTA read interval was out of bounds",
-            ));
+    pub fn offset(&self, tid: usize, start: usize, stop: usize) -> Result<(usize, usize)> {
+        let chr = self.checked_record(tid, start, stop)?;
+        Ok((chr.byte_offset(start), chr.byte_offset(stop)))
+    }
+
+    /// The byte offsets of tid:start-stop like `Fai::offset`, but a range that ends at the end
+    /// of a line ends before the line break, which the last line of a file may lack. An empty
+    /// range starts after the previous base for the same reason.
+    pub(crate) fn base_range(
+        &self,
+        tid: usize,
+        start: usize,
+        stop: usize,
+    ) -> Result<(usize, usize)> {
+        let chr = self.checked_record(tid, start, stop)?;
+        if stop == start {
+            let offset = match start.checked_sub(1) {
+                Some(prev) => chr.byte_offset(prev) + 1,
+                None => chr.byte_offset(0),
+            };
+            return Ok((offset, offset));
         }
+        Ok((chr.byte_offset(start), chr.byte_offset(stop - 1) + 1))
+    }
 
-        let start_offset =
-            chr.offset + (start / chr.line_bases) * chr.line_width + start % chr.line_bases;
-        let stop_offset =
-            chr.offset + (stop / chr.line_bases) * chr.line_width + stop % chr.line_bases;
+    /// The record of chromosome tid, if start-stop is a valid interval on it.
+    #[inline]
+    fn checked_record(&self, tid: usize, start: usize, stop: usize) -> Result<FaiRecord> {
+        let chr = self.record(tid)?;
+        if start > stop {
+            return Err(Error::InvalidInterval { start, stop });
+        }
+        if stop > chr.len {
+            return Err(Error::OutOfRange {
+                tid,
+                start,
+                stop,
+                len: chr.len,
+            });
+        }
+        Ok(chr)
+    }
 
-        Ok((start_offset, stop_offset))
+    /// Calculate the byte offsets of the qualities of the range start-stop of a read in an
+    /// indexed fastq file. A range that ends at the end of a line ends before the line break.
+    ///
+    /// Returns an tuple (start, end) if successful. `Error::OutOfRange` if the interval doesn't
+    /// fit on the read and `Error::MalformedFai` if the index has no quality offset for tid.
+    pub fn quality_offset(&self, tid: usize, start: usize, stop: usize) -> Result<(usize, usize)> {
+        let chr = self.record(tid)?;
+        let qual_offset = chr.qual_offset.ok_or_else(|| Error::MalformedFai {
+            line: tid + 1,
+            message: "Expected 6 columns in the index of a fastq file".to_owned(),
+        })?;
+        let (start_offset, stop_offset) = self.base_range(tid, start, stop)?;
+        Ok((
+            start_offset - chr.offset + qual_offset,
+            stop_offset - chr.offset + qual_offset,
+        ))
     }
 
     /// Calculate the slice coordinates (byte offsets).
     /// tid is the index of the chromosome (lookup with `Fai::tid` if necessary.
     ///
-    /// Returns an tuple (start, end) if successful. `io::Error` otherwise.
+    /// Returns an tuple (start, end) if successful. `Error::UnknownTid` otherwise.
     #[inline]
-    pub fn offset_tid(&self, tid: usize) -> io::Result<(usize, usize)> {
-        let chr = &self.chromosomes.get(tid).ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Chromomsome tid was out of bounds")
-        })?;
-        let start_offset = chr.offset;
-        let stop_offset =
-            chr.offset + (chr.len / chr.line_bases) * chr.line_width + chr.len % chr.line_bases;
-        Ok((start_offset, stop_offset))
+    pub fn offset_tid(&self, tid: usize) -> Result<(usize, usize)> {
+        let chr = self.record(tid)?;
+        Ok((chr.offset, chr.byte_offset(chr.len)))
     }
 
-    /// Return the index of the chromosome by name in the fasta index.
+    /// Return the index of the chromosome by name in the fasta index. Names that are not in the
+    /// index are resolved with the aliases, if set with `Fai::with_aliases`.
     ///
     /// Returns the position of chr `name` if succesful, None otherwise.
     #[inline]
     pub fn tid(&self, name: &str) -> Option<usize> {
-        self.name_map.get_index_of(name)
+        self.name_map
+            .get_index_of(name)
+            .or_else(|| self.aliases.as_ref()?.resolve(&self.name_map, name))
+    }
+
+    /// Resolve chromosome names that are not in the index with `aliases` (see `alias::Aliases`)
+    /// in `Fai::tid` and every lookup by name.
+    pub fn with_aliases(mut self, aliases: Aliases) -> Self {
+        self.aliases = Some(Arc::new(aliases));
+        self
+    }
+
+    /// Return the aliases used to resolve chromosome names, `None` if they are not set.
+    pub fn aliases(&self) -> Option<&Aliases> {
+        self.aliases.as_deref()
     }
 
     /// Return the index of a chromosome in the fasta index.
     ///
     /// Returns the size in bases as usize.
-    pub fn size(&self, tid: usize) -> io::Result<usize> {
-        let chr = &self.chromosomes.get(tid).ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Chromomsome tid was out of bounds")
-        })?;
+    pub fn size(&self, tid: usize) -> Result<usize> {
+        let chr = self.record(tid)?;
         Ok(chr.len)
     }
 
     /// Return the name of the chromomsome at index tid
-    pub fn name(&self, tid: usize) -> io::Result<&String> {
-        self.name_map.get_index(tid).ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Chromomsome tid was out of bounds")
-        })
+    pub fn name(&self, tid: usize) -> Result<&String> {
+        self.name_map.get_index(tid).ok_or(Error::UnknownTid(tid))
     }
 
     /// Return the names of the chromosomes from the fasta index in the same order as in the
@@ -175,138 +483,1069 @@ impl Fai {
     pub fn names(&self) -> Vec<&str> {
         self.name_map.iter().map(|s| s.as_str()).collect()
     }
-}
 
-/// FaiRecord stores the length, offset, and fasta file characterics of a single chromosome
-#[derive(Debug, Clone)]
-pub struct FaiRecord {
-    len: usize,
-    offset: usize,
-    line_bases: usize,
-    line_width: usize,
+    /// Iterate over the names and records of the chromosomes in the same order as in the
+    /// `.fai`. The records of a lazily loaded index are parsed while iterating.
+    ///
+    /// Returns an iterator of `(name, FaiRecord)`, with `Error::MalformedFai` for a lazily
+    /// loaded record that cannot be parsed.
+    pub fn records(&self) -> impl ExactSizeIterator<Item = Result<(&str, FaiRecord)>> + '_ {
+        self.name_map
+            .iter()
+            .enumerate()
+            .map(move |(tid, name)| Ok((name.as_str(), self.record(tid)?)))
+    }
+
+    /// The number of chromosomes in the index.
+    pub fn len(&self) -> usize {
+        self.name_map.len()
+    }
+
+    /// Whether the index contains no chromosomes.
+    pub fn is_empty(&self) -> bool {
+        self.name_map.is_empty()
+    }
+}
+
+/// A `Fai` line for serde: the name and the fields of its `FaiRecord`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct FaiEntry<'a> {
+    #[serde(borrow)]
+    name: Cow<'a, str>,
+    len: usize,
+    offset: usize,
+    line_bases: usize,
+    line_width: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    qual_offset: Option<usize>,
+}
+
+/// Serializes as a sequence of the lines of the `.fai` file, with the fields `name`, `len`,
+/// `offset`, `line_bases`, `line_width` and, for the index of a fastq file, `qual_offset`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Fai {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error as _, SerializeSeq};
+        let mut seq = serializer.serialize_seq(Some(self.name_map.len()))?;
+        for (tid, name) in self.name_map.iter().enumerate() {
+            let chr = self.record(tid).map_err(S::Error::custom)?;
+            seq.serialize_element(&FaiEntry {
+                name: name.into(),
+                len: chr.len,
+                offset: chr.offset,
+                line_bases: chr.line_bases,
+                line_width: chr.line_width,
+                qual_offset: chr.qual_offset,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+/// Fails for a name that is used twice.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Fai {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        use serde::de::Error as _;
+        let entries = Vec::<FaiEntry>::deserialize(deserializer)?;
+        let mut name_map = IndexSet::with_capacity(entries.len());
+        let mut chromosomes = Vec::with_capacity(entries.len());
+        for e in entries {
+            if !name_map.insert(e.name.to_string()) {
+                return Err(D::Error::custom(format!(
+                    "Duplicate sequence name {}",
+                    e.name
+                )));
+            }
+            chromosomes.push(FaiRecord {
+                len: e.len,
+                offset: e.offset,
+                line_bases: e.line_bases,
+                line_width: e.line_width,
+                qual_offset: e.qual_offset,
+            });
+        }
+        Ok(Fai {
+            chromosomes: Records::Parsed(chromosomes),
+            name_map,
+            aliases: None,
+        })
+    }
+}
+
+/// FaiRecord stores the length, offset, and fasta file characterics of a single chromosome
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FaiRecord {
+    len: usize,
+    offset: usize,
+    line_bases: usize,
+    line_width: usize,
+    /// The offset of the qualities in the 6 column index of a fastq file.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    qual_offset: Option<usize>,
+}
+
+impl FaiRecord {
+    /// The length of the sequence in bases.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the sequence contains no bases.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The byte offset of the first base in the fasta file.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The number of bases on a full line.
+    pub fn line_bases(&self) -> usize {
+        self.line_bases
+    }
+
+    /// The number of bytes of a full line, including the line break.
+    pub fn line_width(&self) -> usize {
+        self.line_width
+    }
+
+    /// The byte offset of the first quality in a fastq file, `None` for a fasta file.
+    pub fn qual_offset(&self) -> Option<usize> {
+        self.qual_offset
+    }
+
+    /// The byte offset of base `pos` in the fasta file.
+    #[inline]
+    fn byte_offset(&self, pos: usize) -> usize {
+        // an empty sequence has no lines
+        if self.line_bases == 0 {
+            return self.offset;
+        }
+        self.offset + (pos / self.line_bases) * self.line_width + pos % self.line_bases
+    }
+
+    /// The byte offset after the last base, or after the last quality in the index of a fastq
+    /// file.
+    fn end(&self) -> usize {
+        let start = self.qual_offset.unwrap_or(self.offset);
+        if self.len == 0 {
+            return start;
+        }
+        start + self.byte_offset(self.len - 1) + 1 - self.offset
+    }
+}
+
+/// Collects the `FaiRecord` fields from the sequence lines of a single fasta record.
+struct RecordBuilder {
+    record: FaiRecord,
+    last_line: bool,
+}
+
+impl RecordBuilder {
+    fn new(offset: usize) -> Self {
+        RecordBuilder {
+            record: FaiRecord {
+                len: 0,
+                offset,
+                line_bases: 0,
+                line_width: 0,
+                qual_offset: None,
+            },
+            last_line: false,
+        }
+    }
+
+    fn add_line(&mut self, line: &[u8]) -> std::result::Result<(), &'static str> {
+        let width = line.len();
+        let mut bases = width;
+        if line.ends_with(b"\n") {
+            bases -= 1;
+            if line[..bases].ends_with(b"\r") {
+                bases -= 1;
+            }
+        }
+
+        if bases == 0 {
+            self.last_line = true;
+            return Ok(());
+        }
+        if self.last_line {
+            return Err("Different line length in sequence");
+        }
+
+        let r = &mut self.record;
+        if r.line_bases == 0 {
+            r.line_bases = bases;
+            r.line_width = width;
+        } else if bases > r.line_bases
+            || (bases == r.line_bases && width != r.line_width && line.ends_with(b"\n"))
+        {
+            return Err("Different line length in sequence");
+        } else if bases < r.line_bases {
+            self.last_line = true;
+        }
+        r.len += bases;
+        Ok(())
+    }
+
+    fn finish(self) -> FaiRecord {
+        self.record
+    }
+}
+
+/// Parse the columns of line number `line` (one-based) of a `.fai` file. A `\r` left by Windows
+/// line endings is removed from the last column.
+fn parse_record(p: &[&str], line: usize) -> Result<FaiRecord> {
+    let malformed = |message: String| Error::MalformedFai { line, message };
+    if p.len() != 5 && p.len() != 6 {
+        let hint = if p.iter().any(|c| c.contains(' ')) {
+            ", use Fai::from_file_lenient for columns separated by spaces"
+        } else {
+            ""
+        };
+        return Err(malformed(format!(
+            "Expected 5 or 6 tab separated columns, found {}{}",
+            p.len(),
+            hint
+        )));
+    }
+
+    let column = |i: usize, name: &str| {
+        p[i].strip_suffix('\r')
+            .unwrap_or(p[i])
+            .parse()
+            .map_err(|e| malformed(format!("Error parsing chr {}: {}", name, e)))
+    };
+    let record = FaiRecord {
+        len: column(1, "len")?,
+        offset: column(2, "offset")?,
+        line_bases: column(3, "line_bases")?,
+        line_width: column(4, "line_width")?,
+        qual_offset: if p.len() == 6 {
+            Some(column(5, "qual_offset")?)
+        } else {
+            None
+        },
+    };
+    if record.len > 0 && record.line_bases == 0 {
+        return Err(malformed(
+            "Chr line_bases is 0 for a non-empty sequence".to_owned(),
+        ));
+    }
+    if record.line_width < record.line_bases {
+        return Err(malformed(
+            "Chr line_width is smaller than line_bases".to_owned(),
+        ));
+    }
+    Ok(record)
+}
+
+/// Open an index file, a missing file gives `Error::MissingIndex`.
+fn open_index(path: &Path) -> Result<File> {
+    File::open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => Error::MissingIndex(path.to_owned()),
+        _ => Error::Io(e),
+    })
+}
+
+/// The path of the index file that accompanies the fasta file at `path`.
+fn fai_path<P: AsRef<Path>>(path: P) -> OsString {
+    let mut fai_path = path.as_ref().as_os_str().to_owned();
+    fai_path.push(".fai");
+    fai_path
+}
+
+/// Check that the fasta file at `fasta` was not modified after its index at `index`. Files
+/// without a modification time are not checked.
+///
+/// Returns `Ok(())` if the index is not older, `Error::OutdatedIndex` otherwise.
+pub(crate) fn check_modified<P: AsRef<Path>, I: AsRef<Path>>(fasta: P, index: I) -> Result<()> {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let index = index.as_ref();
+    match (modified(fasta.as_ref()), modified(index)) {
+        (Some(fasta), Some(fai)) if fasta > fai => Err(Error::OutdatedIndex(index.to_owned())),
+        _ => Ok(()),
+    }
+}
+
+/// The `IndexFasta` can be used to open a fasta file that has a valid .fai index file.
+pub struct IndexedFasta {
+    source: Arc<dyn SequenceSource>,
+    fasta_index: Fai,
+    limits: Limits,
+    metrics: Option<Metrics>,
+    #[cfg(feature = "checksum")]
+    checksums: std::sync::OnceLock<checksum::ChecksumIndex>,
+}
+
+impl IndexedFasta {
+    /// Open a fasta file from path `P`. It is assumed that it has a valid .fai index file. The
+    /// .fai file is created by appending .fai to the fasta file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let index_path = fai_path(&path);
+        let fasta_index = Fai::from_file(&index_path)?;
+        IndexedFasta::with_index(path, index_path, fasta_index)
+    }
+
+    /// Open a fasta file from path `P` like `IndexedFasta::from_file`, but load the .fai index
+    /// lazily (see `Fai::from_file_lazy`).
+    pub fn from_file_lazy<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let index_path = fai_path(&path);
+        let fasta_index = Fai::from_file_lazy(&index_path)?;
+        IndexedFasta::with_index(path, index_path, fasta_index)
+    }
+
+    /// Open a fasta file from path `P` like `IndexedFasta::from_file`, but parse the .fai index
+    /// leniently (see `Fai::from_file_lenient`).
+    pub fn from_file_lenient<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let index_path = fai_path(&path);
+        let fasta_index = Fai::from_file_lenient(&index_path)?;
+        IndexedFasta::with_index(path, index_path, fasta_index)
+    }
+
+    /// Map the fasta file at path `P` with its parsed index from `index_path`, and check that
+    /// the index is up to date.
+    fn with_index<P: AsRef<Path>, I: AsRef<Path>>(
+        path: P,
+        index_path: I,
+        fasta_index: Fai,
+    ) -> Result<Self> {
+        check_modified(&path, index_path)?;
+        let file = File::open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file) }.map_err(Error::Mmap)?;
+        let fa = IndexedFasta::from_source(mmap, fasta_index);
+        fa.check_index()?;
+        Ok(fa)
+    }
+
+    /// Check that the fasta data is long enough for every record in the index. The records
+    /// don't have to be in file order and the index can list a subset of the sequences. This
+    /// detects an index that was not updated after sequences were removed or shortened.
+    /// Opening a file with `IndexedFasta::from_file` and the other `from_file` variants runs
+    /// this check, and also checks that the fasta file was not modified after its index.
+    ///
+    /// Returns `Ok(())` if the index fits the data, `Error::StaleIndex` otherwise.
+    pub fn check_index(&self) -> Result<()> {
+        let mut expected = 0;
+        for tid in 0..self.fasta_index.name_map.len() {
+            expected = expected.max(self.fasta_index.record(tid)?.end());
+        }
+        let data = self.data();
+        if data.len() < expected {
+            return Err(Error::StaleIndex {
+                expected,
+                size: data.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Use the fasta data in `source` with its index, see the `source` module. The offsets in
+    /// the index are relative to the start of the data.
+    pub fn from_source<S: SequenceSource + 'static>(source: S, fasta_index: Fai) -> Self {
+        IndexedFasta {
+            source: Arc::new(source),
+            fasta_index,
+            limits: Limits::new(),
+            metrics: None,
+            #[cfg(feature = "checksum")]
+            checksums: std::sync::OnceLock::new(),
+        }
+    }
+
+    /// Index the fasta data in `source` (see `Fai::create_from_bytes`) and use it like
+    /// `IndexedFasta::from_source`.
+    ///
+    /// Returns the `IndexedFasta` if successful, `Error::MalformedFasta` if the data cannot be
+    /// indexed.
+    pub fn index_source<S: SequenceSource + 'static>(source: S) -> Result<Self> {
+        let fasta_index = Fai::create_from_bytes(source.bytes())?;
+        Ok(IndexedFasta::from_source(source, fasta_index))
+    }
+
+    /// The complete fasta data.
+    #[inline]
+    pub(crate) fn data(&self) -> &[u8] {
+        self.source.bytes()
+    }
+
+    /// Use tid, start and end to calculate a slice on the Fasta file. Use this view to iterate
+    /// over the bases.
+    ///
+    /// Returns FastaView for the provided chromsome, start, end if successful, Error otherwise.
+    pub fn view(&self, tid: usize, start: usize, stop: usize) -> Result<FastaView<'_>> {
+        let (start_byte, stop_byte) = self.byte_range(tid, start, stop)?;
+        Ok(FastaView::with_layout(
+            &self.data()[start_byte..stop_byte],
+            stop - start,
+            Layout::new(self.fasta_index.record(tid)?, start),
+        ))
+    }
+
+    /// Use tid to return a view of an entire chromosome.
+    ///
+    /// Returns FastaView for the provided chromsome indicated by tid if successful, Error otherwise.
+    pub fn view_tid(&self, tid: usize) -> Result<FastaView<'_>> {
+        let (start_byte, stop_byte) = self.byte_range_tid(tid)?;
+        Ok(FastaView::with_layout(
+            &self.data()[start_byte..stop_byte],
+            self.fasta_index.size(tid)?,
+            Layout::new(self.fasta_index.record(tid)?, 0),
+        ))
+    }
+
+    /// The byte range of a view of tid:start-stop, checked against the limits and counted in
+    /// the metrics.
+    fn byte_range(&self, tid: usize, start: usize, stop: usize) -> Result<(usize, usize)> {
+        let range = self.checked_offset(tid, start, stop).and_then(|range| {
+            self.limits.check_view(stop - start)?;
+            Ok(range)
+        });
+        self.record(range, stop.saturating_sub(start))
+    }
+
+    /// The byte range of a view of chromosome tid, like `byte_range`.
+    fn byte_range_tid(&self, tid: usize) -> Result<(usize, usize)> {
+        let range = self.fasta_index.size(tid).and_then(|len| {
+            let range = self.checked_offset(tid, 0, len)?;
+            if self.limits.max_view_len.is_some() {
+                self.limits.check_view(self.fasta_index.size(tid)?)?;
+            }
+            Ok(range)
+        });
+        let len = self.fasta_index.size(tid).unwrap_or(0);
+        self.record(range, len)
+    }
+
+    /// The byte range of the bases of tid:start-stop, without a line break at the end, checked
+    /// against the size of the fasta data.
+    pub(crate) fn checked_offset(
+        &self,
+        tid: usize,
+        start: usize,
+        stop: usize,
+    ) -> Result<(usize, usize)> {
+        let range = self.fasta_index.base_range(tid, start, stop)?;
+        self.check_size(tid, range)
+    }
+
+    /// Returns `Error::Truncated` if the byte range of chromosome tid ends beyond the fasta data.
+    pub(crate) fn check_size(&self, tid: usize, range: (usize, usize)) -> Result<(usize, usize)> {
+        let size = self.data().len();
+        if range.1 > size {
+            return Err(Error::Truncated {
+                tid,
+                offset: range.1,
+                size,
+            });
+        }
+        Ok(range)
+    }
+
+    /// Count a view request of `bases` bases in the metrics, if they are enabled.
+    #[inline]
+    fn record<T>(&self, result: Result<T>, bases: usize) -> Result<T> {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_query(&result, bases);
+        }
+        result
+    }
+
+    /// Use the chromosome name, start and end to calculate a slice on the Fasta file. This
+    /// combines `Fai::tid` and `IndexedFasta::view`.
+    ///
+    /// Returns FastaView for the provided chromsome, start, end if successful, Error otherwise.
+    /// The error is `Error::UnknownName` if the chromosome name is not in the index.
+    pub fn view_by_name(&self, name: &str, start: usize, stop: usize) -> Result<FastaView<'_>> {
+        self.view(self.tid_by_name(name)?, start, stop)
+    }
+
+    /// Use the chromosome name to return a view of an entire chromosome. This combines
+    /// `Fai::tid` and `IndexedFasta::view_tid`.
+    ///
+    /// Returns FastaView for the provided chromsome if successful, Error otherwise. The error is
+    /// `Error::UnknownName` if the chromosome name is not in the index.
+    pub fn view_tid_by_name(&self, name: &str) -> Result<FastaView<'_>> {
+        self.view_tid(self.tid_by_name(name)?)
+    }
+
+    fn tid_by_name(&self, name: &str) -> Result<usize> {
+        self.fasta_index
+            .tid(name)
+            .ok_or_else(|| Error::UnknownName(name.to_owned()))
+    }
+
+    /// Return a reference to the `Fai` that contains information from the fasta index.
+    ///
+    /// Returns a reference to `Fai`.
+    pub fn fai(&self) -> &Fai {
+        &self.fasta_index
+    }
+
+    /// Limit the size of queries on this fasta file (see `limits::Limits`).
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Resolve chromosome names that are not in the index with `aliases`, see
+    /// `Fai::with_aliases`.
+    pub fn with_aliases(mut self, aliases: Aliases) -> Self {
+        self.fasta_index = self.fasta_index.with_aliases(aliases);
+        self
+    }
+
+    /// Enable counting of queries (see `metrics::Metrics`).
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = Some(Metrics::default());
+        self
+    }
+
+    /// Return the metrics of this fasta file, `None` if they are not enabled.
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Return the limits for queries on this fasta file.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Iterate over windows of `size` bases on chromosome tid, starting every `step` bases. The
+    /// last window is truncated at the end of the chromosome.
+    ///
+    /// Returns an iterator of `(start, stop, FastaView)` if successful, `Error::Truncated` if
+    /// the chromosome extends beyond the end of the fasta data, Error otherwise.
+    pub fn windows(&self, tid: usize, size: usize, step: usize) -> Result<Windows<'_>> {
+        if size == 0 || step == 0 {
+            return Err(Error::InvalidArgument(
+                "Window size and step should be at least 1".to_owned(),
+            ));
+        }
+        let len = self.fasta_index.size(tid)?;
+        self.limits.check_view(size.min(len))?;
+        // check the whole chromosome once, so the views of the windows can't fail
+        self.checked_offset(tid, 0, len)?;
+        Ok(Windows {
+            fasta: self,
+            tid,
+            len,
+            size,
+            step,
+            pos: 0,
+        })
+    }
+
+    /// Iterate over all chromosomes in the order of the index.
+    ///
+    /// Returns an iterator of `(name, FastaView)` for every chromosome, with an `Error` for a
+    /// chromosome that cannot be viewed (e.g. a truncated fasta file).
+    pub fn iter(&self) -> Sequences<'_> {
+        Sequences {
+            fasta: self,
+            tid: 0,
+        }
+    }
+
+    /// Split chromosome `tid` in consecutive chunks of `chunk_size` bases and digest every chunk
+    /// with `hasher`. The last chunk can be shorter. `hasher` receives the bases of the chunk
+    /// without line endings, so the digests don't depend on the line width of the fasta file.
+    /// The digests can be published as a manifest (or combined into a hash tree) to verify
+    /// parts of a reference without reading the complete chromosome.
+    ///
+    /// Returns the digest of every chunk in order, or an `Error` for an unknown tid, a chunk
+    /// size of 0 or a truncated fasta file.
+    pub fn hash_chunks<H, F>(&self, tid: usize, chunk_size: usize, mut hasher: F) -> Result<Vec<H>>
+    where
+        F: FnMut(&[u8]) -> H,
+    {
+        let mut buf = Vec::with_capacity(chunk_size);
+        Ok(self
+            .windows(tid, chunk_size, chunk_size)?
+            .map(|(_, _, view)| {
+                buf.clear();
+                buf.extend(view.bases());
+                hasher(&buf)
+            })
+            .collect())
+    }
+}
+
+/// Iterator over fixed size windows of a chromosome, created with `IndexedFasta::windows`.
+pub struct Windows<'a> {
+    fasta: &'a IndexedFasta,
+    tid: usize,
+    len: usize,
+    size: usize,
+    step: usize,
+    pos: usize,
+}
+
+impl<'a> Iterator for Windows<'a> {
+    type Item = (usize, usize, FastaView<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.len {
+            return None;
+        }
+        let start = self.pos;
+        let stop = self.len.min(start + self.size);
+        // no more windows after the one that reaches the end of the chromosome
+        self.pos = if stop == self.len {
+            self.len
+        } else {
+            start + self.step
+        };
+        let view = self
+            .fasta
+            .view(self.tid, start, stop)
+            .expect("Windows are checked by IndexedFasta::windows");
+        Some((start, stop, view))
+    }
+}
+
+/// Iterator over the chromosomes of a fasta file, created with `IndexedFasta::iter`.
+pub struct Sequences<'a> {
+    fasta: &'a IndexedFasta,
+    tid: usize,
+}
+
+impl<'a> Iterator for Sequences<'a> {
+    type Item = Result<(&'a str, FastaView<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let name = self.fasta.fasta_index.name(self.tid).ok()?;
+        let view = self.fasta.view_tid(self.tid);
+        self.tid += 1;
+        Some(view.map(|v| (name.as_str(), v)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.fasta.fasta_index.name_map.len() - self.tid;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Sequences<'a> {}
+
+impl<'a> IntoIterator for &'a IndexedFasta {
+    type Item = Result<(&'a str, FastaView<'a>)>;
+    type IntoIter = Sequences<'a>;
+
+    fn into_iter(self) -> Sequences<'a> {
+        self.iter()
+    }
+}
+
+/// A view of a slice of the fasta file bounded by provided coordinates
+#[derive(Clone)]
+pub struct FastaView<'a>(
+    &'a [u8],
+    /// The number of bases in the view.
+    usize,
+    /// The position and line layout, for a view of an indexed fasta.
+    Option<Layout>,
+    /// The view before it was read from, to seek in.
+    Origin<'a>,
+);
+
+/// The bytes and the number of bases of a view when it was created.
+#[derive(Clone, Copy)]
+pub(crate) struct Origin<'a> {
+    data: &'a [u8],
+    len: usize,
+}
+
+/// The position of a view in its chromosome and the line layout of the chromosome.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Layout {
+    /// The zero-based position of the first base of the view.
+    start: usize,
+    line_bases: usize,
+    line_width: usize,
+}
+
+impl Layout {
+    /// The layout of a view from `start` on the chromosome of `record`.
+    pub(crate) fn new(record: FaiRecord, start: usize) -> Self {
+        Layout {
+            start,
+            line_bases: record.line_bases,
+            line_width: record.line_width,
+        }
+    }
+
+    /// The layout of a view from `start` of a sequence without line breaks.
+    pub(crate) fn unwrapped(start: usize) -> Self {
+        Layout {
+            start,
+            line_bases: usize::MAX,
+            line_width: usize::MAX,
+        }
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Create a view on `raw` bytes of fasta sequence, for instance the byte range of a region
+    /// (see `Fai::offset`) that was read by other means. Line breaks are skipped like in any
+    /// other view. The bases are counted once to know the length of the view.
+    pub fn new(raw: &'a [u8]) -> Self {
+        let len = raw.iter().filter(|&&b| b & 192 == 64).count();
+        FastaView(raw, len, None, Origin { data: raw, len })
+    }
+
+    /// A view of `len` bases in `data` at the position and line layout of `layout`.
+    pub(crate) fn with_layout(data: &'a [u8], len: usize, layout: Layout) -> Self {
+        FastaView(data, len, Some(layout), Origin { data, len })
+    }
+
+    /// The base at zero-based position `i` of the view. For a view of an `IndexedFasta` the byte
+    /// is found with the line width of the chromosome, for a view created with `FastaView::new`
+    /// the bases up to `i` are iterated.
+    ///
+    /// Returns the base, or `None` if `i` is not smaller than `len_bases`.
+    #[inline]
+    pub fn at(&self, i: usize) -> Option<u8> {
+        self.byte_index(i).and_then(|j| self.0.get(j)).copied()
+    }
+
+    /// The index in the view bytes of base `i`.
+    #[inline]
+    fn byte_index(&self, i: usize) -> Option<usize> {
+        if i >= self.1 {
+            return None;
+        }
+        match self.2 {
+            Some(l) => {
+                let column = l.start % l.line_bases;
+                let p = column + i;
+                Some(p / l.line_bases * l.line_width + p % l.line_bases - column)
+            }
+            None => self
+                .0
+                .iter()
+                .enumerate()
+                .filter(|(_, &b)| b & 192 == 64)
+                .nth(i)
+                .map(|(j, _)| j),
+        }
+    }
+
+    /// Skip `bytes` bytes that contain `bases` bases, and the line break that follows them.
+    pub(crate) fn advance(&mut self, bytes: usize, bases: usize) {
+        let rest = &self.0[bytes..];
+        let line_break = match &mut self.2 {
+            Some(l) => {
+                l.start += bases;
+                // `\n` or `\r\n` as in the index, the last line may lack it
+                if bases > 0 && l.start % l.line_bases == 0 {
+                    (l.line_width - l.line_bases).min(rest.len())
+                } else {
+                    0
+                }
+            }
+            None => rest.iter().take_while(|&&b| b & 192 != 64).count(),
+        };
+        self.0 = &rest[line_break..];
+        self.1 = self.1.saturating_sub(bases);
+    }
+
+    /// The number of bases in the view. For a view of an `IndexedFasta` this is calculated from
+    /// the coordinates of the view, so it assumes a valid fasta file without characters other
+    /// than bases and line breaks.
+    #[inline]
+    pub fn len_bases(&self) -> usize {
+        self.1
+    }
+
+    /// Whether the view contains no bases.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.1 == 0
+    }
+
+    /// The zero-based chromosome position of the first base of the view, `None` for a view
+    /// created with `FastaView::new`.
+    pub fn start(&self) -> Option<usize> {
+        self.2.map(|l| l.start)
+    }
+
+    /// Count the occurences of A, C, G, T, N, and other in the current view. This function does
+    /// not differentiate between upper or lower case bases, use `count_bases_masked` to count
+    /// soft-masked bases separately.
+    ///
+    /// Returns a `BasecCounts` object.
+    pub fn count_bases(&self) -> BaseCounts {
+        let classes = count_classes(self.0);
+        BaseCounts::from_classes(|c| classes[c] + classes[c | MASKED])
+    }
+
+    /// Count the occurences of A, C, G, T, N, and other in the current view separately for
+    /// uppercase (unmasked) and lowercase (soft-masked) bases.
+    ///
+    /// Returns a `MaskedBaseCounts` object.
+    pub fn count_bases_masked(&self) -> MaskedBaseCounts {
+        let classes = count_classes(self.0);
+        MaskedBaseCounts {
+            unmasked: BaseCounts::from_classes(|c| classes[c]),
+            masked: BaseCounts::from_classes(|c| classes[c | MASKED]),
+        }
+    }
+
+    /// Iterator over the bases in the current view. Bases are returned as `u8` representations of
+    /// the `char`s in the fasta file. Keep only that chars between 164 and 128 (effectively
+    /// skipping newlines). The iterator knows the number of remaining bases, see `len_bases`,
+    /// and can be reversed to walk the view from the end.
+    pub fn bases(&self) -> Bases<'a> {
+        Bases {
+            iter: self.0.iter(),
+            remaining: self.1,
+        }
+    }
+
+    /// The bases in the current view. When the view doesn't span a line break, like most short
+    /// regions, the bases are borrowed from the fasta data, otherwise they are copied without
+    /// the line breaks.
+    pub fn sequence(&self) -> Cow<'a, [u8]> {
+        // a view created with `FastaView::new` may end with a line break
+        let end = self
+            .0
+            .iter()
+            .rposition(|&b| b & 192 == 64)
+            .map_or(0, |i| i + 1);
+        if end == self.1 {
+            Cow::Borrowed(&self.0[..end])
+        } else {
+            Cow::Owned(self.bases().copied().collect())
+        }
+    }
+
+    /// Write the bases in the current view to `w` without line breaks. The bases are written
+    /// directly from the view, one line at a time, so nothing is allocated.
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        for line in self.line_chunks() {
+            w.write_all(line)?;
+        }
+        Ok(())
+    }
+
+    /// Iterator over the slices of bases between the line breaks of the current view, borrowed
+    /// from the fasta data. For a view of an `IndexedFasta` the slices are calculated from the
+    /// line width of the chromosome, for a view created with `FastaView::new` the bytes are
+    /// scanned for line breaks.
+    pub fn line_chunks(&self) -> LineChunks<'a> {
+        LineChunks {
+            data: self.0,
+            remaining: self.1,
+            layout: self.2,
+        }
+    }
+
+    /// Iterator over the bases in the current view with their zero-based chromosome position,
+    /// as `(position, base)`. For a view created with `FastaView::new` the positions start at 0.
+    pub fn bases_with_pos(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (usize, u8)> + ExactSizeIterator + 'a {
+        let start = self.start().unwrap_or(0);
+        (start..start + self.1)
+            .zip(self.bases())
+            .map(|(pos, &b)| (pos, b))
+    }
 }
 
-/// The `IndexFasta` can be used to open a fasta file that has a valid .fai index file.
-pub struct IndexedFasta {
-    mmap: Mmap,
-    fasta_index: Fai,
+/// Iterator over the bases of a `FastaView`, created with `FastaView::bases`.
+pub struct Bases<'a> {
+    iter: std::slice::Iter<'a, u8>,
+    remaining: usize,
 }
 
-impl IndexedFasta {
-    /// Open a fasta file from path `P`. It is assumed that it has a valid .fai index file. The
-    /// .fai file is created by appending .fai to the fasta file.
-    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let mut fai_path = path.as_ref().as_os_str().to_owned();
-        fai_path.push(".fai");
-        let fasta_index = Fai::from_file(&fai_path)?;
+impl<'a> Iterator for Bases<'a> {
+    type Item = &'a u8;
 
-        let file = File::open(path)?;
-        let mmap = unsafe { MmapOptions::new().map(&file)? };
-        Ok(IndexedFasta { mmap, fasta_index })
+    #[inline]
+    fn next(&mut self) -> Option<&'a u8> {
+        let b = self.iter.find(|&&b| b & 192 == 64)?;
+        self.remaining = self.remaining.saturating_sub(1);
+        Some(b)
     }
 
-    /// Use tid, start and end to calculate a slice on the Fasta file. Use this view to iterate
-    /// over the bases.
-    ///
-    /// Returns FastaView for the provided chromsome, start, end if successful, Error otherwise.
-    pub fn view(&self, tid: usize, start: usize, stop: usize) -> io::Result<FastaView> {
-        if start > stop {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Invalid query interval",
-            ));
-        }
-
-        let (start_byte, stop_byte) = self.fasta_index.offset(tid, start, stop)?;
-        //println!("offset for chr {}:{}-{} is {}-{}", tid, start, stop, start_byte, stop_byte);
-        Ok(FastaView(&self.mmap[start_byte..stop_byte]))
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
     }
+}
 
-    /// Use tid to return a view of an entire chromosome.
-    ///
-    /// Returns FastaView for the provided chromsome indicated by tid if successful, Error otherwise.
-    pub fn view_tid(&self, tid: usize) -> io::Result<FastaView> {
-        let (start_byte, stop_byte) = self.fasta_index.offset_tid(tid)?;
-        //println!("offset for chr {}:{}-{} is {}-{}", tid, start, stop, start_byte, stop_byte);
-        Ok(FastaView(&self.mmap[start_byte..stop_byte]))
+impl<'a> DoubleEndedIterator for Bases<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a u8> {
+        let b = self.iter.rfind(|&&b| b & 192 == 64)?;
+        self.remaining = self.remaining.saturating_sub(1);
+        Some(b)
     }
+}
 
-    /// Return a reference to the `Fai` that contains information from the fasta index.
-    ///
-    /// Returns a reference to `Fai`.
-    pub fn fai(&self) -> &Fai {
-        &self.fasta_index
-    }
+impl<'a> ExactSizeIterator for Bases<'a> {}
+
+/// Iterator over the slices of bases between line breaks, created with `FastaView::line_chunks`.
+pub struct LineChunks<'a> {
+    data: &'a [u8],
+    remaining: usize,
+    layout: Option<Layout>,
 }
 
-/// A view of a slice of the fasta file bounded by provided coordinates
-pub struct FastaView<'a>(&'a [u8]);
+impl<'a> Iterator for LineChunks<'a> {
+    type Item = &'a [u8];
 
-impl<'a> FastaView<'a> {
-    /// Count the occurences of A, C, G, T, N, and other in the current view. This function does
-    /// not differentiate between upper or lower case bases.
-    ///
-    /// Returns a `BasecCounts` object.
-    pub fn count_bases(&self) -> BaseCounts {
-        let mut bc: BaseCounts = Default::default();
-
-        for b in self.bases() {
-            let v: u8 = b << 3;
-            if v ^ 8 == 0 {
-                bc.a += 1;
-            } else if v ^ 24 == 0 {
-                bc.c += 1;
-            } else if v ^ 56 == 0 {
-                bc.g += 1;
-            } else if v ^ 112 == 0 {
-                bc.n += 1;
-            } else if v ^ 160 == 0 {
-                bc.t += 1;
-            } else {
-                bc.other += 1;
+    fn next(&mut self) -> Option<&'a [u8]> {
+        match &mut self.layout {
+            Some(l) => {
+                if self.remaining == 0 {
+                    return None;
+                }
+                let column = l.start % l.line_bases;
+                let n = self
+                    .remaining
+                    .min(l.line_bases - column)
+                    .min(self.data.len());
+                let (chunk, rest) = self.data.split_at(n);
+                self.remaining = if rest.is_empty() {
+                    0
+                } else {
+                    self.remaining - n
+                };
+                let line_break = if self.remaining > 0 {
+                    l.line_width - l.line_bases
+                } else {
+                    0
+                };
+                self.data = rest.get(line_break..).unwrap_or_default();
+                l.start += n;
+                Some(chunk)
+            }
+            None => {
+                let start = self.data.iter().position(|&b| b & 192 == 64)?;
+                let rest = &self.data[start..];
+                let n = rest
+                    .iter()
+                    .position(|&b| b & 192 != 64)
+                    .unwrap_or(rest.len());
+                self.data = &rest[n..];
+                self.remaining = self.remaining.saturating_sub(n);
+                Some(&rest[..n])
             }
         }
-
-        bc
     }
+}
 
-    /// Iterator over the bases in the current view. Bases are returned as `u8` representations of
-    /// the `char`s in the fasta file. Keep only that chars between 164 and 128 (effectively
-    /// skipping newlines)
-    pub fn bases(&self) -> impl Iterator<Item = &'a u8> {
-        self.0.iter().filter(|&&b| b & 192 == 64)
+/// Panics if `i` is not smaller than `len_bases`, see `FastaView::at`.
+impl<'a> Index<usize> for FastaView<'a> {
+    type Output = u8;
+
+    fn index(&self, i: usize) -> &u8 {
+        match self.byte_index(i).and_then(|j| self.0.get(j)) {
+            Some(b) => b,
+            None => panic!(
+                "base index {} out of range for a view of {} bases",
+                i, self.1
+            ),
+        }
     }
 }
 
-/// Returns a newly allocated, utf8-validated string with the sequence data in `Self`
-impl<'a> ToString for FastaView<'a> {
-    fn to_string(&self) -> String {
-        String::from_utf8(self.bases().cloned().collect()).unwrap()
+/// Formats the bases of the view without line breaks. `to_string` returns them as a newly
+/// allocated string, use `FastaView::write_to` to write them without allocating.
+impl<'a> fmt::Display for FastaView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in self.line_chunks() {
+            // the bytes of a line are all in the ascii range 64..128
+            f.write_str(std::str::from_utf8(line).map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
     }
 }
 
+/// Reads the bases without line breaks. Whole lines are copied at once, see
+/// `FastaView::line_chunks`.
 impl<'a> Read for FastaView<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let mut read = 0;
-        let mut skipped = 0;
-        for (t, s) in buf.iter_mut().zip(self.0.iter().filter(|&&c| {
-            let base = c & 192 == 64;
-            if !base {
-                skipped += 1;
-            }
-            base
-        })) {
-            *t = *s;
-            read += 1;
+        while read < buf.len() {
+            let Some(line) = self.line_chunks().next() else {
+                break;
+            };
+            let n = line.len().min(buf.len() - read);
+            buf[read..read + n].copy_from_slice(&line[..n]);
+            read += n;
+            // the line starts after the bytes that are not bases, if any
+            let skipped = line.as_ptr() as usize - self.0.as_ptr() as usize;
+            self.advance(skipped + n, n);
         }
-        self.0 = &self.0[(skipped + read)..];
         Ok(read)
     }
 }
 
+/// Serves the slices of `FastaView::line_chunks` without copying.
+impl<'a> BufRead for FastaView<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.line_chunks().next().unwrap_or_default())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(line) = self.line_chunks().next() {
+            let amt = amt.min(line.len());
+            let skipped = line.as_ptr() as usize - self.0.as_ptr() as usize;
+            self.advance(skipped + amt, amt);
+        }
+    }
+}
+
+/// Seeks to a base position in the view as it was created, before any bases were read. Seeking
+/// before the start or after the end of the view fails with `io::ErrorKind::InvalidInput`.
+impl<'a> Seek for FastaView<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let origin = self.3;
+        let position = origin.len - self.1;
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(d) => (origin.len as u64).checked_add_signed(d),
+            SeekFrom::Current(d) => (position as u64).checked_add_signed(d),
+        }
+        .filter(|&t| t <= origin.len as u64)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Seek outside of the view"))?
+            as usize;
+
+        let layout = self.2.map(|l| Layout {
+            start: l.start - position,
+            ..l
+        });
+        let mut view = FastaView(origin.data, origin.len, layout, origin);
+        let byte = view.byte_index(target).unwrap_or(origin.data.len());
+        view.0 = &origin.data[byte..];
+        view.1 = origin.len - target;
+        if let Some(l) = &mut view.2 {
+            l.start += target;
+        }
+        *self = view;
+        Ok(target as u64)
+    }
+}
+
 /// Object that contains count occurrences of the most common bases in DNA genome references: A, C, G,
 /// T, N and other.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BaseCounts {
     pub a: usize,
     pub c: usize,
@@ -330,6 +1569,184 @@ impl Default for BaseCounts {
     }
 }
 
+impl BaseCounts {
+    /// The number of counted bases.
+    pub fn total(&self) -> usize {
+        self.a + self.c + self.g + self.t + self.n + self.other
+    }
+
+    /// The GC fraction of the A, C, G and T bases. N and other bases are excluded.
+    ///
+    /// Returns NaN when there are no A, C, G or T bases.
+    pub fn gc_fraction(&self) -> f64 {
+        gc_fraction(self.c + self.g, self.a + self.t)
+    }
+
+    /// The AT fraction of the A, C, G and T bases. N and other bases are excluded.
+    ///
+    /// Returns NaN when there are no A, C, G or T bases.
+    pub fn at_fraction(&self) -> f64 {
+        gc_fraction(self.a + self.t, self.c + self.g)
+    }
+
+    /// The fraction of N bases of all bases.
+    ///
+    /// Returns NaN when there are no bases.
+    pub fn n_fraction(&self) -> f64 {
+        self.n as f64 / self.total() as f64
+    }
+
+    /// Build the counts from a function that returns the count of a base class.
+    #[inline]
+    fn from_classes<F: Fn(usize) -> usize>(count: F) -> BaseCounts {
+        BaseCounts {
+            a: count(CLASS_A),
+            c: count(CLASS_C),
+            g: count(CLASS_G),
+            t: count(CLASS_T),
+            n: count(CLASS_N),
+            other: count(CLASS_OTHER),
+        }
+    }
+}
+
+impl Add for BaseCounts {
+    type Output = BaseCounts;
+
+    fn add(mut self, rhs: BaseCounts) -> BaseCounts {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for BaseCounts {
+    fn add_assign(&mut self, rhs: BaseCounts) {
+        *self += &rhs;
+    }
+}
+
+impl AddAssign<&BaseCounts> for BaseCounts {
+    fn add_assign(&mut self, rhs: &BaseCounts) {
+        self.a += rhs.a;
+        self.c += rhs.c;
+        self.g += rhs.g;
+        self.t += rhs.t;
+        self.n += rhs.n;
+        self.other += rhs.other;
+    }
+}
+
+impl Sum for BaseCounts {
+    fn sum<I: Iterator<Item = BaseCounts>>(iter: I) -> BaseCounts {
+        iter.fold(BaseCounts::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a BaseCounts> for BaseCounts {
+    fn sum<I: Iterator<Item = &'a BaseCounts>>(iter: I) -> BaseCounts {
+        iter.fold(BaseCounts::default(), |mut total, c| {
+            total += c;
+            total
+        })
+    }
+}
+
+// Base classes used by the counting lookup table. Lowercase bases have the `MASKED` bit set.
+// Bytes outside of the 64..128 range (newlines) go to their own class.
+const CLASS_A: usize = 0;
+const CLASS_C: usize = 1;
+const CLASS_G: usize = 2;
+const CLASS_T: usize = 3;
+const CLASS_N: usize = 4;
+const CLASS_OTHER: usize = 5;
+const CLASS_SKIPPED: usize = 6;
+const MASKED: usize = 8;
+
+/// The base class of every byte.
+static BASE_CLASS: [u8; 256] = base_class_table();
+
+const fn base_class_table() -> [u8; 256] {
+    let mut t = [CLASS_SKIPPED as u8; 256];
+    let mut b = 64u8;
+    while b < 128 {
+        let class = match b & !32 {
+            b'A' => CLASS_A,
+            b'C' => CLASS_C,
+            b'G' => CLASS_G,
+            b'T' => CLASS_T,
+            b'N' => CLASS_N,
+            _ => CLASS_OTHER,
+        };
+        let masked = if b & 32 == 0 { 0 } else { MASKED };
+        t[b as usize] = (class | masked) as u8;
+        b += 1;
+    }
+    t
+}
+
+/// Count the base class of every byte in `data`. Four separate tables are updated in turn, so
+/// consecutive identical bytes don't wait on each other.
+fn count_classes(data: &[u8]) -> [usize; 16] {
+    let mut tables = [[0usize; 16]; 4];
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        tables[0][BASE_CLASS[chunk[0] as usize] as usize] += 1;
+        tables[1][BASE_CLASS[chunk[1] as usize] as usize] += 1;
+        tables[2][BASE_CLASS[chunk[2] as usize] as usize] += 1;
+        tables[3][BASE_CLASS[chunk[3] as usize] as usize] += 1;
+    }
+    for &b in chunks.remainder() {
+        tables[0][BASE_CLASS[b as usize] as usize] += 1;
+    }
+
+    let mut counts = [0; 16];
+    for table in &tables {
+        for (c, v) in counts.iter_mut().zip(table) {
+            *c += v;
+        }
+    }
+    counts
+}
+
+/// Base counts that keep soft-masked (lowercase) bases apart from the unmasked (uppercase) bases.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct MaskedBaseCounts {
+    pub unmasked: BaseCounts,
+    pub masked: BaseCounts,
+}
+
+impl MaskedBaseCounts {
+    /// The GC fraction of all A, C, G and T bases, masked or not.
+    ///
+    /// Returns NaN when there are no A, C, G or T bases.
+    pub fn gc(&self) -> f64 {
+        let u = &self.unmasked;
+        let m = &self.masked;
+        gc_fraction(u.c + u.g + m.c + m.g, u.a + u.t + m.a + m.t)
+    }
+
+    /// The GC fraction of the unmasked A, C, G and T bases. Soft-masked bases are excluded.
+    ///
+    /// Returns NaN when there are no unmasked A, C, G or T bases.
+    pub fn gc_unmasked(&self) -> f64 {
+        let u = &self.unmasked;
+        gc_fraction(u.c + u.g, u.a + u.t)
+    }
+
+    /// The fraction of soft-masked bases, for example the repeat fraction of a RepeatMasker
+    /// masked reference. All bases are included, also N and other.
+    ///
+    /// Returns NaN when there are no bases.
+    pub fn masked_fraction(&self) -> f64 {
+        let masked = self.masked.total();
+        masked as f64 / (masked + self.unmasked.total()) as f64
+    }
+}
+
+fn gc_fraction(gc: usize, at: usize) -> f64 {
+    gc as f64 / (gc + at) as f64
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -344,13 +1761,146 @@ mod tests {
         assert_eq!(ir.fai().size(2).unwrap(), 100);
         assert_eq!(ir.fai().name(2).unwrap(), "ACGT-25");
         assert!(ir.fai().name(3).is_err());
+
+        assert_eq!(ir.fai().len(), 3);
+        let records: Vec<_> = ir
+            .fai()
+            .records()
+            .map(|r| {
+                let (name, rec) = r.unwrap();
+                (
+                    name,
+                    rec.len(),
+                    rec.offset(),
+                    rec.line_bases(),
+                    rec.line_width(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            records,
+            [
+                ("A-10", 10, 6, 10, 11),
+                ("A-100", 100, 24, 50, 51),
+                ("ACGT-25", 100, 135, 50, 51)
+            ]
+        );
+        assert_eq!(ir.fai().record(2).unwrap().qual_offset(), None);
+        assert!(ir.fai().record(3).is_err());
+    }
+
+    #[test]
+    fn create_fai() {
+        let created = Fai::create("test/genome.fa").unwrap();
+        let mut buf = Vec::new();
+        created.write_to(&mut buf).unwrap();
+        assert_eq!(buf, std::fs::read("test/genome.fa.fai").unwrap());
+
+        let dir = std::env::temp_dir().join(format!("faimm-create-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fa = dir.join("crlf.fa");
+        std::fs::write(&fa, "> one desc\r\nACGT\r\nAC\r\n>two\nAAA").unwrap();
+        Fai::create_and_write(&fa).unwrap();
+        let ir = IndexedFasta::from_file(&fa).unwrap();
+        assert_eq!(ir.fai().names(), vec!["one", "two"]);
+        assert_eq!(ir.view_tid(0).unwrap().to_string(), "ACGTAC");
+        assert_eq!(ir.view(0, 3, 5).unwrap().to_string(), "TA");
+        assert_eq!(ir.view_tid(1).unwrap().to_string(), "AAA");
+
+        std::fs::write(&fa, ">bad\nACGT\nAC\nACGT\n").unwrap();
+        assert!(Fai::create(&fa).is_err());
+        std::fs::write(&fa, ">dup\nACGT\n>dup\nACGT\n").unwrap();
+        assert!(Fai::create(&fa).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fai_line_endings() {
+        let dir = std::env::temp_dir().join(format!("faimm-crlf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fai = dir.join("crlf.fa.fai");
+        std::fs::write(&fai, "one\t10\t5\t10\t11\r\ntwo\t20\t21\t10\t11\r\n").unwrap();
+        assert_eq!(Fai::from_file(&fai).unwrap().size(1).unwrap(), 20);
+        assert_eq!(Fai::from_file_lazy(&fai).unwrap().size(1).unwrap(), 20);
+
+        std::fs::write(&fai, "one 10  5\t10 11\r\n\r\ntwo\t20\t21\t10\t11\n\n").unwrap();
+        match Fai::from_file(&fai) {
+            Err(Error::MalformedFai { line: 1, message }) => {
+                assert!(message.contains("from_file_lenient"))
+            }
+            _ => panic!("Expected a malformed index"),
+        }
+        let lenient = Fai::from_file_lenient(&fai).unwrap();
+        assert_eq!(lenient.names(), vec!["one", "two"]);
+        assert_eq!(lenient.offset_tid(1).unwrap(), (21, 43));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fai_lazy() {
+        let ir = IndexedFasta::from_file_lazy("test/genome.fa").unwrap();
+        assert_eq!(ir.fai().names(), vec!["A-10", "A-100", "ACGT-25"]);
+        assert_eq!(ir.fai().size(2).unwrap(), 100);
+        assert!(ir.fai().size(3).is_err());
+        assert_eq!(
+            ir.view(2, 38, 62).unwrap().to_string(),
+            "CCCCCCCCCCCCGGGGGGGGGGGG"
+        );
+        assert_eq!(ir.view_tid(0).unwrap().to_string(), "AAAAAAAAAA");
+
+        let dir = std::env::temp_dir().join(format!("faimm-lazy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fai = dir.join("bad.fa.fai");
+        std::fs::write(&fai, "one\t10\t5\t10\t11\ntwo\tten\t21\t10\t11").unwrap();
+        let lazy = Fai::from_file_lazy(&fai).unwrap();
+        assert_eq!(lazy.size(0).unwrap(), 10);
+        assert!(matches!(
+            lazy.size(1),
+            Err(Error::MalformedFai { line: 2, .. })
+        ));
+        assert!(matches!(
+            Fai::from_file(&fai),
+            Err(Error::MalformedFai { line: 2, .. })
+        ));
+        assert!(matches!(
+            IndexedFasta::from_file(dir.join("missing.fa")),
+            Err(Error::MissingIndex(_))
+        ));
+        std::fs::write(&fai, "").unwrap();
+        assert!(Fai::from_file_lazy(&fai).unwrap().names().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_fai() {
+        let fai = Fai::from_file("test/genome.fa.fai").unwrap();
+        let dir = std::env::temp_dir().join(format!("faimm-write-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("genome.fa.fai");
+        fai.to_file(&out).unwrap();
+        assert_eq!(
+            std::fs::read(&out).unwrap(),
+            std::fs::read("test/genome.fa.fai").unwrap()
+        );
+        let lazy = Fai::from_file_lazy(&out).unwrap();
+        let mut buf = Vec::new();
+        lazy.write_to(&mut buf).unwrap();
+        assert_eq!(buf, std::fs::read(&out).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
     fn view() {
         let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
         assert_eq!(ir.view(0, 0, 10).unwrap().to_string(), "AAAAAAAAAA");
-        assert!(ir.view(0, 0, 11).is_err());
+        assert!(matches!(
+            ir.view(0, 0, 11),
+            Err(Error::OutOfRange {
+                tid: 0,
+                len: 10,
+                ..
+            })
+        ));
 
         assert_eq!(
             ir.view(2, 38, 62).unwrap().to_string(),
@@ -361,6 +1911,115 @@ mod tests {
             "GTTTTTTTTTTTTTTTTTTTTTTTTT"
         );
         assert!(ir.view(0, 120, 130).is_err());
+        assert!(matches!(
+            ir.view(2, 60, 40),
+            Err(Error::InvalidInterval {
+                start: 60,
+                stop: 40
+            })
+        ));
+    }
+
+    #[test]
+    fn line_endings() {
+        for data in [
+            &b">a\r\nACGT\r\nacgt"[..],
+            b">a\nACGT\nacgt",
+            b">a\r\nACGT\r\nacgt\r\n",
+        ] {
+            let fa = IndexedFasta::index_source(data.to_vec()).unwrap();
+            fa.check_index().unwrap();
+            assert!(fa.validate().unwrap().is_valid());
+            let mut v = fa.view_tid(0).unwrap();
+            assert_eq!(v.to_string(), "ACGTacgt");
+            assert_eq!(fa.view(0, 4, 8).unwrap().sequence(), &b"acgt"[..]);
+
+            let mut buf = [0; 3];
+            assert_eq!(v.read(&mut buf).unwrap(), 3);
+            assert_eq!(v.fill_buf().unwrap(), b"T");
+            v.consume(1);
+            // the line break is skipped as a whole
+            assert_eq!(v.start(), Some(4));
+            assert_eq!(v.fill_buf().unwrap(), b"acgt");
+            v.seek(SeekFrom::Start(4)).unwrap();
+            assert_eq!((v.at(0), v.len_bases()), (Some(b'a'), 4));
+            let mut rest = String::new();
+            v.read_to_string(&mut rest).unwrap();
+            assert_eq!(rest, "acgt");
+        }
+        let fa = IndexedFasta::index_source(b">a\nACGT\nAC".to_vec()).unwrap();
+        assert_eq!(fa.view_tid(0).unwrap().to_string(), "ACGTAC");
+        // an empty view at the end of a file without a final line break
+        for pos in [4, 6] {
+            assert_eq!(fa.view(0, pos, pos).unwrap().to_string(), "");
+        }
+        let fai = Fai::from_reader("a\t4\t3\t4\t5\n".as_bytes()).unwrap();
+        let fa = IndexedFasta::from_source(b">a\nACGT".to_vec(), fai);
+        assert_eq!(fa.view(0, 4, 4).unwrap().len_bases(), 0);
+        assert_eq!(fa.view(0, 0, 4).unwrap().to_string(), "ACGT");
+    }
+
+    #[test]
+    fn truncated() {
+        let fai = Fai::from_reader(&b"a\t10\t3\t4\t5\nb\t0\t16\t0\t0\n"[..]).unwrap();
+        let fa = IndexedFasta::from_source(b">a\nACGT\nACGT\n".to_vec(), fai);
+        assert_eq!(fa.view(0, 2, 6).unwrap().to_string(), "GTAC");
+        assert!(matches!(
+            fa.view(0, 6, 10),
+            Err(Error::Truncated {
+                tid: 0,
+                offset: 15,
+                size: 13
+            })
+        ));
+        assert!(matches!(fa.view_tid(0), Err(Error::Truncated { .. })));
+        assert!(matches!(fa.windows(0, 2, 2), Err(Error::Truncated { .. })));
+        assert!(matches!(
+            fa.hash_chunks(0, 2, |c| c.len()),
+            Err(Error::Truncated { .. })
+        ));
+        assert!(matches!(
+            fa.gc_windows(0, 2, 2),
+            Err(Error::Truncated { .. })
+        ));
+        assert!(matches!(
+            fa.warmup(&[(0, 0, 10)]),
+            Err(Error::Truncated { .. })
+        ));
+        // an empty sequence has line_bases 0
+        assert!(matches!(
+            fa.view_tid(1),
+            Err(Error::Truncated { offset: 16, .. })
+        ));
+
+        for fai in ["a\t10\t3\t0\t0", "a\t10\t3\t4\t3"] {
+            assert!(matches!(
+                Fai::from_reader(fai.as_bytes()),
+                Err(Error::MalformedFai { line: 1, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn view_by_name() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert_eq!(
+            ir.view_by_name("ACGT-25", 48, 52).unwrap().to_string(),
+            "CCGG"
+        );
+        assert_eq!(
+            ir.view_tid_by_name("A-10").unwrap().to_string(),
+            "AAAAAAAAAA"
+        );
+        assert!(matches!(
+            ir.view_by_name("chr1", 0, 1).err(),
+            Some(Error::UnknownName(_))
+        ));
+        assert!(matches!(
+            ir.view_tid_by_name("chr1").err(),
+            Some(Error::UnknownName(_))
+        ));
+        assert!(ir.view_by_name("A-10", 0, 11).is_err());
     }
 
     #[test]
@@ -374,6 +2033,53 @@ mod tests {
         assert!(ir.view_tid(3).is_err());
     }
 
+    #[test]
+    fn windows() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let w: Vec<_> = ir
+            .windows(0, 4, 3)
+            .unwrap()
+            .map(|(s, e, v)| (s, e, v.to_string()))
+            .collect();
+        assert_eq!(
+            w,
+            vec![
+                (0, 4, "AAAA".to_string()),
+                (3, 7, "AAAA".to_string()),
+                (6, 10, "AAAA".to_string())
+            ]
+        );
+        let w: Vec<_> = ir.windows(2, 30, 40).unwrap().map(|w| (w.0, w.1)).collect();
+        assert_eq!(w, vec![(0, 30), (40, 70), (80, 100)]);
+        assert!(ir.windows(0, 0, 1).is_err());
+        assert!(ir.windows(3, 1, 1).is_err());
+    }
+
+    #[test]
+    fn sequences() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let seqs = ir.iter();
+        assert_eq!(seqs.len(), 3);
+        let lengths: Vec<_> = seqs
+            .map(|r| r.map(|(name, v)| (name, v.len_bases())))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(lengths, [("A-10", 10), ("A-100", 100), ("ACGT-25", 100)]);
+        let total: usize = (&ir)
+            .into_iter()
+            .map(|r| r.unwrap().1.count_bases().a)
+            .sum();
+        assert_eq!(total, 135);
+
+        let data = std::fs::read("test/genome.fa").unwrap();
+        let truncated = IndexedFasta::from_source(data[..100].to_vec(), ir.fai().clone());
+        let mut seqs = truncated.iter();
+        assert!(seqs.next().unwrap().is_ok());
+        assert!(matches!(seqs.next(), Some(Err(Error::Truncated { .. }))));
+        assert!(seqs.next().unwrap().is_err());
+        assert!(seqs.next().is_none());
+    }
+
     #[test]
     fn view_bases() {
         let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
@@ -386,6 +2092,22 @@ mod tests {
         assert_eq!(b.next(), None);
     }
 
+    #[test]
+    fn hash_chunks() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let chunks = ir.hash_chunks(2, 30, |b| b.to_vec()).unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[3].len(), 10);
+        assert_eq!(
+            chunks.concat(),
+            ir.view_tid(2).unwrap().to_string().into_bytes()
+        );
+        let sums = ir.hash_chunks(1, 50, |b| b.len()).unwrap();
+        assert_eq!(sums, vec![50, 50]);
+        assert!(ir.hash_chunks(2, 0, |b| b.len()).is_err());
+        assert!(ir.hash_chunks(3, 10, |b| b.len()).is_err());
+    }
+
     #[test]
     fn view_counts() {
         let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
@@ -399,16 +2121,188 @@ mod tests {
         );
     }
 
+    #[test]
+    fn count_table() {
+        // every byte value, so the remainder and all four tables are used
+        let all: Vec<u8> = (0..=255).collect();
+        let bc = FastaView::new(&all).count_bases();
+        assert_eq!((bc.a, bc.c, bc.g, bc.t, bc.n), (2, 2, 2, 2, 2));
+        assert_eq!(bc.other, 64 - 10);
+        let counts = count_classes(&all[..255]);
+        assert_eq!(counts[CLASS_SKIPPED], 191);
+        assert_eq!(counts[CLASS_A | MASKED], 1);
+    }
+
+    #[test]
+    fn view_counts_masked() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let v = FastaView::new(b"ACgt\nnaCG\nxX");
+        let mbc = v.count_bases_masked();
+        assert_eq!(
+            mbc.unmasked,
+            BaseCounts {
+                a: 1,
+                c: 2,
+                g: 1,
+                other: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            mbc.masked,
+            BaseCounts {
+                a: 1,
+                g: 1,
+                t: 1,
+                n: 1,
+                other: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(mbc.gc_unmasked(), 0.75);
+        assert_eq!(mbc.gc(), 4.0 / 7.0);
+        assert_eq!(mbc.masked_fraction(), 0.5);
+        assert_eq!(
+            ir.view(2, 0, 10)
+                .unwrap()
+                .count_bases_masked()
+                .masked_fraction(),
+            0.0
+        );
+        assert!(ir.view(2, 0, 0).unwrap().count_bases_masked().gc().is_nan());
+    }
+
+    #[test]
+    fn base_counts_arithmetic() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let parts: Vec<_> = [(0, 30), (30, 70), (70, 100)]
+            .iter()
+            .map(|&(s, e)| ir.view(2, s, e).unwrap().count_bases())
+            .collect();
+        let all = ir.view_tid(2).unwrap().count_bases();
+        assert_eq!(parts.iter().sum::<BaseCounts>(), all);
+        assert_eq!(parts.clone().into_iter().sum::<BaseCounts>(), all);
+        let mut acc = parts[0].clone() + parts[1].clone();
+        acc += &parts[2];
+        assert_eq!(acc, all);
+
+        assert_eq!(all.total(), 100);
+        assert_eq!(all.gc_fraction(), 0.5);
+        assert_eq!(all.at_fraction(), 0.5);
+        assert_eq!(all.n_fraction(), 0.0);
+        assert_eq!(
+            ir.view(2, 0, 30).unwrap().count_bases().at_fraction(),
+            25.0 / 30.0
+        );
+        assert!(BaseCounts::default().gc_fraction().is_nan());
+        assert!(BaseCounts::default().n_fraction().is_nan());
+    }
+
+    #[test]
+    fn view_len() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let v = ir.view(2, 45, 55).unwrap();
+        assert_eq!(v.len_bases(), 10);
+        assert!(!v.is_empty());
+        let mut bases = v.bases();
+        assert_eq!(bases.len(), 10);
+        bases.nth(3);
+        assert_eq!(bases.len(), 6);
+        assert_eq!(ir.view_tid(1).unwrap().bases().len(), 100);
+        assert!(ir.view(1, 7, 7).unwrap().is_empty());
+
+        let rev: Vec<u8> = ir.view(2, 45, 55).unwrap().bases().rev().copied().collect();
+        assert_eq!(rev, b"GGGGGCCCCC");
+
+        let v = ir.view(2, 48, 52).unwrap();
+        assert_eq!(v.start(), Some(48));
+        let pos: Vec<_> = v.bases_with_pos().collect();
+        assert_eq!(pos, [(48, b'C'), (49, b'C'), (50, b'G'), (51, b'G')]);
+        assert_eq!(v.bases_with_pos().next_back(), Some((51, b'G')));
+        let g = ir
+            .view_tid(2)
+            .unwrap()
+            .bases_with_pos()
+            .find(|&(_, b)| b == b'G');
+        assert_eq!(g, Some((50, b'G')));
+
+        assert!(matches!(
+            ir.view(2, 10, 50).unwrap().sequence(),
+            Cow::Borrowed(_)
+        ));
+        let crossing = ir.view(2, 45, 55).unwrap().sequence();
+        assert!(matches!(crossing, Cow::Owned(_)));
+        assert_eq!(&crossing[..], b"CCCCCGGGGG");
+
+        let v = ir.view(2, 20, 100).unwrap();
+        let chunks: Vec<_> = v.line_chunks().collect();
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), [30, 50]);
+        assert_eq!(chunks.concat(), v.to_string().as_bytes());
+        let chunks: Vec<_> = ir.view(1, 10, 50).unwrap().line_chunks().collect();
+        assert_eq!(chunks, [&[b'A'; 40][..]]);
+        assert_eq!(ir.view(1, 50, 50).unwrap().line_chunks().count(), 0);
+        let crlf = IndexedFasta::index_source(b">a\r\nACG\r\nTAC\r\nG\r\n".to_vec()).unwrap();
+        let chunks: Vec<_> = crlf.view(0, 1, 7).unwrap().line_chunks().collect();
+        assert_eq!(chunks, [&b"CG"[..], b"TAC", b"G"]);
+
+        let raw = FastaView::new(b"ACgt\r\nNN\nA");
+        let chunks: Vec<_> = raw.line_chunks().collect();
+        assert_eq!(chunks, [&b"ACgt"[..], b"NN", b"A"]);
+        assert_eq!(&raw.sequence()[..], b"ACgtNNA");
+        assert_eq!(format!("{}", raw), "ACgtNNA");
+        let mut written = Vec::new();
+        raw.write_to(&mut written).unwrap();
+        assert_eq!(written, b"ACgtNNA");
+        assert_eq!(raw.start(), None);
+        assert_eq!(raw.bases_with_pos().last(), Some((6, b'A')));
+        assert_eq!(raw.len_bases(), 7);
+        let mut both = raw.bases();
+        assert_eq!((both.next(), both.next_back()), (Some(&b'A'), Some(&b'A')));
+        assert_eq!((both.next_back(), both.len()), (Some(&b'N'), 4));
+        assert_eq!(raw.bases().len(), raw.bases().count());
+        assert!(FastaView::new(b"\n").is_empty());
+    }
+
+    #[test]
+    fn view_at() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        for (start, stop) in [(0, 100), (37, 64), (50, 51), (49, 100)] {
+            let v = ir.view(2, start, stop).unwrap();
+            let bases: Vec<u8> = v.bases().copied().collect();
+            let at: Vec<u8> = (0..v.len_bases()).map(|i| v.at(i).unwrap()).collect();
+            assert_eq!(at, bases);
+            assert_eq!(v.at(stop - start), None);
+        }
+        let v = ir.view_tid(2).unwrap();
+        assert_eq!((v[24], v[25], v[99]), (b'A', b'C', b'T'));
+
+        let mut v = ir.view(2, 45, 55).unwrap();
+        let mut buf = [0; 5];
+        v.read_exact(&mut buf).unwrap();
+        assert_eq!((v.at(0), v.at(4), v.at(5)), (Some(b'G'), Some(b'G'), None));
+
+        let raw = FastaView::new(b"AC\ngT\r\nN");
+        assert_eq!((raw.at(2), raw[4], raw.at(5)), (Some(b'g'), b'N', None));
+    }
+
+    #[test]
+    #[should_panic]
+    fn view_index_out_of_range() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let _ = ir.view(0, 2, 5).unwrap()[3];
+    }
+
     #[test]
     fn read_view() {
         let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
         let mut buf = vec![0; 25];
         let mut v = ir.view_tid(2).unwrap();
-        println!("{}", v.to_string());
+        println!("{}", v);
         assert_eq!(v.read(&mut buf).unwrap(), 25);
         assert_eq!(buf, vec![b'A'; 25]);
         assert_eq!(v.read(&mut buf).unwrap(), 25);
         assert_eq!(buf, vec![b'C'; 25]);
+        assert_eq!(v.len_bases(), 50);
         assert_eq!(v.read(&mut buf).unwrap(), 25);
         assert_eq!(buf, vec![b'G'; 25]);
 
@@ -419,5 +2313,56 @@ mod tests {
         assert_eq!(buf2, vec![b'T'; 10]);
         assert_eq!(v.read(&mut buf2).unwrap(), 5);
         assert_eq!(&buf2[0..5], vec![b'T'; 5].as_slice());
+
+        v.seek(SeekFrom::Start(48)).unwrap();
+        assert_eq!(v.fill_buf().unwrap(), b"CC");
+        v.consume(1);
+        assert_eq!(v.stream_position().unwrap(), 49);
+        assert_eq!(v.at(0), Some(b'C'));
+        v.consume(1);
+        assert_eq!(v.fill_buf().unwrap(), [[b'G'; 25], [b'T'; 25]].concat());
+        assert_eq!(v.seek(SeekFrom::End(-1)).unwrap(), 99);
+        assert_eq!(v.read(&mut buf2).unwrap(), 1);
+        assert!(v.seek(SeekFrom::Current(1)).is_err());
+        assert!(v.fill_buf().unwrap().is_empty());
+
+        let mut raw = FastaView::new(b"\nAC\r\ngt\n\nNA\n");
+        let mut all = Vec::new();
+        assert_eq!(raw.read(&mut buf2[..3]).unwrap(), 3);
+        raw.read_to_end(&mut all).unwrap();
+        assert_eq!((&buf2[..3], &all[..]), (&b"ACg"[..], &b"tNA"[..]));
+        raw.seek(SeekFrom::Current(-3)).unwrap();
+        let mut lines = String::new();
+        raw.read_line(&mut lines).unwrap();
+        assert_eq!(lines, "tNA");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json() {
+        let fai = Fai::from_file("test/genome.fa.fai").unwrap();
+        let json = serde_json::to_string(&fai).unwrap();
+        assert!(json.starts_with(r#"[{"name":"A-10","len":10,"offset":6,"#));
+        let back: Fai = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.names(), fai.names());
+        assert_eq!(
+            back.offset(2, 45, 55).unwrap(),
+            fai.offset(2, 45, 55).unwrap()
+        );
+
+        let lazy = Fai::from_file_lazy("test/genome.fa.fai").unwrap();
+        assert_eq!(serde_json::to_string(&lazy).unwrap(), json);
+
+        let duplicate = format!("[{},{}", &json[1..json.find('}').unwrap() + 1], &json[1..]);
+        assert!(serde_json::from_str::<Fai>(&duplicate).is_err());
+
+        let counts = IndexedFasta::from_file("test/genome.fa")
+            .unwrap()
+            .view_tid(2)
+            .unwrap()
+            .count_bases();
+        let json = serde_json::to_string(&counts).unwrap();
+        assert_eq!(json, r#"{"a":25,"c":25,"g":25,"t":25,"n":0,"other":0}"#);
+        assert_eq!(serde_json::from_str::<BaseCounts>(&json).unwrap(), counts);
     }
 }
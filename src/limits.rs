@@ -0,0 +1,102 @@
+//! Guards against queries that are too large.
+//!
+//! Views on an mmapped file are cheap, but turning a view of a complete chromosome into a
+//! `String`, decompressing it from a bgzip file, or counting a batch of a million regions is
+//! not. A service that passes user requests to faimm can set `Limits` to reject such queries
+//! with `Error::ViewTooLong` or `Error::BatchTooLarge` before any work is done. By default
+//! nothing is limited.
+//!
+//! # Example
+//! ```
+//! use faimm::{Error, IndexedFasta};
+//! use faimm::limits::Limits;
+//! let fa = IndexedFasta::from_file("test/genome.fa")
+//!     .expect("Error opening fa")
+//!     .with_limits(Limits::new().max_view_len(50));
+//! assert!(fa.view(2, 0, 50).is_ok());
+//! assert!(matches!(fa.view_tid(2), Err(Error::ViewTooLong { len: 100, max: 50 })));
+//! ```
+
+use crate::{Error, Result};
+
+/// Configurable limits for the size of queries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub(crate) max_view_len: Option<usize>,
+    pub(crate) max_batch_size: Option<usize>,
+}
+
+impl Limits {
+    /// Create limits that don't limit anything.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the maximum number of bases in a single view.
+    pub fn max_view_len(mut self, len: usize) -> Self {
+        self.max_view_len = Some(len);
+        self
+    }
+
+    /// Set the maximum number of regions in a single batch request.
+    pub fn max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = Some(size);
+        self
+    }
+
+    /// Return `Error::ViewTooLong` if a view of `len` bases is not allowed.
+    pub fn check_view(&self, len: usize) -> Result<()> {
+        match self.max_view_len {
+            Some(max) if len > max => Err(Error::ViewTooLong { len, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Return `Error::BatchTooLarge` if a batch of `size` regions is not allowed.
+    pub fn check_batch(&self, size: usize) -> Result<()> {
+        match self.max_batch_size {
+            Some(max) if size > max => Err(Error::BatchTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexedFasta;
+
+    #[test]
+    fn limits() {
+        let limits = Limits::new();
+        assert!(limits.check_view(usize::MAX).is_ok());
+        assert!(limits.check_batch(usize::MAX).is_ok());
+
+        let limits = limits.max_view_len(10).max_batch_size(2);
+        assert!(limits.check_view(10).is_ok());
+        assert!(matches!(
+            limits.check_batch(3),
+            Err(Error::BatchTooLarge { size: 3, max: 2 })
+        ));
+    }
+
+    #[test]
+    fn limited_fasta() {
+        let fa = IndexedFasta::from_file("test/genome.fa")
+            .unwrap()
+            .with_limits(Limits::new().max_view_len(10));
+        assert_eq!(fa.view_tid(0).unwrap().to_string(), "AAAAAAAAAA");
+        assert!(matches!(
+            fa.view(1, 0, 11),
+            Err(Error::ViewTooLong { len: 11, max: 10 })
+        ));
+        assert!(fa
+            .windows(1, 10, 10)
+            .unwrap()
+            .all(|(_, _, v)| v.bases().count() == 10));
+        assert!(matches!(
+            fa.windows(1, 20, 10),
+            Err(Error::ViewTooLong { .. })
+        ));
+    }
+}
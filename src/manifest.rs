@@ -0,0 +1,368 @@
+//! Manifests that describe a reference bundle (feature `manifest`).
+//!
+//! A `Manifest` lists every sequence of an indexed fasta file with its length, the MD5 digest of
+//! the complete sequence and the MD5 digests of fixed size chunks of the sequence. It also
+//! records which companion files (`.fai`, bgzip compressed fasta, `.gzi`) are present next to the
+//! fasta file. Digests are calculated over the uppercased bases without line endings, the same
+//! convention as the `M5` tag of a sequence dictionary.
+//!
+//! The manifest is written as tab separated text. The first line is `#faimm-manifest` with the
+//! format version, followed by a `chunk_size` line, a `file` line for every companion file and a
+//! `sequence` line with the name, length, digest and comma separated chunk digests of every
+//! sequence.
+//!
+//! # Example
+//! ```
+//! use faimm::manifest::Manifest;
+//! let manifest = Manifest::create("test/genome.fa", 50).expect("Cannot create manifest");
+//! let problems = manifest.verify("test/genome.fa").expect("Cannot verify");
+//! assert!(problems.is_empty());
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use md5::{Digest, Md5};
+
+use crate::{Error, IndexedFasta, Result};
+
+/// The companion file extensions that are recorded in a manifest.
+const COMPANION_FILES: [&str; 4] = [".fai", ".gz", ".gz.fai", ".gz.gzi"];
+
+/// The digests of a single sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestSequence {
+    pub name: String,
+    pub len: usize,
+    pub md5: String,
+    pub chunks: Vec<String>,
+}
+
+/// Description of a reference bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub chunk_size: usize,
+    pub files: Vec<String>,
+    pub sequences: Vec<ManifestSequence>,
+}
+
+/// A difference between a manifest and the reference bundle on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestMismatch {
+    /// A companion file in the manifest is missing.
+    MissingFile(String),
+    /// A companion file is present, but not in the manifest.
+    ExtraFile(String),
+    /// A sequence in the manifest is missing from the index.
+    MissingSequence(String),
+    /// A sequence in the index is not in the manifest.
+    ExtraSequence(String),
+    /// A sequence has a different length.
+    Length {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A chunk of a sequence has a different digest.
+    Chunk { name: String, index: usize },
+    /// A sequence has a different digest.
+    Digest { name: String },
+}
+
+impl fmt::Display for ManifestMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManifestMismatch::MissingFile(file) => write!(f, "Missing file {}", file),
+            ManifestMismatch::ExtraFile(file) => write!(f, "File {} not in manifest", file),
+            ManifestMismatch::MissingSequence(name) => write!(f, "Missing sequence {}", name),
+            ManifestMismatch::ExtraSequence(name) => {
+                write!(f, "Sequence {} not in manifest", name)
+            }
+            ManifestMismatch::Length {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Sequence {} has length {}, expected {}",
+                name, found, expected
+            ),
+            ManifestMismatch::Chunk { name, index } => {
+                write!(f, "Digest mismatch in chunk {} of sequence {}", index, name)
+            }
+            ManifestMismatch::Digest { name } => {
+                write!(f, "Digest mismatch of sequence {}", name)
+            }
+        }
+    }
+}
+
+impl Manifest {
+    /// Create the manifest of the indexed fasta file at path `P`, with chunk digests for every
+    /// `chunk_size` bases.
+    ///
+    /// Returns the manifest if successful, the errors of opening and reading the fasta file
+    /// otherwise.
+    pub fn create<P: AsRef<Path>>(fasta: P, chunk_size: usize) -> Result<Self> {
+        let fa = IndexedFasta::from_file(&fasta)?;
+        let mut sequences = Vec::new();
+        for (tid, name) in fa.fai().names().into_iter().enumerate() {
+            let mut sequence = Md5::new();
+            let chunks = fa.hash_chunks(tid, chunk_size, |bases| {
+                let upper = bases.to_ascii_uppercase();
+                sequence.update(&upper);
+                format!("{:x}", Md5::digest(&upper))
+            })?;
+            sequences.push(ManifestSequence {
+                name: name.to_owned(),
+                len: fa.fai().size(tid)?,
+                md5: format!("{:x}", sequence.finalize()),
+                chunks,
+            });
+        }
+
+        Ok(Manifest {
+            chunk_size,
+            files: companion_files(fasta.as_ref()),
+            sequences,
+        })
+    }
+
+    /// Read a manifest from the file at path `P`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Manifest::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a manifest from `r`.
+    ///
+    /// Returns the manifest if successful, `Error::Malformed` for an invalid manifest.
+    pub fn read_from<R: BufRead>(r: R) -> Result<Self> {
+        let invalid = |line: usize, message: &str| Error::Malformed {
+            format: "manifest",
+            line: Some(line),
+            message: message.to_owned(),
+        };
+
+        let mut lines = r.lines();
+        match lines.next().transpose()? {
+            Some(l) if l == "#faimm-manifest\t1" => {}
+            _ => return Err(invalid(1, "Expected manifest header")),
+        }
+
+        let mut manifest = Manifest {
+            chunk_size: 0,
+            files: Vec::new(),
+            sequences: Vec::new(),
+        };
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let number = i + 2;
+            let p: Vec<_> = line.split('\t').collect();
+            match p[..] {
+                ["chunk_size", size] => {
+                    manifest.chunk_size = size
+                        .parse()
+                        .map_err(|_| invalid(number, "Invalid chunk size"))?;
+                }
+                ["file", file] => manifest.files.push(file.to_owned()),
+                ["sequence", name, len, md5, chunks] => {
+                    manifest.sequences.push(ManifestSequence {
+                        name: name.to_owned(),
+                        len: len
+                            .parse()
+                            .map_err(|_| invalid(number, "Invalid sequence length"))?,
+                        md5: md5.to_owned(),
+                        chunks: chunks
+                            .split(',')
+                            .filter(|c| !c.is_empty())
+                            .map(str::to_owned)
+                            .collect(),
+                    });
+                }
+                [""] => {}
+                _ => return Err(invalid(number, "Invalid manifest line")),
+            }
+        }
+
+        if manifest.chunk_size == 0 {
+            return Err(Error::Malformed {
+                format: "manifest",
+                line: None,
+                message: "Missing chunk size".to_owned(),
+            });
+        }
+        Ok(manifest)
+    }
+
+    /// Write the manifest to a new file at path `P`.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut bw = BufWriter::new(File::create(path)?);
+        self.write_to(&mut bw)?;
+        bw.flush()?;
+        Ok(())
+    }
+
+    /// Write the manifest to `w`.
+    pub fn write_to<W: Write>(&self, mut w: W) -> Result<()> {
+        writeln!(w, "#faimm-manifest\t1")?;
+        writeln!(w, "chunk_size\t{}", self.chunk_size)?;
+        for file in &self.files {
+            writeln!(w, "file\t{}", file)?;
+        }
+        for s in &self.sequences {
+            writeln!(
+                w,
+                "sequence\t{}\t{}\t{}\t{}",
+                s.name,
+                s.len,
+                s.md5,
+                s.chunks.join(",")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Compare the manifest with the indexed fasta file at path `P` and its companion files.
+    /// Sequence digests are only compared when the length matches.
+    ///
+    /// Returns all differences, an empty `Vec` if the bundle matches the manifest. An `Error`
+    /// is returned when the fasta file cannot be read.
+    pub fn verify<P: AsRef<Path>>(&self, fasta: P) -> Result<Vec<ManifestMismatch>> {
+        let mut mismatches = Vec::new();
+
+        let files = companion_files(fasta.as_ref());
+        for file in &self.files {
+            if !files.contains(file) {
+                mismatches.push(ManifestMismatch::MissingFile(file.clone()));
+            }
+        }
+        for file in files {
+            if !self.files.contains(&file) {
+                mismatches.push(ManifestMismatch::ExtraFile(file));
+            }
+        }
+
+        let found = Manifest::create(fasta, self.chunk_size)?;
+        for expected in &self.sequences {
+            let Some(s) = found.sequences.iter().find(|s| s.name == expected.name) else {
+                mismatches.push(ManifestMismatch::MissingSequence(expected.name.clone()));
+                continue;
+            };
+            if s.len != expected.len {
+                mismatches.push(ManifestMismatch::Length {
+                    name: s.name.clone(),
+                    expected: expected.len,
+                    found: s.len,
+                });
+                continue;
+            }
+            for (index, (a, b)) in expected.chunks.iter().zip(&s.chunks).enumerate() {
+                if a != b {
+                    mismatches.push(ManifestMismatch::Chunk {
+                        name: s.name.clone(),
+                        index,
+                    });
+                }
+            }
+            if s.md5 != expected.md5 {
+                mismatches.push(ManifestMismatch::Digest {
+                    name: s.name.clone(),
+                });
+            }
+        }
+        for s in &found.sequences {
+            if !self.sequences.iter().any(|e| e.name == s.name) {
+                mismatches.push(ManifestMismatch::ExtraSequence(s.name.clone()));
+            }
+        }
+
+        Ok(mismatches)
+    }
+}
+
+/// The names of the companion files that exist next to `fasta`.
+fn companion_files(fasta: &Path) -> Vec<String> {
+    let name = fasta
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    COMPANION_FILES
+        .iter()
+        .map(|ext| format!("{}{}", name, ext))
+        .filter(|file| fasta.with_file_name(file).is_file())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create() {
+        let manifest = Manifest::create("test/genome.fa", 30).unwrap();
+        assert_eq!(
+            manifest.files,
+            vec![
+                "genome.fa.fai",
+                "genome.fa.gz",
+                "genome.fa.gz.fai",
+                "genome.fa.gz.gzi"
+            ]
+        );
+        assert_eq!(manifest.sequences.len(), 3);
+        let s = &manifest.sequences[0];
+        assert_eq!((s.name.as_str(), s.len), ("A-10", 10));
+        // md5 of AAAAAAAAAA
+        assert_eq!(s.md5, "16c52c6e8326c071da771e66dc6e9e57");
+        assert_eq!(s.chunks, vec![s.md5.clone()]);
+        assert_eq!(manifest.sequences[2].chunks.len(), 4);
+
+        let mut buf = Vec::new();
+        manifest.write_to(&mut buf).unwrap();
+        assert_eq!(Manifest::read_from(&buf[..]).unwrap(), manifest);
+        assert!(matches!(
+            Manifest::read_from(&b"chunk_size\t10\n"[..]),
+            Err(Error::Malformed { line: Some(1), .. })
+        ));
+    }
+
+    #[test]
+    fn verify() {
+        let mut manifest = Manifest::create("test/genome.fa", 30).unwrap();
+        manifest.files.push("genome.fa.dict".to_owned());
+        manifest.sequences[0].len = 11;
+        manifest.sequences[2].chunks[1] = "0".repeat(32);
+        manifest.sequences[2].md5 = "0".repeat(32);
+        manifest.sequences.remove(1);
+        manifest.sequences.push(ManifestSequence {
+            name: "chr1".to_owned(),
+            len: 1,
+            md5: String::new(),
+            chunks: Vec::new(),
+        });
+
+        let mismatches = manifest.verify("test/genome.fa").unwrap();
+        assert_eq!(
+            mismatches,
+            vec![
+                ManifestMismatch::MissingFile("genome.fa.dict".to_owned()),
+                ManifestMismatch::Length {
+                    name: "A-10".to_owned(),
+                    expected: 11,
+                    found: 10
+                },
+                ManifestMismatch::Chunk {
+                    name: "ACGT-25".to_owned(),
+                    index: 1
+                },
+                ManifestMismatch::Digest {
+                    name: "ACGT-25".to_owned()
+                },
+                ManifestMismatch::MissingSequence("chr1".to_owned()),
+                ManifestMismatch::ExtraSequence("A-100".to_owned()),
+            ]
+        );
+    }
+}
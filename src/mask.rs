@@ -0,0 +1,139 @@
+//! Writing a copy of a fasta file with masked regions.
+//!
+//! `IndexedFasta::write_masked` copies the fasta data and hard-masks (N) or soft-masks
+//! (lowercase) the bases in the intervals of a `RegionSet`, like `bedtools maskfasta`. Everything
+//! else, the headers, the line wrapping and the line endings, is copied unchanged, so the index
+//! of the original file is also valid for the masked copy. `IndexedFasta::write_masked_file`
+//! writes the index next to the copy.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::mask::MaskMode;
+//! use faimm::region::RegionSet;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let mut regions = RegionSet::new(fa.fai());
+//! regions.insert(0, 2, 8).unwrap();
+//! let mut out = Vec::new();
+//! fa.write_masked(&regions, MaskMode::Hard, &mut out).expect("Cannot mask");
+//! assert!(out.starts_with(b">A-10\nAANNNNNNAA\n>A-100\n"));
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::region::RegionSet;
+use crate::{fai_path, IndexedFasta, Result};
+
+/// How the bases of masked regions are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaskMode {
+    /// Replace the bases by N.
+    #[default]
+    Hard,
+    /// Write the bases in lowercase.
+    Soft,
+}
+
+impl MaskMode {
+    fn apply(self, b: u8) -> u8 {
+        match self {
+            MaskMode::Hard => b'N',
+            MaskMode::Soft => b.to_ascii_lowercase(),
+        }
+    }
+}
+
+impl IndexedFasta {
+    /// Write a copy of the fasta data to `w` with the bases in `regions` masked with `mode`.
+    /// Line breaks in the masked regions are kept.
+    ///
+    /// Returns `Error::OutOfRange` for an interval beyond the end of its chromosome in this fasta
+    /// file, `Error::Truncated` for a fasta file that is shorter than its index, Error otherwise.
+    pub fn write_masked<W: Write>(
+        &self,
+        regions: &RegionSet,
+        mode: MaskMode,
+        mut w: W,
+    ) -> Result<()> {
+        let mut ranges = regions
+            .iter()
+            .map(|(tid, start, end)| self.checked_offset(tid, start, end))
+            .collect::<Result<Vec<_>>>()?;
+        // the chromosomes of an index are not necessarily in file order
+        ranges.sort_unstable();
+
+        let data = self.data();
+        let mut buf = Vec::new();
+        let mut pos = 0;
+        for (from, to) in ranges {
+            let from = from.max(pos);
+            if to <= from {
+                continue;
+            }
+            w.write_all(&data[pos..from])?;
+            for chunk in data[from..to].chunks(1 << 16) {
+                buf.clear();
+                buf.extend(
+                    chunk
+                        .iter()
+                        .map(|&b| if b & 192 == 64 { mode.apply(b) } else { b }),
+                );
+                w.write_all(&buf)?;
+            }
+            pos = to;
+        }
+        w.write_all(&data[pos..])?;
+        Ok(())
+    }
+
+    /// Write a masked copy of the fasta data to a new file at path `P` and a copy of the index
+    /// to `P.fai`, see `IndexedFasta::write_masked`.
+    pub fn write_masked_file<P: AsRef<Path>>(
+        &self,
+        regions: &RegionSet,
+        mode: MaskMode,
+        path: P,
+    ) -> Result<()> {
+        let mut w = BufWriter::new(File::create(&path)?);
+        self.write_masked(regions, mode, &mut w)?;
+        w.flush()?;
+        self.fai().to_file(fai_path(path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut regions = RegionSet::new(fa.fai());
+        regions.insert(2, 20, 30).unwrap();
+        regions.insert(2, 45, 55).unwrap();
+        regions.insert(2, 25, 27).unwrap();
+        regions.insert(1, 99, 100).unwrap();
+
+        let mut out = Vec::new();
+        fa.write_masked(&regions, MaskMode::Soft, &mut out).unwrap();
+        let original = std::fs::read("test/genome.fa").unwrap();
+        assert_eq!(out.len(), original.len());
+        let masked = IndexedFasta::from_source(out, fa.fai().clone());
+        assert_eq!(
+            masked.view(2, 18, 32).unwrap().to_string(),
+            "AAaaaaacccccCC"
+        );
+        assert_eq!(masked.view(2, 44, 56).unwrap().to_string(), "CcccccgggggG");
+        assert_eq!(masked.view(1, 98, 100).unwrap().to_string(), "Aa");
+        assert_eq!(masked.view_tid(0).unwrap().to_string(), "AAAAAAAAAA");
+
+        let mut out = Vec::new();
+        fa.write_masked(&regions, MaskMode::Hard, &mut out).unwrap();
+        let masked = IndexedFasta::from_source(out, fa.fai().clone());
+        assert_eq!(masked.view(2, 44, 56).unwrap().to_string(), "CNNNNNNNNNNG");
+        assert_eq!(masked.view(2, 45, 55).unwrap().count_bases().n, 10);
+    }
+}
@@ -0,0 +1,141 @@
+//! Lightweight counters for services that embed faimm.
+//!
+//! Metrics are disabled by default. When they are enabled with `IndexedFasta::with_metrics` or
+//! `BgzfIndexedFasta::with_metrics`, every view request is counted with a few atomic
+//! increments. A `MetricsSnapshot` of the counters can be taken at any time, for instance to
+//! export them to a monitoring system.
+//!
+//! Views on an mmapped file are lazy: the pages are read from disk when the caller reads the
+//! bases, which can't be timed by faimm. For bgzip compressed files the time spent decompressing
+//! blocks is recorded instead, together with the hits and misses of the block cache.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa")
+//!     .expect("Error opening fa")
+//!     .with_metrics();
+//! fa.view(2, 0, 50).unwrap();
+//! assert!(fa.view(2, 0, 500).is_err());
+//! let snapshot = fa.metrics().expect("Metrics enabled").snapshot();
+//! assert_eq!((snapshot.queries, snapshot.errors, snapshot.bases), (2, 1, 50));
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::Result;
+
+/// Counters of the requests to a fasta file.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    queries: AtomicU64,
+    errors: AtomicU64,
+    bases: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    decompress_nanos: AtomicU64,
+}
+
+/// The values of the counters in `Metrics` at one point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// The number of requested views, including failed requests.
+    pub queries: u64,
+    /// The number of requests that returned an error.
+    pub errors: u64,
+    /// The number of bases in the returned views.
+    pub bases: u64,
+    /// The number of bgzf blocks that were found in the block cache.
+    pub cache_hits: u64,
+    /// The number of bgzf blocks that had to be decompressed.
+    pub cache_misses: u64,
+    /// The time spent decompressing bgzf blocks.
+    pub decompress_time: Duration,
+}
+
+impl Metrics {
+    /// Take a snapshot of the current values.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            queries: self.queries.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            bases: self.bases.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            decompress_time: Duration::from_nanos(self.decompress_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Set all counters to zero.
+    pub fn reset(&self) {
+        for counter in [
+            &self.queries,
+            &self.errors,
+            &self.bases,
+            &self.cache_hits,
+            &self.cache_misses,
+            &self.decompress_nanos,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Count a request for a view of `bases` bases that returned `result`.
+    pub(crate) fn record_query<T>(&self, result: &Result<T>, bases: usize) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        match result {
+            Ok(_) => self.bases.fetch_add(bases as u64, Ordering::Relaxed),
+            Err(_) => self.errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Count a lookup in the block cache.
+    #[cfg_attr(not(feature = "bgzf"), allow(dead_code))]
+    pub(crate) fn record_cache(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add the time it took to decompress a block.
+    #[cfg_attr(not(feature = "bgzf"), allow(dead_code))]
+    pub(crate) fn record_decompress(&self, time: Duration) {
+        self.decompress_nanos
+            .fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, IndexedFasta};
+
+    #[test]
+    fn counters() {
+        let metrics = Metrics::default();
+        metrics.record_query(&Ok(()), 10);
+        metrics.record_query::<()>(&Err(Error::UnknownTid(3)), 10);
+        metrics.record_cache(true);
+        metrics.record_decompress(Duration::from_micros(5));
+        let s = metrics.snapshot();
+        assert_eq!((s.queries, s.errors, s.bases), (2, 1, 10));
+        assert_eq!((s.cache_hits, s.cache_misses), (1, 0));
+        assert_eq!(s.decompress_time, Duration::from_micros(5));
+        metrics.reset();
+        assert_eq!(metrics.snapshot(), MetricsSnapshot::default());
+    }
+
+    #[test]
+    fn disabled() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert!(fa.metrics().is_none());
+        let fa = fa.with_metrics();
+        fa.view_tid(1).unwrap();
+        fa.view_by_name("A-10", 0, 5).unwrap();
+        assert_eq!(fa.metrics().unwrap().snapshot().bases, 105);
+    }
+}
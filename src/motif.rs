@@ -0,0 +1,181 @@
+//! Motif search with IUPAC ambiguity codes.
+//!
+//! A motif like `CANNTG` (an E-box) is a pattern of IUPAC nucleotide codes. Every code matches
+//! the bases it stands for, ignoring case, so `N` matches A, C, G and T. Ambiguity codes and other
+//! characters in the sequence itself never match, so a gap of N doesn't match a motif of N.
+//! Matches are reported as the zero-based start position relative to the start of the view and
+//! can overlap.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::strand::Strand;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let view = fa.view_tid(2).expect("Cannot get view");
+//! assert_eq!(view.find_motif("AMC").expect("Invalid motif"), vec![23, 24]);
+//! let both = view.find_motif_stranded("AMC").expect("Invalid motif");
+//! assert_eq!(both[2], (73, Strand::Reverse));
+//! ```
+
+use crate::strand::Strand;
+use crate::{Error, FastaView, Result};
+
+const A: u8 = 1;
+const C: u8 = 2;
+const G: u8 = 4;
+const T: u8 = 8;
+
+/// The bases matched by an IUPAC code as a bit mask, `None` for an invalid code.
+fn iupac_mask(code: u8) -> Option<u8> {
+    let mask = match code.to_ascii_uppercase() {
+        b'A' => A,
+        b'C' => C,
+        b'G' => G,
+        b'T' | b'U' => T,
+        b'R' => A | G,
+        b'Y' => C | T,
+        b'S' => C | G,
+        b'W' => A | T,
+        b'K' => G | T,
+        b'M' => A | C,
+        b'B' => C | G | T,
+        b'D' => A | G | T,
+        b'H' => A | C | T,
+        b'V' => A | C | G,
+        b'N' => A | C | G | T,
+        _ => return None,
+    };
+    Some(mask)
+}
+
+/// The mask of a base in the sequence, 0 for anything other than A, C, G or T.
+#[inline]
+fn base_mask(b: u8) -> u8 {
+    match b {
+        b'A' | b'a' => A,
+        b'C' | b'c' => C,
+        b'G' | b'g' => G,
+        b'T' | b't' => T,
+        _ => 0,
+    }
+}
+
+/// The mask of the complementary bases.
+fn complement_mask(mask: u8) -> u8 {
+    (mask & A) << 3 | (mask & C) << 1 | (mask & G) >> 1 | (mask & T) >> 3
+}
+
+/// Parse `motif` into masks.
+fn parse_motif(motif: &str) -> Result<Vec<u8>> {
+    if motif.is_empty() {
+        return Err(Error::InvalidArgument("Empty motif".to_owned()));
+    }
+    motif
+        .bytes()
+        .map(|b| {
+            iupac_mask(b).ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "Invalid IUPAC code {:?} in motif {}",
+                    b as char, motif
+                ))
+            })
+        })
+        .collect()
+}
+
+impl<'a> FastaView<'a> {
+    /// Find all matches of the IUPAC `motif` on the forward strand of the current view.
+    ///
+    /// Returns the start positions of the matches, or `Error::InvalidArgument` for an empty
+    /// motif or an invalid IUPAC code.
+    pub fn find_motif(&self, motif: &str) -> Result<Vec<usize>> {
+        let pattern = parse_motif(motif)?;
+        Ok(self
+            .find_patterns(&[&pattern])
+            .into_iter()
+            .map(|(pos, _)| pos)
+            .collect())
+    }
+
+    /// Find all matches of the IUPAC `motif` on both strands of the current view. A match on
+    /// the reverse strand is a match of the reverse complement of the motif on the forward
+    /// strand. A palindromic motif is only reported on the forward strand.
+    ///
+    /// Returns the start positions and strands of the matches sorted by position, or
+    /// `Error::InvalidArgument` for an empty motif or an invalid IUPAC code.
+    pub fn find_motif_stranded(&self, motif: &str) -> Result<Vec<(usize, Strand)>> {
+        let pattern = parse_motif(motif)?;
+        let rc: Vec<u8> = pattern.iter().rev().map(|&m| complement_mask(m)).collect();
+        if rc == pattern {
+            return Ok(self
+                .find_patterns(&[&pattern])
+                .into_iter()
+                .map(|(pos, _)| (pos, Strand::Forward))
+                .collect());
+        }
+        Ok(self
+            .find_patterns(&[&pattern, &rc])
+            .into_iter()
+            .map(|(pos, i)| (pos, [Strand::Forward, Strand::Reverse][i]))
+            .collect())
+    }
+
+    /// Find all matches of the equally long `patterns`, as `(start, pattern index)`.
+    fn find_patterns(&self, patterns: &[&[u8]]) -> Vec<(usize, usize)> {
+        let len = patterns[0].len();
+        let mut ring = vec![0u8; len];
+        let mut matches = Vec::new();
+        for (i, &b) in self.bases().enumerate() {
+            ring[i % len] = base_mask(b);
+            if i + 1 < len {
+                continue;
+            }
+            let start = i + 1 - len;
+            for (p, pattern) in patterns.iter().enumerate() {
+                let hit = pattern
+                    .iter()
+                    .enumerate()
+                    .all(|(j, &m)| ring[(start + j) % len] & m != 0);
+                if hit {
+                    matches.push((start, p));
+                }
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_motif() {
+        let v = FastaView::new(b"ACAGCTGNNcagg\ntgTTCACGTG");
+        assert_eq!(v.find_motif("CANNTG").unwrap(), vec![1, 9, 17]);
+        assert_eq!(v.find_motif("NN").unwrap().len(), 19);
+        assert_eq!(v.find_motif("u").unwrap(), vec![5, 13, 15, 16, 21]);
+        assert!(v.find_motif("").is_err());
+        assert!(v.find_motif("CAX").is_err());
+    }
+
+    #[test]
+    fn stranded() {
+        let v = FastaView::new(b"AACCGTT\nGGTT");
+        // CANNTG is palindromic, so only forward matches
+        assert!(v
+            .find_motif_stranded("CANNTG")
+            .unwrap()
+            .iter()
+            .all(|m| m.1 == Strand::Forward));
+        assert_eq!(
+            v.find_motif_stranded("AAC").unwrap(),
+            vec![
+                (0, Strand::Forward),
+                (4, Strand::Reverse),
+                (8, Strand::Reverse)
+            ]
+        );
+        assert_eq!(complement_mask(A | C), G | T);
+    }
+}
@@ -0,0 +1,147 @@
+//! Interoperability with [noodles](https://crates.io/crates/noodles-fasta) (feature `noodles`).
+//!
+//! A `Fai` converts to and from a `noodles_fasta::fai::Index`, so an index that was read by one
+//! crate doesn't have to be read again by the other. A `FastaView` converts to a
+//! `noodles_fasta::record::Sequence`, and an `IndexedFasta` is a `repository::Adapter`, so it
+//! can back a `noodles_fasta::Repository`, for instance when reading CRAM files.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use noodles_fasta::{fai, record::Sequence, Repository};
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let index = fai::Index::try_from(fa.fai()).expect("Cannot convert index");
+//! assert_eq!(index.as_ref()[1].length(), 100);
+//!
+//! let sequence = Sequence::from(&fa.view(2, 20, 30).expect("Cannot get view"));
+//! assert_eq!(sequence.as_ref(), b"AAAAACCCCC");
+//!
+//! let repository = Repository::new(fa);
+//! let chr = repository.get(b"ACGT-25").expect("Unknown sequence").expect("Cannot read");
+//! assert_eq!(chr.len(), 100);
+//! ```
+
+use std::io;
+use std::num::NonZeroU64;
+
+use indexmap::IndexSet;
+use noodles_fasta::record::{Definition, Sequence};
+use noodles_fasta::repository::Adapter;
+use noodles_fasta::{fai, Record};
+
+use crate::{Error, Fai, FaiRecord, FastaView, IndexedFasta, Records};
+
+/// Fails with `Error::InvalidArgument` for a record with 0 bases per line, like an empty
+/// sequence, which noodles doesn't support.
+impl TryFrom<&Fai> for fai::Index {
+    type Error = Error;
+
+    fn try_from(fai: &Fai) -> Result<Self, Error> {
+        fai.name_map
+            .iter()
+            .enumerate()
+            .map(|(tid, name)| {
+                let r = fai.record(tid)?;
+                let non_zero = |v: usize| {
+                    NonZeroU64::new(v as u64).ok_or_else(|| {
+                        Error::InvalidArgument(format!(
+                            "Sequence {} has no bases per line, noodles requires at least 1",
+                            name
+                        ))
+                    })
+                };
+                Ok(fai::Record::new(
+                    name.as_str(),
+                    r.len as u64,
+                    r.offset as u64,
+                    non_zero(r.line_bases)?,
+                    non_zero(r.line_width)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(fai::Index::from)
+    }
+}
+
+/// Fails with `Error::InvalidArgument` for a name that is not valid UTF-8 or is used twice.
+impl TryFrom<&fai::Index> for Fai {
+    type Error = Error;
+
+    fn try_from(index: &fai::Index) -> Result<Self, Error> {
+        let mut name_map = IndexSet::new();
+        let mut chromosomes = Vec::new();
+        for r in index.as_ref() {
+            let name = std::str::from_utf8(r.name()).map_err(|_| {
+                Error::InvalidArgument(format!("Sequence name {} is not valid UTF-8", r.name()))
+            })?;
+            if !name_map.insert(name.to_owned()) {
+                return Err(Error::InvalidArgument(format!(
+                    "Duplicate sequence name {}",
+                    name
+                )));
+            }
+            chromosomes.push(FaiRecord {
+                len: r.length() as usize,
+                offset: r.position() as usize,
+                line_bases: r.line_base_count().get() as usize,
+                line_width: r.line_width().get() as usize,
+                qual_offset: None,
+            });
+        }
+        Ok(Fai {
+            chromosomes: Records::Parsed(chromosomes),
+            name_map,
+            aliases: None,
+        })
+    }
+}
+
+/// Copies the bases of the view, without line breaks.
+impl From<&FastaView<'_>> for Sequence {
+    fn from(view: &FastaView) -> Self {
+        view.bases().copied().collect()
+    }
+}
+
+/// Reads complete sequences by name, for a `noodles_fasta::Repository`.
+impl Adapter for IndexedFasta {
+    fn get(&mut self, name: &[u8]) -> Option<io::Result<Record>> {
+        let name = std::str::from_utf8(name).ok()?;
+        let tid = self.fai().tid(name)?;
+        let record = self
+            .view_tid(tid)
+            .map(|view| Record::new(Definition::new(name, None), Sequence::from(&view)));
+        Some(record.map_err(io::Error::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_roundtrip() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let index = fai::Index::try_from(fa.fai()).unwrap();
+        let read = fai::fs::read("test/genome.fa.fai").unwrap();
+        assert_eq!(index, read);
+
+        let fai = Fai::try_from(&read).unwrap();
+        let mut written = Vec::new();
+        fai.write_to(&mut written).unwrap();
+        assert_eq!(written, std::fs::read("test/genome.fa.fai").unwrap());
+
+        let empty = Fai::from_reader("empty\t0\t7\t0\t0\n".as_bytes()).unwrap();
+        assert!(fai::Index::try_from(&empty).is_err());
+    }
+
+    #[test]
+    fn repository() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let expected = fa.view_tid(1).unwrap().to_string();
+        let repository = noodles_fasta::Repository::new(fa);
+        let seq = repository.get(b"A-100").unwrap().unwrap();
+        assert_eq!(seq.as_ref().as_ref(), expected.as_bytes());
+        assert!(repository.get(b"chr1").is_none());
+    }
+}
@@ -0,0 +1,217 @@
+//! Dense oligomer (k-mer) composition counts for small k.
+//!
+//! Oligomers are counted on the forward strand in a dense array with `4^k` entries. The index of
+//! an oligomer is its base-4 number with `A=0, C=1, G=2, T=3`, so `AAA` has index 0 and `TTT`
+//! the last index. Counting ignores case and oligomers that contain any other base (N, IUPAC
+//! codes) are skipped.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let counts = fa.view(2, 20, 30).unwrap().count_oligos(2).unwrap();
+//! assert_eq!(counts.get(b"AC"), Some(1));
+//! assert_eq!(counts.total(), 9);
+//!
+//! // CpG observed/expected ratio from the dinucleotide and base counts
+//! let view = fa.view_tid(2).unwrap();
+//! let cg = view.count_dinucleotides()[0b01_10] as f64;
+//! let bases = view.count_bases();
+//! let ratio = cg * bases.total() as f64 / (bases.c * bases.g) as f64;
+//! assert_eq!(ratio, 0.16);
+//! ```
+
+use crate::{Error, FastaView, Result};
+
+/// The largest oligomer size that can be counted.
+pub const MAX_K: usize = 6;
+
+/// Dense counts of all oligomers of length k.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OligoCounts {
+    k: usize,
+    counts: Vec<usize>,
+}
+
+impl OligoCounts {
+    /// Create zeroed counts for oligomers of length `k` (1 up to `MAX_K`).
+    ///
+    /// Returns `Error::InvalidArgument` for an unsupported k.
+    pub fn new(k: usize) -> Result<Self> {
+        if k == 0 || k > MAX_K {
+            return Err(Error::InvalidArgument(format!(
+                "Oligomer size should be between 1 and {}",
+                MAX_K
+            )));
+        }
+        Ok(OligoCounts {
+            k,
+            counts: vec![0; 1 << (2 * k)],
+        })
+    }
+
+    /// The oligomer length.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The dense count array, indexed by the base-4 encoding of the oligomer.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// The total number of counted oligomers.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// The count of `oligo`. Returns `None` if the length differs from k or it contains other
+    /// bases than A, C, G or T.
+    pub fn get(&self, oligo: &[u8]) -> Option<usize> {
+        if oligo.len() != self.k {
+            return None;
+        }
+        let mut index = 0;
+        for &b in oligo {
+            index = (index << 2) | encode(b)? as usize;
+        }
+        Some(self.counts[index])
+    }
+
+    /// The frequency of every oligomer relative to the total count.
+    pub fn frequencies(&self) -> Vec<f64> {
+        let total = self.total() as f64;
+        self.counts.iter().map(|&c| c as f64 / total).collect()
+    }
+
+    /// The oligomer that belongs to `index` in the count array.
+    pub fn oligo(&self, index: usize) -> Vec<u8> {
+        (0..self.k)
+            .rev()
+            .map(|i| b"ACGT"[(index >> (2 * i)) & 3])
+            .collect()
+    }
+
+    /// Add the oligomers of `view` to the counts.
+    pub fn add_view(&mut self, view: &FastaView) {
+        count_into(view.0, self.k, &mut self.counts);
+    }
+}
+
+/// The two bit code of every byte. Other bases reset the oligomer, bytes outside of the 64..128
+/// range (newlines) are skipped like in `FastaView::bases`.
+pub(crate) static CODE: [u8; 256] = code_table();
+pub(crate) const OTHER: u8 = 4;
+pub(crate) const SKIPPED: u8 = 5;
+
+const fn code_table() -> [u8; 256] {
+    let mut t = [SKIPPED; 256];
+    let mut b = 64u8;
+    while b < 128 {
+        t[b as usize] = match b & !32 {
+            b'A' => 0,
+            b'C' => 1,
+            b'G' => 2,
+            b'T' => 3,
+            _ => OTHER,
+        };
+        b += 1;
+    }
+    t
+}
+
+/// Count the oligomers of length `k` in the raw view bytes `data`. `counts` has `4^k` entries.
+#[inline]
+fn count_into(data: &[u8], k: usize, counts: &mut [usize]) {
+    let mask = counts.len() - 1;
+    let mut index = 0;
+    let mut valid = 0;
+    for &b in data {
+        match CODE[b as usize] {
+            SKIPPED => {}
+            OTHER => valid = 0,
+            v => {
+                index = ((index << 2) | v as usize) & mask;
+                valid += 1;
+                if valid >= k {
+                    counts[index] += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Two bit encoding of a base, ignoring case.
+#[inline]
+pub(crate) fn encode(b: u8) -> Option<u8> {
+    match b {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Count all oligomers of length `k` (1 up to `oligo::MAX_K`) in the current view.
+    ///
+    /// Returns an `OligoCounts` object, or `Error::InvalidArgument` for an unsupported k.
+    pub fn count_oligos(&self, k: usize) -> Result<OligoCounts> {
+        let mut counts = OligoCounts::new(k)?;
+        counts.add_view(self);
+        Ok(counts)
+    }
+
+    /// Count the 16 dinucleotides in the current view, indexed like `OligoCounts` with k = 2,
+    /// so `CG` is at index 6.
+    pub fn count_dinucleotides(&self) -> [usize; 16] {
+        let mut counts = [0; 16];
+        count_into(self.0, 2, &mut counts);
+        counts
+    }
+
+    /// Count the 64 trinucleotides in the current view, indexed like `OligoCounts` with k = 3.
+    pub fn count_trinucleotides(&self) -> [usize; 64] {
+        let mut counts = [0; 64];
+        count_into(self.0, 3, &mut counts);
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexedFasta;
+
+    #[test]
+    fn count() {
+        let v = FastaView::new(b"ACgT\nNAC\nGT");
+        let c = v.count_oligos(2).unwrap();
+        assert_eq!(c.counts().len(), 16);
+        assert_eq!(c.get(b"AC"), Some(2));
+        assert_eq!(c.get(b"CG"), Some(2));
+        assert_eq!(c.get(b"GT"), Some(2));
+        assert_eq!(c.get(b"TN"), None);
+        assert_eq!(c.total(), 6);
+        assert_eq!(c.oligo(6), b"CG");
+        assert_eq!(&v.count_dinucleotides()[..], c.counts());
+        let tri = v.count_trinucleotides();
+        assert_eq!(&tri[..], v.count_oligos(3).unwrap().counts());
+        assert_eq!(tri[0b01_10_11], 2);
+        assert_eq!(tri.iter().sum::<usize>(), 4);
+        assert!(matches!(v.count_oligos(0), Err(Error::InvalidArgument(_))));
+        assert!(v.count_oligos(7).is_err());
+    }
+
+    #[test]
+    fn windows() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let per_window: Vec<_> = fa
+            .windows(2, 50, 50)
+            .unwrap()
+            .map(|(_, _, v)| v.count_oligos(6).unwrap().get(b"AAAAAA").unwrap())
+            .collect();
+        assert_eq!(per_window, vec![20, 0]);
+    }
+}
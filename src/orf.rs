@@ -0,0 +1,170 @@
+//! Open reading frames.
+//!
+//! An open reading frame (ORF) starts at a start codon and ends with a stop codon of a
+//! `GeneticCode`, both included, so the bacterial code also finds ORFs that start with `GTG` or
+//! `TTG`. ORFs are searched in all three frames on both strands, in a single pass over the bases
+//! of each strand.
+//! Only the longest ORF is reported for every stop codon, so ORFs don't contain a nested start
+//! codon in the same frame. An ORF that is not closed by a stop codon before the end of the
+//! sequence is not reported.
+//!
+//! # Example
+//! ```
+//! use faimm::translate::GeneticCode;
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! for orf in fa.find_orfs(2, 90, GeneticCode::Standard).expect("Unknown chromosome") {
+//!     println!("{}-{} {} frame {}", orf.start, orf.end, orf.strand, orf.frame);
+//! }
+//! ```
+
+use crate::strand::Strand;
+use crate::translate::GeneticCode;
+use crate::{FastaView, IndexedFasta, Result};
+
+/// An open reading frame. `start` and `end` are zero-based, half-open forward strand
+/// coordinates that include the stop codon. `frame` (0, 1 or 2) is the offset of the first codon
+/// from the start of the sequence on the strand of the ORF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Orf {
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+    pub frame: usize,
+}
+
+impl Orf {
+    /// The length of the ORF in bases, including the stop codon.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the ORF has no bases, which never happens for a found ORF.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Find the ORFs in `bases`, the bases of one strand, as `(start, end, frame)` coordinates on
+/// that strand. The three frames are scanned at the same time.
+fn orfs_on_strand<I: Iterator<Item = u8>>(
+    bases: I,
+    code: GeneticCode,
+    min_len: usize,
+) -> Vec<(usize, usize, usize)> {
+    let mut orfs = Vec::new();
+    let mut open = [None; 3];
+    let mut codon = [0; 3];
+    for (i, b) in bases.enumerate() {
+        codon = [codon[1], codon[2], b];
+        let Some(pos) = i.checked_sub(2) else {
+            continue;
+        };
+        let frame = pos % 3;
+        if open[frame].is_none() && code.is_start(&codon) {
+            open[frame] = Some(pos);
+        } else if code.is_stop(&codon) {
+            if let Some(start) = open[frame].take() {
+                if pos + 3 - start >= min_len {
+                    orfs.push((start, pos + 3, frame));
+                }
+            }
+        }
+    }
+    orfs
+}
+
+impl<'a> FastaView<'a> {
+    /// Find the ORFs of at least `min_len` bases (including the stop codon) with the start and
+    /// stop codons of `code` on both strands of the current view. Coordinates are relative to
+    /// the start of the view.
+    ///
+    /// Returns the ORFs sorted by start position.
+    pub fn find_orfs(&self, min_len: usize, code: GeneticCode) -> Vec<Orf> {
+        let len = self.len_bases();
+        let mut orfs: Vec<Orf> = orfs_on_strand(self.bases().copied(), code, min_len)
+            .into_iter()
+            .map(|(start, end, frame)| Orf {
+                start,
+                end,
+                strand: Strand::Forward,
+                frame,
+            })
+            .collect();
+        orfs.extend(
+            orfs_on_strand(self.rc().bases(), code, min_len)
+                .into_iter()
+                .map(|(start, end, frame)| Orf {
+                    start: len - end,
+                    end: len - start,
+                    strand: Strand::Reverse,
+                    frame,
+                }),
+        );
+        orfs.sort_by_key(|o| (o.start, o.end));
+        orfs
+    }
+}
+
+impl IndexedFasta {
+    /// Find the ORFs of at least `min_len` bases with the start and stop codons of `code` on
+    /// both strands of chromosome tid.
+    ///
+    /// Returns the ORFs sorted by start position, or an `Error` if the tid is unknown.
+    pub fn find_orfs(&self, tid: usize, min_len: usize, code: GeneticCode) -> Result<Vec<Orf>> {
+        Ok(self.view_tid(tid)?.find_orfs(min_len, code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_orfs() {
+        // forward: ATG AAA TGA at 2..11, reverse: TTA CAT (ATG TAA) at 21..27
+        let v = FastaView::new(b"CCATGAA\nATGAGTTACCCATC\nTTACAT");
+        let orfs = v.find_orfs(6, GeneticCode::Standard);
+        assert_eq!(
+            orfs,
+            vec![
+                Orf {
+                    start: 2,
+                    end: 11,
+                    strand: Strand::Forward,
+                    frame: 2
+                },
+                Orf {
+                    start: 21,
+                    end: 27,
+                    strand: Strand::Reverse,
+                    frame: 0
+                },
+            ]
+        );
+        assert_eq!(orfs[0].len(), 9);
+        assert_eq!(v.find_orfs(7, GeneticCode::Standard).len(), 1);
+        assert!(FastaView::new(b"ATGAAA")
+            .find_orfs(0, GeneticCode::Standard)
+            .is_empty());
+
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert!(fa
+            .find_orfs(2, 3, GeneticCode::Standard)
+            .unwrap()
+            .is_empty());
+        assert!(fa.find_orfs(3, 3, GeneticCode::Standard).is_err());
+    }
+
+    #[test]
+    fn genetic_code() {
+        // GTG is a start codon of the bacterial code, TGA codes for W in the mitochondrial code
+        let v = FastaView::new(b"CGTGAAA\nTAGGTGA");
+        assert!(v.find_orfs(3, GeneticCode::Standard).is_empty());
+        let orfs = v.find_orfs(3, GeneticCode::Bacterial);
+        assert_eq!((orfs[0].start, orfs[0].end, orfs[0].frame), (1, 10, 1));
+        assert_eq!(orfs.len(), 1);
+        let mito = GeneticCode::VertebrateMitochondrial;
+        assert_eq!(v.find_orfs(3, mito)[0].end, 10);
+    }
+}
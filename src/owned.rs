@@ -0,0 +1,108 @@
+//! Views that own a reference to the fasta data.
+//!
+//! A `FastaView` borrows the `IndexedFasta`, so it can't be returned from a function that owns
+//! the `IndexedFasta` or be sent to a thread that may outlive it. An `OwnedFastaView` holds a
+//! reference counted pointer to the mapped file instead, and stays valid after the
+//! `IndexedFasta` is dropped. It is cheap to clone and can be sent between threads. The bases
+//! are accessed through a regular `FastaView`, so all analyses are available.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::owned::OwnedFastaView;
+//!
+//! fn load_region() -> OwnedFastaView {
+//!     let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//!     fa.owned_view(2, 45, 55).expect("Cannot get view")
+//! }
+//!
+//! let view = load_region();
+//! let worker = std::thread::spawn(move || view.view().to_string());
+//! assert_eq!(worker.join().unwrap(), "CCCCCGGGGG");
+//! ```
+
+use std::sync::Arc;
+
+use crate::source::SequenceSource;
+use crate::{FastaView, IndexedFasta, Layout, Result};
+
+/// A view that keeps the fasta data it points to alive.
+#[derive(Clone)]
+pub struct OwnedFastaView {
+    source: Arc<dyn SequenceSource>,
+    start_byte: usize,
+    stop_byte: usize,
+    len: usize,
+    layout: Layout,
+}
+
+impl OwnedFastaView {
+    /// Borrow the owned view as a `FastaView`.
+    pub fn view(&self) -> FastaView<'_> {
+        FastaView::with_layout(
+            &self.source.bytes()[self.start_byte..self.stop_byte],
+            self.len,
+            self.layout,
+        )
+    }
+}
+
+impl IndexedFasta {
+    /// Like `IndexedFasta::view`, but the view owns a reference to the fasta data, see the
+    /// `owned` module.
+    ///
+    /// Returns an OwnedFastaView if successful, Error otherwise.
+    pub fn owned_view(&self, tid: usize, start: usize, stop: usize) -> Result<OwnedFastaView> {
+        let (start_byte, stop_byte) = self.byte_range(tid, start, stop)?;
+        let layout = Layout::new(self.fasta_index.record(tid)?, start);
+        Ok(self.owned(start_byte, stop_byte, stop - start, layout))
+    }
+
+    /// Like `IndexedFasta::view_tid`, but the view owns a reference to the fasta data.
+    ///
+    /// Returns an OwnedFastaView if successful, Error otherwise.
+    pub fn owned_view_tid(&self, tid: usize) -> Result<OwnedFastaView> {
+        let (start_byte, stop_byte) = self.byte_range_tid(tid)?;
+        let layout = Layout::new(self.fasta_index.record(tid)?, 0);
+        Ok(self.owned(start_byte, stop_byte, self.fasta_index.size(tid)?, layout))
+    }
+
+    fn owned(
+        &self,
+        start_byte: usize,
+        stop_byte: usize,
+        len: usize,
+        layout: Layout,
+    ) -> OwnedFastaView {
+        OwnedFastaView {
+            source: Arc::clone(&self.source),
+            start_byte,
+            stop_byte,
+            len,
+            layout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limits;
+
+    #[test]
+    fn outlives_fasta() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let expected = fa.view(1, 40, 60).unwrap().to_string();
+        let owned = fa.owned_view(1, 40, 60).unwrap();
+        let tid = fa.owned_view_tid(2).unwrap();
+        drop(fa);
+        assert_eq!(owned.clone().view().to_string(), expected);
+        assert_eq!(tid.view().count_bases().t, 25);
+
+        let limited = IndexedFasta::from_file("test/genome.fa")
+            .unwrap()
+            .with_limits(Limits::new().max_view_len(10));
+        assert!(limited.owned_view_tid(2).is_err());
+        assert!(limited.owned_view(0, 5, 11).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! 2-bit packed sequences.
+//!
+//! Hashing and alignment kernels usually take their input packed with two bits per base.
+//! `FastaView::to_packed` packs the bases of a view directly from the memory map, without an
+//! intermediate ASCII copy. The bases are encoded as A = 0, C = 1, G = 2 and T = 3, four bases per
+//! byte with the first base in the most significant bits. Bases other than A, C, G or T (N and
+//! the other IUPAC codes) are stored as 0 and listed as `(start, end)` blocks in the N-mask.
+//! Soft-masking is not kept.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let packed = fa.view(2, 23, 27).expect("Cannot get view").to_packed();
+//! assert_eq!(packed.len(), 4);
+//! // AACC
+//! assert_eq!(packed.as_bytes(), &[0b0000_0101]);
+//! assert!(packed.n_blocks().is_empty());
+//! ```
+
+use crate::oligo::encode;
+use crate::FastaView;
+
+/// A sequence packed with two bits per base, with the blocks of bases other than A, C, G or T.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PackedSeq {
+    len: usize,
+    data: Vec<u8>,
+    n_blocks: Vec<(usize, usize)>,
+}
+
+impl PackedSeq {
+    /// The number of bases.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no bases.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The packed bases, four per byte. The unused bits of the last byte are 0.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The sorted `(start, end)` blocks of bases other than A, C, G or T.
+    pub fn n_blocks(&self) -> &[(usize, usize)] {
+        &self.n_blocks
+    }
+
+    /// The 2-bit code of the base at position `i`, `None` if `i` is out of range or the base is
+    /// in an N block.
+    pub fn code(&self, i: usize) -> Option<u8> {
+        if i >= self.len {
+            return None;
+        }
+        let block = self.n_blocks.partition_point(|b| b.1 <= i);
+        if self.n_blocks.get(block).is_some_and(|b| b.0 <= i) {
+            return None;
+        }
+        Some(self.data[i / 4] >> (6 - 2 * (i % 4)) & 3)
+    }
+
+    /// Unpack to uppercase ASCII, with N for the N blocks.
+    pub fn unpack(&self) -> Vec<u8> {
+        let mut bases: Vec<u8> = (0..self.len)
+            .map(|i| b"ACGT"[(self.data[i / 4] >> (6 - 2 * (i % 4)) & 3) as usize])
+            .collect();
+        for &(start, end) in &self.n_blocks {
+            bases[start..end].fill(b'N');
+        }
+        bases
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Pack the bases of the current view with two bits per base, see the `packed` module.
+    pub fn to_packed(&self) -> PackedSeq {
+        let mut packed = PackedSeq {
+            len: 0,
+            data: Vec::with_capacity(self.0.len() / 4 + 1),
+            n_blocks: Vec::new(),
+        };
+        let (mut byte, mut filled) = (0, 0);
+        for &b in self.bases() {
+            let code = match encode(b) {
+                Some(code) => code,
+                None => {
+                    match packed.n_blocks.last_mut() {
+                        Some(block) if block.1 == packed.len => block.1 += 1,
+                        _ => packed.n_blocks.push((packed.len, packed.len + 1)),
+                    }
+                    0
+                }
+            };
+            byte = byte << 2 | code;
+            packed.len += 1;
+            filled += 1;
+            if filled == 4 {
+                packed.data.push(byte);
+                (byte, filled) = (0, 0);
+            }
+        }
+        if filled > 0 {
+            packed.data.push(byte << (2 * (4 - filled)));
+        }
+        packed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack() {
+        let packed = FastaView::new(b"ACgT\nNNac\r\nR").to_packed();
+        assert_eq!(packed.len(), 9);
+        assert_eq!(packed.as_bytes(), &[0b0001_1011, 0b0000_0001, 0]);
+        assert_eq!(packed.n_blocks(), &[(4, 6), (8, 9)]);
+        assert_eq!(packed.unpack(), b"ACGTNNACN");
+        assert_eq!(packed.code(3), Some(3));
+        assert_eq!(packed.code(5), None);
+        assert_eq!(packed.code(7), Some(1));
+        assert_eq!(packed.code(9), None);
+        assert!(FastaView::new(b"").to_packed().is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Parallel operations using [rayon](https://crates.io/crates/rayon) (feature `rayon`).
+//!
+//! Views don't need mutable access to the `IndexedFasta`, so regions can be processed on the
+//! rayon thread pool without any locking. `IndexedFasta::par_digest_all` digests the
+//! chromosomes in parallel, which is also used for the checksums of all chromosomes when the
+//! `checksum` feature is enabled.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use rayon::prelude::*;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let counts = fa.count_bases_many(&[(2, 0, 50), (2, 50, 100)]).expect("Cannot count");
+//! assert_eq!(counts[0].a, 25);
+//! let gc: Vec<_> = fa
+//!     .par_iter()
+//!     .map(|r| r.map(|(name, v)| (name, v.count_bases().gc_fraction())))
+//!     .collect::<faimm::Result<_>>()
+//!     .expect("Cannot count");
+//! assert_eq!(gc[2], ("ACGT-25", 0.5));
+//! ```
+
+use rayon::prelude::*;
+
+use crate::digest::Digester;
+use crate::{BaseCounts, FastaView, IndexedFasta, Result};
+
+impl IndexedFasta {
+    /// Count the bases of many `(tid, start, stop)` regions in parallel on the rayon thread
+    /// pool. The number of regions is checked against `Limits::max_batch_size`.
+    ///
+    /// Returns the `BaseCounts` of every region in the same order as `regions`, or the first
+    /// `Error` for an invalid region.
+    pub fn count_bases_many(&self, regions: &[(usize, usize, usize)]) -> Result<Vec<BaseCounts>> {
+        self.limits().check_batch(regions.len())?;
+        regions
+            .par_iter()
+            .map(|&(tid, start, stop)| Ok(self.view(tid, start, stop)?.count_bases()))
+            .collect()
+    }
+
+    /// Iterate over all chromosomes in parallel on the rayon thread pool, see
+    /// `IndexedFasta::iter`. Collecting the iterator keeps the order of the index.
+    pub fn par_iter(
+        &self,
+    ) -> impl IndexedParallelIterator<Item = Result<(&str, FastaView<'_>)>> + '_ {
+        (0..self.fai().name_map.len())
+            .into_par_iter()
+            .map(move |tid| {
+                let name = self.fai().name(tid)?;
+                Ok((name.as_str(), self.view_tid(tid)?))
+            })
+    }
+
+    /// Digest all chromosomes in parallel on the rayon thread pool, with a new digester from
+    /// `new` for every chromosome, see `IndexedFasta::digest_all`.
+    ///
+    /// Returns the digests in index order if successful, Error otherwise.
+    pub fn par_digest_all<D, F>(&self, new: F) -> Result<Vec<D::Output>>
+    where
+        D: Digester,
+        D::Output: Send,
+        F: Fn() -> D + Sync,
+    {
+        self.par_digest_all_with_progress(new, |_| ())
+    }
+
+    /// Digest all chromosomes in parallel like `IndexedFasta::par_digest_all`, and call
+    /// `progress` with the tid of every chromosome that is digested. The calls are made from
+    /// the threads of the pool, in the order the chromosomes finish.
+    pub fn par_digest_all_with_progress<D, F, P>(
+        &self,
+        new: F,
+        progress: P,
+    ) -> Result<Vec<D::Output>>
+    where
+        D: Digester,
+        D::Output: Send,
+        F: Fn() -> D + Sync,
+        P: Fn(usize) + Sync,
+    {
+        (0..self.fai().name_map.len())
+            .into_par_iter()
+            .map(|tid| {
+                let digest = self.digest(tid, new())?;
+                progress(tid);
+                Ok(digest)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn count_many() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let regions: Vec<_> = (0..100).map(|i| (2, i, 100)).collect();
+        let counts = fa.count_bases_many(&regions).unwrap();
+        for (&(tid, start, stop), bc) in regions.iter().zip(&counts) {
+            assert_eq!(bc, &fa.view(tid, start, stop).unwrap().count_bases());
+        }
+        assert!(fa.count_bases_many(&[(2, 0, 10), (2, 0, 101)]).is_err());
+
+        let lengths: Vec<_> = fa.par_iter().map(|r| r.unwrap().1.len_bases()).collect();
+        assert_eq!(lengths, [10, 100, 100]);
+    }
+
+    /// Counts the bases, starting from a large number to check the order of the results.
+    struct Count(usize);
+
+    impl Digester for Count {
+        type Output = usize;
+
+        fn update(&mut self, bases: &[u8]) {
+            self.0 += bases.len();
+        }
+
+        fn finish(self) -> usize {
+            self.0
+        }
+    }
+
+    #[test]
+    fn digest() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let done = AtomicUsize::new(0);
+        let digests = fa
+            .par_digest_all_with_progress(
+                || Count(1000),
+                |tid| {
+                    done.fetch_or(1 << tid, Ordering::Relaxed);
+                },
+            )
+            .unwrap();
+        assert_eq!(digests, [1010, 1100, 1100]);
+        assert_eq!(digests, fa.digest_all(|| Count(1000)).unwrap());
+        assert_eq!(done.into_inner(), 0b111);
+
+        let data = std::fs::read("test/genome.fa").unwrap();
+        let truncated =
+            IndexedFasta::from_source(data[..data.len() - 60].to_vec(), fa.fai().clone());
+        assert!(matches!(
+            truncated.par_digest_all(|| Count(0)),
+            Err(Error::Truncated { tid: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+//! A pool of references that are opened on demand.
+//!
+//! A service that serves many assemblies can't keep all of them mapped. A `ReferencePool` knows
+//! the path of every reference by name, opens a reference the first time it is requested and
+//! keeps at most `max_open` references and `max_mapped_bytes` bytes of fasta data mapped. When a
+//! limit is reached, the least recently used references are closed first. The pool can be shared
+//! between threads. References are handed out as `Arc<IndexedFasta>`, so a reference that is
+//! evicted while it is still in use stays mapped until the last user drops it.
+//!
+//! # Example
+//! ```
+//! use faimm::pool::ReferencePool;
+//! let mut pool = ReferencePool::new().max_open(1);
+//! pool.add("genome", "test/genome.fa");
+//! let fa = pool.get("genome").expect("Cannot open reference");
+//! assert_eq!(fa.view(0, 0, 5).unwrap().to_string(), "AAAAA");
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::{Error, IndexedFasta, Result};
+
+/// An open reference and its size in bytes.
+struct OpenReference {
+    name: String,
+    bytes: usize,
+    fasta: Arc<IndexedFasta>,
+}
+
+/// Named references that are opened on demand, with limits on the number of open references
+/// and the number of mapped bytes.
+#[derive(Default)]
+pub struct ReferencePool {
+    paths: HashMap<String, PathBuf>,
+    max_open: Option<usize>,
+    max_mapped_bytes: Option<usize>,
+    /// The open references, least recently used first.
+    open: Mutex<Vec<OpenReference>>,
+}
+
+impl ReferencePool {
+    /// Create an empty pool without limits.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the maximum number of simultaneously opened references.
+    pub fn max_open(mut self, n: usize) -> Self {
+        self.max_open = Some(n);
+        self
+    }
+
+    /// Set the maximum number of mapped bytes of all open references together.
+    pub fn max_mapped_bytes(mut self, bytes: usize) -> Self {
+        self.max_mapped_bytes = Some(bytes);
+        self
+    }
+
+    /// Add the indexed fasta file at path `P` as reference `name`. The file is not opened until
+    /// it is requested. A reference that was already added under the same name is replaced, but
+    /// stays open until it is evicted.
+    pub fn add<P: AsRef<Path>>(&mut self, name: &str, path: P) {
+        self.paths.insert(name.to_owned(), path.as_ref().to_owned());
+    }
+
+    /// The names of all references in the pool, in arbitrary order.
+    pub fn names(&self) -> Vec<&str> {
+        self.paths.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// The number of currently opened references.
+    pub fn open_count(&self) -> usize {
+        self.open.lock().unwrap().len()
+    }
+
+    /// The number of bytes that are mapped by the currently opened references.
+    pub fn mapped_bytes(&self) -> usize {
+        self.open.lock().unwrap().iter().map(|r| r.bytes).sum()
+    }
+
+    /// Get reference `name`, opening it if necessary. Opening a reference closes the least
+    /// recently used references until it fits within the limits.
+    ///
+    /// Returns the reference if successful, `Error::InvalidArgument` for an unknown reference
+    /// or a reference that is larger than `max_mapped_bytes`, and an `Error` when opening fails.
+    pub fn get(&self, name: &str) -> Result<Arc<IndexedFasta>> {
+        let path = self
+            .paths
+            .get(name)
+            .ok_or_else(|| Error::InvalidArgument(format!("Unknown reference {}", name)))?;
+
+        let mut open = self.open.lock().unwrap();
+        if let Some(i) = open.iter().position(|r| r.name == name) {
+            let reference = open.remove(i);
+            let fasta = Arc::clone(&reference.fasta);
+            open.push(reference);
+            return Ok(fasta);
+        }
+
+        let bytes = std::fs::metadata(path)?.len() as usize;
+        if let Some(max) = self.max_mapped_bytes {
+            if bytes > max {
+                return Err(Error::InvalidArgument(format!(
+                    "Reference {} of {} bytes exceeds the limit of {} mapped bytes",
+                    name, bytes, max
+                )));
+            }
+        }
+
+        // make room first, so the limits also hold while the new reference is opened
+        let mut mapped: usize = open.iter().map(|r| r.bytes).sum::<usize>() + bytes;
+        while !open.is_empty()
+            && (self.max_open.is_some_and(|max| open.len() >= max)
+                || self.max_mapped_bytes.is_some_and(|max| mapped > max))
+        {
+            mapped -= open.remove(0).bytes;
+        }
+
+        let fasta = Arc::new(IndexedFasta::from_file(path)?);
+        open.push(OpenReference {
+            name: name.to_owned(),
+            bytes,
+            fasta: Arc::clone(&fasta),
+        });
+        Ok(fasta)
+    }
+
+    /// Close all open references.
+    pub fn clear(&self) {
+        self.open.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eviction() {
+        let mut pool = ReferencePool::new().max_open(2);
+        pool.add("a", "test/genome.fa");
+        pool.add("b", "test/genome.fa");
+        pool.add("c", "test/genome.fa");
+        let a = pool.get("a").unwrap();
+        pool.get("b").unwrap();
+        // a becomes the most recently used reference
+        assert!(Arc::ptr_eq(&a, &pool.get("a").unwrap()));
+        pool.get("c").unwrap();
+        assert_eq!(pool.open_count(), 2);
+        assert!(Arc::ptr_eq(&a, &pool.get("a").unwrap()));
+        assert!(pool.get("d").is_err());
+
+        let bytes = std::fs::metadata("test/genome.fa").unwrap().len() as usize;
+        assert_eq!(pool.mapped_bytes(), 2 * bytes);
+        pool.clear();
+        assert_eq!(pool.open_count(), 0);
+    }
+
+    #[test]
+    fn mapped_bytes() {
+        let bytes = std::fs::metadata("test/genome.fa").unwrap().len() as usize;
+        let mut pool = ReferencePool::new().max_mapped_bytes(bytes);
+        pool.add("a", "test/genome.fa");
+        pool.add("b", "test/genome.fa");
+        pool.get("a").unwrap();
+        pool.get("b").unwrap();
+        assert_eq!(pool.open_count(), 1);
+
+        let mut pool = ReferencePool::new().max_mapped_bytes(bytes - 1);
+        pool.add("a", "test/genome.fa");
+        assert!(matches!(pool.get("a"), Err(Error::InvalidArgument(_))));
+    }
+}
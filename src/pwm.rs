@@ -0,0 +1,344 @@
+//! Position weight matrix scanning.
+//!
+//! A `Pwm` holds a score for every base at every position of a motif, usually the log-odds of
+//! the base frequencies in known binding sites against a background. `FastaView::scan_pwm`
+//! slides the matrix over the bases of a view, without copying them, and yields the positions
+//! where the score on the forward or reverse strand reaches a threshold. Windows with a base
+//! other than A, C, G or T are skipped. Soft-masked bases are scored like uppercase bases.
+//!
+//! Count matrices in the JASPAR format can be read with `Pwm::read_jaspar`. The counts are
+//! converted into log2-odds scores against a uniform background.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::pwm::Pwm;
+//! use faimm::strand::Strand;
+//! let jaspar = ">MA0000.1 AC-box\nA [ 9 0 0 ]\nC [ 0 9 9 ]\nG [ 1 1 0 ]\nT [ 0 0 1 ]\n";
+//! let pwm = Pwm::read_jaspar(jaspar.as_bytes(), 0.25).expect("Invalid matrix");
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let view = fa.view_tid(2).expect("Cannot get view");
+//! let hits: Vec<_> = view.scan_pwm(&pwm, 0.9 * pwm.max_score()).collect();
+//! assert_eq!(hits.len(), 2);
+//! assert_eq!((hits[0].0, hits[0].1), (24, Strand::Forward));
+//! assert_eq!((hits[1].0, hits[1].1), (73, Strand::Reverse));
+//! ```
+
+use std::io::BufRead;
+
+use crate::bed::Hit;
+use crate::strand::Strand;
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// A position weight matrix with the scores of A, C, G and T at every position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pwm {
+    name: String,
+    weights: Vec<[f64; 4]>,
+}
+
+/// The index of a base in the rows of a matrix, 4 for anything other than A, C, G or T.
+#[inline]
+fn base_index(b: u8) -> u8 {
+    match b {
+        b'A' | b'a' => 0,
+        b'C' | b'c' => 1,
+        b'G' | b'g' => 2,
+        b'T' | b't' => 3,
+        _ => 4,
+    }
+}
+
+impl Pwm {
+    /// Create a matrix with the scores of A, C, G and T at every position of the motif.
+    ///
+    /// Returns the matrix, or `Error::InvalidArgument` for an empty matrix.
+    pub fn new(name: &str, weights: Vec<[f64; 4]>) -> Result<Self> {
+        if weights.is_empty() {
+            return Err(Error::InvalidArgument("Empty weight matrix".to_owned()));
+        }
+        Ok(Pwm {
+            name: name.to_owned(),
+            weights,
+        })
+    }
+
+    /// Create a matrix from the counts of A, C, G and T at every position of the motif. The
+    /// `pseudocount` is added to every count, to avoid a score of minus infinity for bases
+    /// that were never observed. The score is the log2-odds of the base frequency against a
+    /// background frequency of 0.25.
+    ///
+    /// Returns the matrix, or `Error::InvalidArgument` for an empty matrix or a position
+    /// without counts.
+    pub fn from_counts(name: &str, counts: &[[f64; 4]], pseudocount: f64) -> Result<Self> {
+        let weights = counts
+            .iter()
+            .map(|column| {
+                let total: f64 = column.iter().map(|c| c + pseudocount).sum();
+                if total <= 0.0 {
+                    return Err(Error::InvalidArgument(
+                        "Position without counts in matrix".to_owned(),
+                    ));
+                }
+                Ok(column.map(|c| ((c + pseudocount) / total / 0.25).log2()))
+            })
+            .collect::<Result<_>>()?;
+        Pwm::new(name, weights)
+    }
+
+    /// Read a count matrix in the JASPAR format and convert it with `Pwm::from_counts`. The
+    /// matrix has a row for every base, labeled with the base and optionally enclosed in
+    /// brackets. Rows without labels are read in the order A, C, G, T. The name is the text
+    /// after the `>` of the header line, if there is one.
+    ///
+    /// Returns the `Pwm` if successful, `Error::Malformed` for an invalid matrix, Error
+    /// otherwise.
+    pub fn read_jaspar<R: BufRead>(r: R, pseudocount: f64) -> Result<Self> {
+        let invalid = |message: String| Error::Malformed {
+            format: "JASPAR",
+            line: None,
+            message,
+        };
+        let mut name = String::new();
+        let mut rows: [Option<Vec<f64>>; 4] = Default::default();
+        let mut unlabeled = 0;
+        for line in r.lines() {
+            let line = line?;
+            let line = line.trim();
+            if let Some(header) = line.strip_prefix('>') {
+                name = header.trim().to_owned();
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let (row, values) = match line.as_bytes()[0] {
+                b @ (b'A' | b'C' | b'G' | b'T') => (base_index(b) as usize, &line[1..]),
+                _ => {
+                    unlabeled += 1;
+                    (unlabeled - 1, line)
+                }
+            };
+            if row > 3 || rows[row].is_some() {
+                return Err(invalid(format!("Duplicate or extra matrix row: {}", line)));
+            }
+            let values = values
+                .split(|c: char| c.is_whitespace() || c == '[' || c == ']')
+                .filter(|v| !v.is_empty())
+                .map(|v| {
+                    v.parse()
+                        .map_err(|e| invalid(format!("Invalid count {}: {}", v, e)))
+                })
+                .collect::<Result<Vec<f64>>>()?;
+            rows[row] = Some(values);
+        }
+
+        let [Some(a), Some(c), Some(g), Some(t)] = rows else {
+            return Err(invalid("Expected a row for A, C, G and T".to_owned()));
+        };
+        if c.len() != a.len() || g.len() != a.len() || t.len() != a.len() {
+            return Err(invalid("Matrix rows differ in length".to_owned()));
+        }
+        let counts: Vec<_> = (0..a.len()).map(|i| [a[i], c[i], g[i], t[i]]).collect();
+        Pwm::from_counts(&name, &counts, pseudocount)
+    }
+
+    /// The name of the motif.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The length of the motif.
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Always `false`, a matrix has at least one position.
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// The highest possible score.
+    pub fn max_score(&self) -> f64 {
+        self.weights
+            .iter()
+            .map(|w| w.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+            .sum()
+    }
+
+    /// The lowest possible score.
+    pub fn min_score(&self) -> f64 {
+        self.weights
+            .iter()
+            .map(|w| w.iter().copied().fold(f64::INFINITY, f64::min))
+            .sum()
+    }
+
+    /// The matrix of the reverse complement of the motif.
+    pub fn reverse_complement(&self) -> Pwm {
+        Pwm {
+            name: self.name.clone(),
+            weights: self
+                .weights
+                .iter()
+                .rev()
+                .map(|&[a, c, g, t]| [t, g, c, a])
+                .collect(),
+        }
+    }
+
+    /// Score the window starting at `start` in the ring buffer of base indexes.
+    fn score(&self, ring: &[u8], start: usize) -> f64 {
+        self.weights
+            .iter()
+            .enumerate()
+            .map(|(j, w)| w[ring[(start + j) % ring.len()] as usize])
+            .sum()
+    }
+}
+
+/// Iterator over the matches of a `Pwm`, created with `FastaView::scan_pwm`.
+pub struct PwmScan<'a> {
+    iter: std::slice::Iter<'a, u8>,
+    pwm: &'a Pwm,
+    rc: Pwm,
+    threshold: f64,
+    /// The base indexes of the current window.
+    ring: Vec<u8>,
+    /// The number of bases read.
+    pos: usize,
+    /// The position after the last base other than A, C, G or T.
+    valid_from: usize,
+    reverse: Option<(usize, Strand, f64)>,
+}
+
+impl<'a> Iterator for PwmScan<'a> {
+    type Item = (usize, Strand, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(hit) = self.reverse.take() {
+            return Some(hit);
+        }
+        let len = self.ring.len();
+        for &b in &mut self.iter {
+            if b & 192 != 64 {
+                continue;
+            }
+            let i = base_index(b);
+            self.ring[self.pos % len] = i;
+            self.pos += 1;
+            if i == 4 {
+                self.valid_from = self.pos;
+            }
+            if self.pos < self.valid_from + len {
+                continue;
+            }
+
+            let start = self.pos - len;
+            let forward = self.pwm.score(&self.ring, start);
+            let reverse = self.rc.score(&self.ring, start);
+            let forward = (forward >= self.threshold).then_some((start, Strand::Forward, forward));
+            let reverse = (reverse >= self.threshold).then_some((start, Strand::Reverse, reverse));
+            match (forward, reverse) {
+                (Some(f), r) => {
+                    self.reverse = r;
+                    return Some(f);
+                }
+                (None, Some(r)) => return Some(r),
+                (None, None) => {}
+            }
+        }
+        None
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Scan the current view with `pwm` on both strands. The score of the reverse strand is the
+    /// score of the reverse complement of the window.
+    ///
+    /// Returns an iterator of `(start, strand, score)` for the windows with a score of at least
+    /// `threshold`, sorted by position.
+    pub fn scan_pwm<'p>(&self, pwm: &'p Pwm, threshold: f64) -> PwmScan<'p>
+    where
+        'a: 'p,
+    {
+        PwmScan {
+            iter: self.0.iter(),
+            pwm,
+            rc: pwm.reverse_complement(),
+            threshold,
+            ring: vec![4; pwm.len()],
+            pos: 0,
+            valid_from: 0,
+            reverse: None,
+        }
+    }
+}
+
+impl IndexedFasta {
+    /// Scan chromosome tid with `pwm` on both strands, see `FastaView::scan_pwm`.
+    ///
+    /// Returns the matches as `Hit`s named after the motif if successful, Error otherwise.
+    pub fn scan_pwm(&self, tid: usize, pwm: &Pwm, threshold: f64) -> Result<Vec<Hit>> {
+        Ok(self
+            .view_tid(tid)?
+            .scan_pwm(pwm, threshold)
+            .map(|(start, strand, score)| Hit {
+                tid,
+                start,
+                end: start + pwm.len(),
+                name: pwm.name().to_owned(),
+                score,
+                strand,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acg() -> Pwm {
+        let counts = [
+            [10.0, 0.0, 0.0, 0.0],
+            [0.0, 10.0, 0.0, 0.0],
+            [0.0, 0.0, 10.0, 0.0],
+        ];
+        Pwm::from_counts("acg", &counts, 0.25).unwrap()
+    }
+
+    #[test]
+    fn scan() {
+        let pwm = acg();
+        assert!(pwm.min_score() < 0.0);
+        let v = FastaView::new(b"ACGT\nNacg");
+        let hits: Vec<_> = v
+            .scan_pwm(&pwm, pwm.max_score() - 0.001)
+            .map(|(pos, strand, _)| (pos, strand))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                (0, Strand::Forward),
+                (1, Strand::Reverse),
+                (5, Strand::Forward)
+            ]
+        );
+        assert_eq!(v.scan_pwm(&pwm, f64::NEG_INFINITY).count(), 6);
+        assert!(Pwm::new("empty", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn jaspar() {
+        let labeled =
+            ">MA0001.1 acg\nA  [10  0  0 ]\nC  [ 0 10  0 ]\nG  [ 0  0 10 ]\nT  [ 0  0  0 ]\n";
+        let pwm = Pwm::read_jaspar(labeled.as_bytes(), 0.25).unwrap();
+        assert_eq!(pwm.name(), "MA0001.1 acg");
+        assert_eq!(pwm.weights, acg().weights);
+        let plain = "10 0 0\n0 10 0\n0 0 10\n0 0 0\n";
+        assert_eq!(Pwm::read_jaspar(plain.as_bytes(), 0.25).unwrap().len(), 3);
+        assert!(Pwm::read_jaspar("A [1 2]\nC [1]\nG [1]\nT [1]".as_bytes(), 0.25).is_err());
+        assert!(Pwm::read_jaspar("A [1]\nC [1]\nG [1]".as_bytes(), 0.25).is_err());
+    }
+}
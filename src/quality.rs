@@ -0,0 +1,218 @@
+//! Phred quality score decoding and summary statistics.
+//!
+//! Quality strings are encoded as printable ascii characters with a fixed offset: 33 for Sanger
+//! and Illumina 1.8+ data, 64 for older Illumina data. The functions in this module work on the
+//! raw encoded bytes, so they can run directly over quality data in a memory mapped file.
+//!
+//! # Example
+//! ```
+//! use faimm::quality::{PhredOffset, QualityStats};
+//! let stats = QualityStats::from_bytes(b"II?5+", PhredOffset::Sanger)
+//!     .expect("Invalid quality string")
+//!     .expect("Empty quality string");
+//! assert_eq!(stats.min, 10);
+//! assert_eq!(stats.median, 30);
+//! ```
+
+use crate::{Error, Result};
+
+/// The highest phred score that can be encoded in printable ascii with offset 33.
+const MAX_QUAL: usize = 93;
+
+/// The ascii offset used to encode phred scores.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PhredOffset {
+    /// Offset 33, used by Sanger and Illumina 1.8+.
+    Sanger,
+    /// Offset 64, used by Illumina 1.3 up to 1.7.
+    Illumina,
+}
+
+impl PhredOffset {
+    /// Return the ascii value that encodes a phred score of zero.
+    pub fn value(self) -> u8 {
+        match self {
+            PhredOffset::Sanger => 33,
+            PhredOffset::Illumina => 64,
+        }
+    }
+
+    /// Decode a single quality character into its phred score.
+    ///
+    /// Returns `Error::InvalidQuality` if the character is outside the range of this encoding.
+    #[inline]
+    pub fn decode(self, q: u8) -> Result<u8> {
+        if q < self.value() || q > b'~' {
+            return Err(Error::InvalidQuality(q));
+        }
+        Ok(q - self.value())
+    }
+}
+
+/// Decode a slice of quality characters into phred scores.
+pub fn decode(quals: &[u8], offset: PhredOffset) -> Result<Vec<u8>> {
+    quals.iter().map(|&q| offset.decode(q)).collect()
+}
+
+/// Convert a phred score into the probability that the base call is wrong.
+pub fn error_probability(phred: u8) -> f64 {
+    10f64.powf(-(phred as f64) / 10.0)
+}
+
+/// Summary of the phred scores of a quality string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityStats {
+    pub count: usize,
+    pub min: u8,
+    pub max: u8,
+    pub mean: f64,
+    pub median: u8,
+}
+
+impl QualityStats {
+    /// Calculate the statistics from encoded quality characters. The median is the lower median
+    /// for an even number of scores.
+    ///
+    /// Returns `None` for empty input and `Error::InvalidQuality` on invalid quality characters.
+    pub fn from_bytes<'a, I>(quals: I, offset: PhredOffset) -> Result<Option<QualityStats>>
+    where
+        I: IntoIterator<Item = &'a u8>,
+    {
+        let mut hist = [0usize; MAX_QUAL + 1];
+        for &q in quals {
+            hist[offset.decode(q)? as usize] += 1;
+        }
+        Ok(stats_from_histogram(&hist))
+    }
+}
+
+fn stats_from_histogram(hist: &[usize]) -> Option<QualityStats> {
+    let count: usize = hist.iter().sum();
+    if count == 0 {
+        return None;
+    }
+
+    let min = hist.iter().position(|&c| c > 0)? as u8;
+    let max = hist.iter().rposition(|&c| c > 0)? as u8;
+    let sum: usize = hist.iter().enumerate().map(|(q, &c)| q * c).sum();
+
+    let mut seen = 0;
+    let mut median = 0;
+    for (q, &c) in hist.iter().enumerate() {
+        seen += c;
+        if seen * 2 >= count {
+            median = q as u8;
+            break;
+        }
+    }
+
+    Some(QualityStats {
+        count,
+        min,
+        max,
+        mean: sum as f64 / count as f64,
+        median,
+    })
+}
+
+/// Aggregates the phred scores of many quality strings into a histogram per read position.
+#[derive(Debug, Clone)]
+pub struct PositionHistogram {
+    offset: PhredOffset,
+    positions: Vec<[usize; MAX_QUAL + 1]>,
+}
+
+impl PositionHistogram {
+    /// Create an empty histogram for quality strings encoded with `offset`.
+    pub fn new(offset: PhredOffset) -> Self {
+        PositionHistogram {
+            offset,
+            positions: Vec::new(),
+        }
+    }
+
+    /// Add the encoded quality string of a single read. The histogram grows to the length of
+    /// the longest read.
+    pub fn add<'a, I>(&mut self, quals: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a u8>,
+    {
+        for (pos, &q) in quals.into_iter().enumerate() {
+            let phred = self.offset.decode(q)?;
+            if pos == self.positions.len() {
+                self.positions.push([0; MAX_QUAL + 1]);
+            }
+            self.positions[pos][phred as usize] += 1;
+        }
+        Ok(())
+    }
+
+    /// Return the number of read positions in the histogram.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns true if no quality strings were added.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Return the counts per phred score (index) at read position `pos`.
+    pub fn histogram(&self, pos: usize) -> Option<&[usize]> {
+        self.positions.get(pos).map(|h| &h[..])
+    }
+
+    /// Return the quality statistics at read position `pos`.
+    pub fn stats(&self, pos: usize) -> Option<QualityStats> {
+        self.positions
+            .get(pos)
+            .and_then(|h| stats_from_histogram(h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_offsets() {
+        assert_eq!(PhredOffset::Sanger.decode(b'I').unwrap(), 40);
+        assert_eq!(PhredOffset::Illumina.decode(b'h').unwrap(), 40);
+        assert!(matches!(
+            PhredOffset::Illumina.decode(b'5'),
+            Err(Error::InvalidQuality(b'5'))
+        ));
+        assert_eq!(
+            decode(b"!+5", PhredOffset::Sanger).unwrap(),
+            vec![0, 10, 20]
+        );
+        assert!((error_probability(20) - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stats() {
+        let s = QualityStats::from_bytes(b"5+5?", PhredOffset::Sanger)
+            .unwrap()
+            .unwrap();
+        assert_eq!(s.count, 4);
+        assert_eq!((s.min, s.max, s.median), (10, 30, 20));
+        assert_eq!(s.mean, 20.0);
+        assert!(QualityStats::from_bytes(b"", PhredOffset::Sanger)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn position_histogram() {
+        let mut h = PositionHistogram::new(PhredOffset::Sanger);
+        h.add(b"II5").unwrap();
+        h.add(b"5I").unwrap();
+        assert_eq!(h.len(), 3);
+        assert_eq!(h.histogram(0).unwrap()[40], 1);
+        assert_eq!(h.histogram(0).unwrap()[20], 1);
+        assert_eq!(h.stats(1).unwrap().mean, 40.0);
+        assert_eq!(h.stats(2).unwrap().count, 1);
+        assert!(h.stats(3).is_none());
+        assert!(h.add(b"\n").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Sequential record access for fasta files without an index.
+//!
+//! `FastaRecords` makes a single pass over a (memory mapped) fasta file and yields every record
+//! with its header and a `FastaView` of its sequence. It is used to create `.fai` indexes, but is
+//! also useful for quick scans where building an index isn't wanted.
+//!
+//! # Example
+//! ```
+//! use faimm::records::FastaRecords;
+//! let fa = FastaRecords::from_file("test/genome.fa").expect("Error opening fa");
+//! for record in fa.records() {
+//!     let record = record.expect("Invalid fasta record");
+//!     println!("{}\t{:?}", record.name(), record.view().count_bases());
+//! }
+//! ```
+
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::{Mmap, MmapOptions};
+
+use crate::{Error, FastaView, Result};
+
+/// A memory mapped fasta file that is read sequentially.
+pub struct FastaRecords {
+    mmap: Mmap,
+}
+
+impl FastaRecords {
+    /// Open a fasta file from path `P`. No index is needed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file) }.map_err(Error::Mmap)?;
+        Ok(FastaRecords { mmap })
+    }
+
+    /// Iterator over the records in the file.
+    pub fn records(&self) -> Records<'_> {
+        Records::new(&self.mmap)
+    }
+}
+
+/// A single fasta record.
+#[derive(Debug, Clone)]
+pub struct Record<'a> {
+    header: &'a str,
+    header_offset: usize,
+    offset: usize,
+    sequence: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    /// The header line without the leading `>` and the line ending.
+    pub fn header(&self) -> &'a str {
+        self.header
+    }
+
+    /// The name of the record: the header up to the first whitespace.
+    pub fn name(&self) -> &'a str {
+        self.header.split_whitespace().next().unwrap_or("")
+    }
+
+    /// The description of the record: the header after the name, if any.
+    pub fn description(&self) -> Option<&'a str> {
+        self.header
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .map(|(_, d)| d.trim())
+            .filter(|d| !d.is_empty())
+    }
+
+    /// The byte offset of the header line (the `>`) in the file.
+    pub fn header_offset(&self) -> usize {
+        self.header_offset
+    }
+
+    /// The byte offset of the first sequence line in the file.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The raw sequence data including line endings.
+    pub fn raw_sequence(&self) -> &'a [u8] {
+        self.sequence
+    }
+
+    /// A view of the sequence of this record.
+    pub fn view(&self) -> FastaView<'a> {
+        FastaView::new(self.sequence)
+    }
+}
+
+/// Iterator over the records of fasta data, created with `FastaRecords::records` or
+/// `Records::new`.
+pub struct Records<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Records<'a> {
+    /// Iterate over the records in `data`. Only whitespace may precede the first header.
+    pub fn new(data: &'a [u8]) -> Self {
+        Records { data, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.data;
+        if self.pos == 0 {
+            // skip leading whitespace and make sure the data starts with a header
+            self.pos = data
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .unwrap_or(data.len());
+            if self.pos < data.len() && data[self.pos] != b'>' {
+                let pos = self.pos;
+                self.pos = data.len();
+                return Some(Err(Error::MalformedFasta {
+                    offset: pos,
+                    message: "Sequence data before first fasta header".to_owned(),
+                }));
+            }
+        }
+        if self.pos >= data.len() {
+            return None;
+        }
+
+        let header_start = self.pos + 1;
+        let header_end = find(data, header_start, b'\n').unwrap_or(data.len());
+        let offset = (header_end + 1).min(data.len());
+        let mut end = offset;
+        while end < data.len() && data[end] != b'>' {
+            end = find(data, end, b'\n').map_or(data.len(), |p| p + 1);
+        }
+        self.pos = end;
+
+        let header = &data[header_start..header_end];
+        let header = header.strip_suffix(b"\r").unwrap_or(header);
+        match std::str::from_utf8(header) {
+            Ok(header) => Some(Ok(Record {
+                header,
+                header_offset: header_start - 1,
+                offset,
+                sequence: &data[offset..end],
+            })),
+            Err(e) => Some(Err(Error::MalformedFasta {
+                offset: header_start - 1,
+                message: format!("Invalid fasta header: {}", e),
+            })),
+        }
+    }
+}
+
+fn find(data: &[u8], from: usize, byte: u8) -> Option<usize> {
+    data[from..]
+        .iter()
+        .position(|&b| b == byte)
+        .map(|p| from + p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records() {
+        let data = b"\n>one first record\r\nACGT\r\nAC\r\n>two\nAAA\n\n>three\n>four  \nTT";
+        let records: Vec<_> = Records::new(data).map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].name(), "one");
+        assert_eq!(records[0].description(), Some("first record"));
+        assert_eq!(records[0].header_offset(), 1);
+        assert_eq!(records[0].offset(), 20);
+        assert_eq!(records[0].view().to_string(), "ACGTAC");
+        assert_eq!(records[1].header(), "two");
+        assert_eq!(records[1].description(), None);
+        assert_eq!(records[1].raw_sequence(), b"AAA\n\n");
+        assert_eq!(records[2].view().to_string(), "");
+        assert_eq!(records[3].name(), "four");
+        assert_eq!(records[3].view().to_string(), "TT");
+
+        let mut bad = Records::new(b"ACGT\n>one\nA");
+        assert!(bad.next().unwrap().is_err());
+        assert!(bad.next().is_none());
+        assert!(Records::new(b"").next().is_none());
+    }
+
+    #[test]
+    fn from_file() {
+        let fa = FastaRecords::from_file("test/genome.fa").unwrap();
+        let names: Vec<_> = fa.records().map(|r| r.unwrap().name().to_owned()).collect();
+        assert_eq!(names, vec!["A-10", "A-100", "ACGT-25"]);
+    }
+}
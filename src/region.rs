@@ -0,0 +1,435 @@
+//! Genomic regions and sets of intervals that are validated against the chromosome lengths in a
+//! `Fai`.
+//!
+//! A `Region` is parsed from a samtools style region string like `chr1:1,000,000-1,000,500`,
+//! which uses one-based inclusive coordinates. After parsing the coordinates are zero-based and
+//! half-open like everywhere else in this crate.
+//!
+//! A `RegionSet` stores zero-based, half-open `(start, end)` intervals per chromosome index
+//! (tid). Intervals can be added in any order; `merge` sorts them and collapses overlapping or
+//! bookended intervals. The set operations `complement` and `subtract` always work on merged
+//! intervals, so the result is sorted and free of duplicates.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::region::RegionSet;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//!
+//! let mut gaps = RegionSet::new(fa.fai());
+//! gaps.insert(2, 0, 25).unwrap();
+//! let mut blacklist = RegionSet::new(fa.fai());
+//! blacklist.insert(2, 75, 100).unwrap();
+//!
+//! let targets = gaps.complement().subtract(&blacklist);
+//! assert_eq!(targets.iter().filter(|r| r.0 == 2).collect::<Vec<_>>(), vec![(2, 25, 75)]);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, Fai, FastaView, IndexedFasta, Result};
+
+/// A region on a named chromosome. `start` and `end` are zero-based, half-open coordinates. An
+/// `end` of `None` extends the region to the end of the chromosome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl FromStr for Region {
+    type Err = Error;
+
+    /// Parse a samtools style region: `name`, `name:start`, `name:start-` or `name:start-end`.
+    /// Coordinates are one-based and inclusive and can contain commas as thousands separators.
+    /// A name that contains a `:` followed by digits is parsed as a name and coordinates, use
+    /// `IndexedFasta::view_region` to resolve these using the index.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |msg: &str| Error::InvalidRegion(format!("{} in region {:?}", msg, s));
+
+        let (name, range) = match s.rfind(':') {
+            Some(i) if is_range(&s[i + 1..]) => (&s[..i], Some(&s[i + 1..])),
+            _ => (s, None),
+        };
+        if name.is_empty() {
+            return Err(invalid("Missing chromosome name"));
+        }
+
+        let (start, end) = match range {
+            None => (0, None),
+            Some(range) => {
+                let (start, end) = match range.split_once('-') {
+                    Some((start, end)) => (start, Some(end).filter(|e| !e.is_empty())),
+                    None => (range, None),
+                };
+                let start = parse_coordinate(start).ok_or_else(|| invalid("Invalid start"))?;
+                let end = match end {
+                    Some(e) => Some(parse_coordinate(e).ok_or_else(|| invalid("Invalid end"))?),
+                    None => None,
+                };
+                if start == 0 {
+                    return Err(invalid("Start coordinate should be at least 1"));
+                }
+                if end.is_some_and(|e| e < start - 1) {
+                    return Err(invalid("End before start"));
+                }
+                (start - 1, end)
+            }
+        };
+
+        Ok(Region {
+            name: name.to_owned(),
+            start,
+            end,
+        })
+    }
+}
+
+/// Formats the region as a samtools style region string.
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}:{}-{}", self.name, self.start + 1, end),
+            None if self.start == 0 => write!(f, "{}", self.name),
+            None => write!(f, "{}:{}-", self.name, self.start + 1),
+        }
+    }
+}
+
+fn is_range(s: &str) -> bool {
+    !s.is_empty()
+        && s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c == '-')
+}
+
+fn parse_coordinate(s: &str) -> Option<usize> {
+    s.replace(',', "").parse().ok()
+}
+
+impl IndexedFasta {
+    /// Parse a samtools style region string (see `Region`) and return a view of it. A string
+    /// that exactly matches a chromosome name is always used as a name, even if it contains a
+    /// `:`.
+    ///
+    /// Returns a FastaView for the region if successful, Error otherwise. The error is
+    /// `Error::UnknownName` if the chromosome is unknown.
+    pub fn view_region(&self, region: &str) -> Result<FastaView<'_>> {
+        if let Some(tid) = self.fai().tid(region) {
+            return self.view_tid(tid);
+        }
+        let region: Region = region.parse()?;
+        let tid = self.tid_by_name(&region.name)?;
+        let end = match region.end {
+            Some(end) => end,
+            None => self.fai().size(tid)?,
+        };
+        self.view(tid, region.start, end)
+    }
+}
+
+/// A set of intervals on the chromosomes of a fasta index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionSet {
+    lengths: Vec<usize>,
+    intervals: Vec<Vec<(usize, usize)>>,
+}
+
+impl RegionSet {
+    /// Create an empty `RegionSet` for the chromosomes in `fai`.
+    pub fn new(fai: &Fai) -> Self {
+        let lengths: Vec<usize> = (0..fai.name_map.len())
+            .map(|tid| fai.size(tid).unwrap_or(0))
+            .collect();
+        let intervals = vec![Vec::new(); lengths.len()];
+        RegionSet { lengths, intervals }
+    }
+
+    /// Create a `RegionSet` that covers every chromosome in `fai` from start to end.
+    pub fn genome(fai: &Fai) -> Self {
+        let mut set = RegionSet::new(fai);
+        for (tid, &len) in set.lengths.iter().enumerate() {
+            if len > 0 {
+                set.intervals[tid].push((0, len));
+            }
+        }
+        set
+    }
+
+    /// Add the zero-based, half-open interval `start..end` on chromosome `tid`.
+    ///
+    /// Returns `Error::UnknownTid` when the tid is unknown and `Error::OutOfRange` when the
+    /// interval is reversed or extends beyond the end of the chromosome. Empty intervals are
+    /// accepted but not stored.
+    pub fn insert(&mut self, tid: usize, start: usize, end: usize) -> Result<()> {
+        let len = *self.lengths.get(tid).ok_or(Error::UnknownTid(tid))?;
+        if start > end || end > len {
+            return Err(Error::OutOfRange {
+                tid,
+                start,
+                stop: end,
+                len,
+            });
+        }
+        if start < end {
+            self.intervals[tid].push((start, end));
+        }
+        Ok(())
+    }
+
+    /// Sort the intervals of every chromosome by start and end coordinate. Duplicate and
+    /// overlapping intervals are kept.
+    pub fn sort(&mut self) {
+        for ivs in &mut self.intervals {
+            ivs.sort_unstable();
+        }
+    }
+
+    /// Sort the intervals and merge all overlapping and bookended intervals. This also removes
+    /// duplicates.
+    pub fn merge(&mut self) {
+        for ivs in &mut self.intervals {
+            merge_intervals(ivs);
+        }
+    }
+
+    /// Return a new, merged `RegionSet` covering all bases on the chromosomes that are not
+    /// covered by `self`.
+    pub fn complement(&self) -> RegionSet {
+        let mut merged = self.clone();
+        merged.merge();
+
+        let intervals = merged
+            .intervals
+            .iter()
+            .zip(&merged.lengths)
+            .map(|(ivs, &len)| {
+                let mut out = Vec::with_capacity(ivs.len() + 1);
+                let mut pos = 0;
+                for &(start, end) in ivs {
+                    if start > pos {
+                        out.push((pos, start));
+                    }
+                    pos = end;
+                }
+                if pos < len {
+                    out.push((pos, len));
+                }
+                out
+            })
+            .collect();
+
+        RegionSet {
+            lengths: merged.lengths,
+            intervals,
+        }
+    }
+
+    /// Return a new, merged `RegionSet` with all bases covered by `other` removed from `self`.
+    /// Chromosomes are matched by tid, so both sets should originate from the same `Fai`.
+    pub fn subtract(&self, other: &RegionSet) -> RegionSet {
+        let mut merged = self.clone();
+        merged.merge();
+        let mut remove = other.clone();
+        remove.merge();
+
+        for (tid, ivs) in merged.intervals.iter_mut().enumerate() {
+            let remove = match remove.intervals.get(tid) {
+                Some(r) if !r.is_empty() => r,
+                _ => continue,
+            };
+
+            let mut out = Vec::with_capacity(ivs.len());
+            let mut r = 0;
+            for &(start, end) in ivs.iter() {
+                let mut pos = start;
+                while r < remove.len() && remove[r].1 <= pos {
+                    r += 1;
+                }
+                let mut i = r;
+                while i < remove.len() && remove[i].0 < end {
+                    if remove[i].0 > pos {
+                        out.push((pos, remove[i].0));
+                    }
+                    pos = pos.max(remove[i].1);
+                    i += 1;
+                }
+                if pos < end {
+                    out.push((pos, end));
+                }
+            }
+            *ivs = out;
+        }
+
+        merged
+    }
+
+    /// Iterate over the intervals as `(tid, start, end)` tuples, ordered by tid.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.intervals
+            .iter()
+            .enumerate()
+            .flat_map(|(tid, ivs)| ivs.iter().map(move |&(s, e)| (tid, s, e)))
+    }
+
+    /// Return the intervals stored for chromosome `tid`, or an empty slice if the tid is
+    /// unknown.
+    pub fn intervals(&self, tid: usize) -> &[(usize, usize)] {
+        self.intervals.get(tid).map_or(&[], |v| v.as_slice())
+    }
+
+    /// Return the intervals on chromosome `tid` that overlap `start..end`. The set should be
+    /// merged.
+    pub(crate) fn overlapping(&self, tid: usize, start: usize, end: usize) -> &[(usize, usize)] {
+        let ivs = self.intervals(tid);
+        let first = ivs.partition_point(|&(_, e)| e <= start);
+        let last = ivs.partition_point(|&(s, _)| s < end);
+        &ivs[first..last.max(first)]
+    }
+
+    /// Return the number of stored intervals.
+    pub fn len(&self) -> usize {
+        self.intervals.iter().map(|v| v.len()).sum()
+    }
+
+    /// Returns true if the set contains no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.intervals.iter().all(|v| v.is_empty())
+    }
+
+    /// Return the number of bases covered by the set. Overlapping intervals are only counted
+    /// once.
+    pub fn covered_bases(&self) -> usize {
+        let mut merged = self.clone();
+        merged.merge();
+        merged.iter().map(|(_, s, e)| e - s).sum()
+    }
+}
+
+fn merge_intervals(ivs: &mut Vec<(usize, usize)>) {
+    ivs.sort_unstable();
+    let mut out: Vec<(usize, usize)> = Vec::with_capacity(ivs.len());
+    for &(start, end) in ivs.iter() {
+        match out.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => out.push((start, end)),
+        }
+    }
+    *ivs = out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexedFasta;
+
+    #[test]
+    fn parse_region() {
+        let r: Region = "chr1:1,000,000-1,000,500".parse().unwrap();
+        assert_eq!(
+            r,
+            Region {
+                name: "chr1".to_owned(),
+                start: 999_999,
+                end: Some(1_000_500)
+            }
+        );
+        assert_eq!(r.to_string(), "chr1:1000000-1000500");
+        let r: Region = "chr1".parse().unwrap();
+        assert_eq!((r.start, r.end), (0, None));
+        let r: Region = "chr1:100".parse().unwrap();
+        assert_eq!((r.start, r.end), (99, None));
+        assert_eq!(r.to_string(), "chr1:100-");
+        // without an index a trailing number is always a coordinate
+        let r: Region = "HLA-A*01:01".parse().unwrap();
+        assert_eq!((r.name.as_str(), r.start), ("HLA-A*01", 0));
+        let r: Region = "HLA-A*01:01:5-6".parse().unwrap();
+        assert_eq!(
+            (r.name.as_str(), r.start, r.end),
+            ("HLA-A*01:01", 4, Some(6))
+        );
+        assert!("chr1:0-10".parse::<Region>().is_err());
+        assert!("chr1:10-5".parse::<Region>().is_err());
+        assert!(":1-5".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn view_region() {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert_eq!(ir.view_region("A-10").unwrap().to_string(), "AAAAAAAAAA");
+        assert_eq!(ir.view_region("ACGT-25:49-52").unwrap().to_string(), "CCGG");
+        assert_eq!(ir.view_region("ACGT-25:99").unwrap().to_string(), "TT");
+        assert!(matches!(
+            ir.view_region("chr1:1-10").err(),
+            Some(Error::UnknownName(_))
+        ));
+        assert!(matches!(
+            ir.view_region("A-10:5-11").err(),
+            Some(Error::OutOfRange { len: 10, .. })
+        ));
+    }
+
+    fn set() -> RegionSet {
+        let ir = IndexedFasta::from_file("test/genome.fa").unwrap();
+        RegionSet::new(ir.fai())
+    }
+
+    #[test]
+    fn insert_validates() {
+        let mut s = set();
+        assert!(s.insert(0, 0, 10).is_ok());
+        assert!(s.insert(0, 0, 11).is_err());
+        assert!(s.insert(0, 5, 4).is_err());
+        assert!(s.insert(3, 0, 1).is_err());
+        s.insert(1, 5, 5).unwrap();
+        assert_eq!(s.len(), 1);
+    }
+
+    #[test]
+    fn merge() {
+        let mut s = set();
+        s.insert(2, 50, 60).unwrap();
+        s.insert(2, 10, 20).unwrap();
+        s.insert(2, 20, 30).unwrap();
+        s.insert(2, 55, 70).unwrap();
+        s.insert(2, 10, 20).unwrap();
+        s.insert(2, 80, 90).unwrap();
+        s.merge();
+        assert_eq!(s.intervals(2), &[(10, 30), (50, 70), (80, 90)]);
+        assert_eq!(s.covered_bases(), 50);
+    }
+
+    #[test]
+    fn complement() {
+        let mut s = set();
+        s.insert(0, 0, 5).unwrap();
+        s.insert(2, 10, 20).unwrap();
+        s.insert(2, 90, 100).unwrap();
+        let c = s.complement();
+        assert_eq!(c.intervals(0), &[(5, 10)]);
+        assert_eq!(c.intervals(1), &[(0, 100)]);
+        assert_eq!(c.intervals(2), &[(0, 10), (20, 90)]);
+        assert_eq!(c.complement(), {
+            s.merge();
+            s
+        });
+    }
+
+    #[test]
+    fn subtract() {
+        let mut s = set();
+        s.insert(2, 0, 50).unwrap();
+        s.insert(2, 60, 100).unwrap();
+        let mut r = set();
+        r.insert(2, 10, 20).unwrap();
+        r.insert(2, 15, 25).unwrap();
+        r.insert(2, 45, 65).unwrap();
+        r.insert(2, 90, 100).unwrap();
+        r.insert(1, 0, 100).unwrap();
+        let d = s.subtract(&r);
+        assert_eq!(d.intervals(2), &[(0, 10), (25, 45), (65, 90)]);
+        assert!(d.intervals(1).is_empty());
+    }
+}
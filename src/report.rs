@@ -0,0 +1,243 @@
+//! Render a region of a fasta file as a text or HTML snapshot for reports.
+//!
+//! The sequence is wrapped in blocks of a fixed number of bases. Each block has a ruler with
+//! one-based coordinates for every tenth base, the sequence itself with the coordinates of the
+//! first and last base, and optionally a mask track for soft-masked (lowercase) bases and a
+//! translation track using the standard or another genetic code. In HTML output soft-masked bases are
+//! highlighted with a `<span class="masked">` element instead of a mask track.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::report::LocusReport;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let report = LocusReport::new().line_width(40).translation(0);
+//! println!("{}", report.to_text(&fa, 2, 10, 60).expect("Cannot render region"));
+//! ```
+
+use std::fmt::Write;
+
+use crate::translate::GeneticCode;
+use crate::{IndexedFasta, Result};
+
+/// Configures the layout of a rendered region.
+#[derive(Debug, Clone)]
+pub struct LocusReport {
+    line_width: usize,
+    frame: Option<usize>,
+    code: GeneticCode,
+}
+
+impl Default for LocusReport {
+    fn default() -> Self {
+        LocusReport {
+            line_width: 60,
+            frame: None,
+            code: GeneticCode::Standard,
+        }
+    }
+}
+
+impl LocusReport {
+    /// Create a report layout with 60 bases per block and no translation track.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the number of bases per block.
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.line_width = width.max(1);
+        self
+    }
+
+    /// Add a translation track that starts translating at `frame` (0, 1 or 2) bases from the
+    /// start of the region. Amino acids are shown below the middle base of their codon.
+    pub fn translation(mut self, frame: usize) -> Self {
+        self.frame = Some(frame % 3);
+        self
+    }
+
+    /// Set the genetic code of the translation track, the standard code by default.
+    pub fn genetic_code(mut self, code: GeneticCode) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Render the region `start..stop` of chromosome `tid` (zero-based coordinates) as plain
+    /// text.
+    pub fn to_text(
+        &self,
+        fa: &IndexedFasta,
+        tid: usize,
+        start: usize,
+        stop: usize,
+    ) -> Result<String> {
+        self.render(fa, tid, start, stop, false)
+    }
+
+    /// Render the region `start..stop` of chromosome `tid` (zero-based coordinates) as an HTML
+    /// `<pre>` element.
+    pub fn to_html(
+        &self,
+        fa: &IndexedFasta,
+        tid: usize,
+        start: usize,
+        stop: usize,
+    ) -> Result<String> {
+        self.render(fa, tid, start, stop, true)
+    }
+
+    fn render(
+        &self,
+        fa: &IndexedFasta,
+        tid: usize,
+        start: usize,
+        stop: usize,
+        html: bool,
+    ) -> Result<String> {
+        let name = fa.fai().name(tid)?;
+        let bases: Vec<u8> = fa.view(tid, start, stop)?.bases().cloned().collect();
+        let aa = self
+            .frame
+            .map(|frame| translation_track(&bases, frame, self.code));
+
+        let margin = (stop.max(1)).to_string().len();
+        let pad = " ".repeat(margin + 1);
+        let mut out = String::new();
+
+        if html {
+            out.push_str("<pre class=\"faimm-locus\">");
+            out.push_str(&escape_html(&format!("{}:{}-{}\n", name, start + 1, stop)));
+        } else {
+            writeln!(out, "{}:{}-{}", name, start + 1, stop).unwrap();
+        }
+
+        for (i, block) in bases.chunks(self.line_width).enumerate() {
+            let first = start + i * self.line_width;
+
+            // ruler with labels at every tenth base (one-based)
+            let mut labels = String::new();
+            let mut ticks = String::new();
+            for pos in first + 1..=first + block.len() {
+                let col = pos - first - 1;
+                if pos % 10 == 0 {
+                    if labels.len() <= col {
+                        labels.push_str(&" ".repeat(col - labels.len()));
+                        labels.push_str(&pos.to_string());
+                    }
+                    ticks.push_str(&" ".repeat(col - ticks.len()));
+                    ticks.push('|');
+                }
+            }
+            writeln!(out, "{}{}", pad, labels.trim_end()).unwrap();
+            writeln!(out, "{}{}", pad, ticks).unwrap();
+
+            write!(out, "{:>w$} ", first + 1, w = margin).unwrap();
+            if html {
+                write_html_bases(&mut out, block);
+            } else {
+                out.push_str(std::str::from_utf8(block).unwrap_or(""));
+            }
+            writeln!(out, " {}", first + block.len()).unwrap();
+
+            if !html && block.iter().any(u8::is_ascii_lowercase) {
+                let mask: String = block
+                    .iter()
+                    .map(|b| if b.is_ascii_lowercase() { '~' } else { ' ' })
+                    .collect();
+                writeln!(out, "{}{}", pad, mask.trim_end()).unwrap();
+            }
+
+            if let Some(aa) = &aa {
+                let offset = i * self.line_width;
+                let track = &aa[offset..offset + block.len()];
+                writeln!(out, "{}{}", pad, track.trim_end()).unwrap();
+            }
+        }
+
+        if html {
+            out.push_str("</pre>\n");
+        }
+        Ok(out)
+    }
+}
+
+fn write_html_bases(out: &mut String, block: &[u8]) {
+    let mut masked = false;
+    for &b in block {
+        if b.is_ascii_lowercase() != masked {
+            masked = !masked;
+            out.push_str(if masked {
+                "<span class=\"masked\">"
+            } else {
+                "</span>"
+            });
+        }
+        out.push_str(&escape_html(&(b as char).to_string()));
+    }
+    if masked {
+        out.push_str("</span>");
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A string with the same length as `bases` with the amino acid of every complete codon below
+/// its middle base.
+fn translation_track(bases: &[u8], frame: usize, code: GeneticCode) -> String {
+    let mut track = vec![b' '; bases.len()];
+    let mut i = frame;
+    while i + 3 <= bases.len() {
+        track[i + 1] = code.translate_codon(&bases[i..i + 3]);
+        i += 3;
+    }
+    String::from_utf8(track).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codons() {
+        assert_eq!(
+            translation_track(b"AATGGCC", 1, GeneticCode::Standard),
+            "  M  A "
+        );
+        assert_eq!(
+            translation_track(b"TGA", 0, GeneticCode::VertebrateMitochondrial),
+            " W "
+        );
+    }
+
+    #[test]
+    fn text() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let text = LocusReport::new()
+            .line_width(20)
+            .translation(0)
+            .to_text(&fa, 2, 15, 35)
+            .unwrap();
+        let expected = "ACGT-25:16-35
+       20        30
+       |         |
+16 AAAAAAAAAACCCCCCCCCC 35
+    K  K  K  T  P  P
+";
+        assert_eq!(text, expected);
+        let html = LocusReport::new().to_html(&fa, 0, 0, 4).unwrap();
+        assert!(html.starts_with("<pre class=\"faimm-locus\">A-10:1-4\n"));
+        assert!(LocusReport::new().to_text(&fa, 0, 0, 11).is_err());
+    }
+
+    #[test]
+    fn html_masking() {
+        let mut out = String::new();
+        write_html_bases(&mut out, b"ACgtA");
+        assert_eq!(out, "AC<span class=\"masked\">gt</span>A");
+    }
+}
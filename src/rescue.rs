@@ -0,0 +1,133 @@
+//! Fetching sequences by the names of another build of the reference (feature `checksum`).
+//!
+//! Pipelines that use different builds of the same reference disagree on the names of the
+//! sequences, like `chr1`, `1` and `NC_000001.11`. `IndexedFasta::rescue_tid` looks up a name
+//! that is not in the index with the aliases of the `Fai` (see `alias::Aliases`), and then by
+//! the MD5 digest of the sequence with that name in the sequence dictionary of the other build
+//! (see `dict::read_dict`). The `Strategy` that found the sequence is returned, so a pipeline
+//! can report how every name was resolved. The digest lookup calculates the checksums of all
+//! sequences on first use, see `IndexedFasta::tid_by_md5`.
+//!
+//! # Example
+//! ```
+//! use faimm::dict::read_dict;
+//! use faimm::rescue::Strategy;
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let dict = "@SQ\tSN:chrX\tLN:100\tM5:2f488dab379d6a0c424926f54dc8a333\n";
+//! let dict = read_dict(dict.as_bytes()).expect("Invalid dictionary");
+//! let (view, strategy) = fa.rescue_view("chrX", 20, 30, &dict).expect("Cannot rescue");
+//! assert_eq!(view.to_string(), "AAAAACCCCC");
+//! assert_eq!(strategy, Strategy::Digest);
+//! assert_eq!(fa.rescue_tid("A-100", &dict).unwrap(), (1, Strategy::Name));
+//! ```
+
+use std::fmt;
+
+use crate::dict::DictSequence;
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// How `IndexedFasta::rescue_tid` found a sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// The name is in the index.
+    Name,
+    /// An alias of the name is in the index.
+    Alias,
+    /// A sequence in the index has the MD5 digest of the sequence with the name in the
+    /// dictionary.
+    Digest,
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Strategy::Name => write!(f, "name"),
+            Strategy::Alias => write!(f, "alias"),
+            Strategy::Digest => write!(f, "digest"),
+        }
+    }
+}
+
+impl IndexedFasta {
+    /// Return the index of the sequence `name`, trying the name itself, its aliases and the
+    /// MD5 digest of the sequence with that name (`SN`) or alternative name (`AN`) in `dict`,
+    /// in that order.
+    ///
+    /// Returns the tid and the `Strategy` that found it if successful, `Error::UnknownName` if
+    /// no strategy found the sequence, Error otherwise.
+    pub fn rescue_tid(&self, name: &str, dict: &[DictSequence]) -> Result<(usize, Strategy)> {
+        if let Some(tid) = self.fai().name_map.get_index_of(name) {
+            return Ok((tid, Strategy::Name));
+        }
+        if let Some(tid) = self.fai().tid(name) {
+            return Ok((tid, Strategy::Alias));
+        }
+        let md5 = dict
+            .iter()
+            .find(|s| s.name == name || s.alt_names.iter().any(|n| n == name))
+            .and_then(|s| s.md5.as_deref());
+        if let Some(md5) = md5 {
+            if let Some(tid) = self.tid_by_md5(md5)? {
+                return Ok((tid, Strategy::Digest));
+            }
+        }
+        Err(Error::UnknownName(name.to_owned()))
+    }
+
+    /// Return a view of the sequence `name` from `start` to `stop`, with the sequence found by
+    /// `IndexedFasta::rescue_tid`.
+    ///
+    /// Returns the view and the `Strategy` that found the sequence if successful, Error
+    /// otherwise.
+    pub fn rescue_view(
+        &self,
+        name: &str,
+        start: usize,
+        stop: usize,
+        dict: &[DictSequence],
+    ) -> Result<(FastaView<'_>, Strategy)> {
+        let (tid, strategy) = self.rescue_tid(name, dict)?;
+        Ok((self.view(tid, start, stop)?, strategy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alias::Aliases;
+    use crate::dict::{read_dict, write_dict};
+
+    #[test]
+    fn rescue() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut dict = Vec::new();
+        write_dict(&fa, None, &mut dict).unwrap();
+        // the other build names the sequences differently
+        let dict = String::from_utf8(dict)
+            .unwrap()
+            .replace("SN:A-10", "SN:ten\tAN:chr10")
+            .replace("SN:ACGT-25", "SN:acgt");
+        let dict = read_dict(dict.as_bytes()).unwrap();
+
+        assert_eq!(fa.rescue_tid("A-10", &dict).unwrap(), (0, Strategy::Name));
+        assert_eq!(fa.rescue_tid("ten", &dict).unwrap(), (0, Strategy::Digest));
+        assert_eq!(
+            fa.rescue_tid("chr10", &dict).unwrap(),
+            (0, Strategy::Digest)
+        );
+        assert!(matches!(
+            fa.rescue_tid("chr1", &dict),
+            Err(Error::UnknownName(n)) if n == "chr1"
+        ));
+        assert!(fa.rescue_tid("acgt", &[]).is_err());
+        let (view, strategy) = fa.rescue_view("acgt", 45, 55, &dict).unwrap();
+        assert_eq!(view.to_string(), "CCCCCGGGGG");
+        assert_eq!(strategy.to_string(), "digest");
+
+        let mut aliases = Aliases::new();
+        aliases.add(&["acgt", "ACGT-25"]);
+        let fa = fa.with_aliases(aliases);
+        assert_eq!(fa.rescue_tid("acgt", &dict).unwrap(), (2, Strategy::Alias));
+    }
+}
@@ -0,0 +1,196 @@
+//! A drop-in replacement for the indexed reader of [rust-bio](https://crates.io/crates/bio)
+//! (feature `bio`).
+//!
+//! `IndexedReader` has the `fetch`/`read` interface of `bio::io::fasta::IndexedReader`, so a
+//! project can switch to faimm by changing the import. The bases are copied from the mapped
+//! file instead of being read and seeked through a buffered file. It also implements
+//! `bio::io::fasta::FastaRead`, reading the sequences in index order. The index doesn't store
+//! the description of a record, so the records have no description.
+//!
+//! # Example
+//! ```
+//! use faimm::rust_bio::IndexedReader;
+//! let mut faidx = IndexedReader::from_file(&"test/genome.fa").expect("Error opening fa");
+//! faidx.fetch("ACGT-25", 20, 30).expect("Couldn't fetch interval");
+//! let mut seq = Vec::new();
+//! faidx.read(&mut seq).expect("Couldn't read the interval");
+//! assert_eq!(seq, b"AAAAACCCCC");
+//! assert_eq!(faidx.index.sequences()[2].len, 100);
+//! ```
+
+use std::io;
+use std::path::Path;
+
+use bio::io::fasta::{FastaRead, Index, Record, Sequence};
+
+use crate::{Error, IndexedFasta, Result};
+
+/// An `IndexedFasta` with the interface of `bio::io::fasta::IndexedReader`.
+pub struct IndexedReader {
+    fasta: IndexedFasta,
+    /// The index in the representation of rust-bio.
+    pub index: Index,
+    fetched: Option<(usize, u64, u64)>,
+    next_tid: usize,
+}
+
+impl IndexedReader {
+    /// Open a fasta file from path `P`, the index is expected at `P.fai`.
+    ///
+    /// Returns an IndexedReader if successful, Error otherwise.
+    pub fn from_file<P: AsRef<Path>>(path: &P) -> Result<Self> {
+        IndexedReader::try_from(IndexedFasta::from_file(path)?)
+    }
+
+    /// The wrapped `IndexedFasta`.
+    pub fn fasta(&self) -> &IndexedFasta {
+        &self.fasta
+    }
+
+    /// The names and lengths of the sequences in index order.
+    pub fn sequences(&self) -> Vec<Sequence> {
+        self.index.sequences()
+    }
+
+    /// Fetch the zero-based, half-open interval start-stop of sequence `seq_name` for reading.
+    ///
+    /// Returns an `io::Error` with kind `NotFound` if the name is not in the index.
+    pub fn fetch(&mut self, seq_name: &str, start: u64, stop: u64) -> io::Result<()> {
+        let tid = self.tid(seq_name)?;
+        self.fetch_by_rid(tid, start, stop)
+    }
+
+    /// Fetch the zero-based, half-open interval start-stop of sequence `rid` for reading.
+    pub fn fetch_by_rid(&mut self, rid: usize, start: u64, stop: u64) -> io::Result<()> {
+        self.fasta.fai().size(rid)?;
+        self.fetched = Some((rid, start, stop));
+        Ok(())
+    }
+
+    /// Fetch the whole sequence `seq_name` for reading.
+    pub fn fetch_all(&mut self, seq_name: &str) -> io::Result<()> {
+        let tid = self.tid(seq_name)?;
+        self.fetch_all_by_rid(tid)
+    }
+
+    /// Fetch the whole sequence `rid` for reading.
+    pub fn fetch_all_by_rid(&mut self, rid: usize) -> io::Result<()> {
+        let len = self.fasta.fai().size(rid)?;
+        self.fetched = Some((rid, 0, len as u64));
+        Ok(())
+    }
+
+    /// Replace the contents of `seq` with the bases of the fetched interval.
+    pub fn read(&mut self, seq: &mut Vec<u8>) -> io::Result<()> {
+        seq.clear();
+        seq.extend(self.fetched_view()?.bases());
+        Ok(())
+    }
+
+    /// An iterator over the bases of the fetched interval.
+    pub fn read_iter(&mut self) -> io::Result<impl Iterator<Item = io::Result<u8>> + '_> {
+        Ok(self.fetched_view()?.bases().map(|&b| Ok(b)))
+    }
+
+    fn tid(&self, seq_name: &str) -> io::Result<usize> {
+        self.fasta
+            .fai()
+            .tid(seq_name)
+            .ok_or_else(|| Error::UnknownName(seq_name.to_owned()).into())
+    }
+
+    fn fetched_view(&self) -> io::Result<crate::FastaView<'_>> {
+        let (tid, start, stop) = self
+            .fetched
+            .ok_or_else(|| io::Error::other("No sequence fetched for reading."))?;
+        Ok(self.fasta.view(tid, start as usize, stop as usize)?)
+    }
+}
+
+/// Fails with `Error::Io` if rust-bio cannot parse the index.
+impl TryFrom<IndexedFasta> for IndexedReader {
+    type Error = Error;
+
+    fn try_from(fasta: IndexedFasta) -> Result<Self> {
+        let mut fai = Vec::new();
+        fasta.fai().write_to(&mut fai)?;
+        let index = Index::new(&fai[..])
+            .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        Ok(IndexedReader {
+            fasta,
+            index,
+            fetched: None,
+            next_tid: 0,
+        })
+    }
+}
+
+/// Reads the complete sequences in index order. After the last sequence the record is empty.
+impl FastaRead for IndexedReader {
+    fn read(&mut self, record: &mut Record) -> io::Result<()> {
+        let fai = self.fasta.fai();
+        *record = match fai.names().get(self.next_tid) {
+            Some(name) => {
+                let seq: Vec<u8> = self
+                    .fasta
+                    .view_tid(self.next_tid)?
+                    .bases()
+                    .copied()
+                    .collect();
+                self.next_tid += 1;
+                Record::with_attrs(name, None, &seq)
+            }
+            None => Record::new(),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_as_bio() {
+        let mut faimm = IndexedReader::from_file(&"test/genome.fa").unwrap();
+        let mut bio = bio::io::fasta::IndexedReader::from_file(&"test/genome.fa").unwrap();
+        assert_eq!(faimm.sequences(), bio.index.sequences());
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        for (name, start, stop) in [("ACGT-25", 20, 60), ("A-100", 0, 100), ("A-10", 3, 3)] {
+            faimm.fetch(name, start, stop).unwrap();
+            bio.fetch(name, start, stop).unwrap();
+            faimm.read(&mut a).unwrap();
+            bio.read(&mut b).unwrap();
+            assert_eq!(a, b);
+        }
+        faimm.fetch_all_by_rid(2).unwrap();
+        let iter: Vec<u8> = faimm.read_iter().unwrap().map(|b| b.unwrap()).collect();
+        assert_eq!(iter.len(), 100);
+
+        assert!(faimm.fetch("chr1", 0, 1).is_err());
+        faimm.fetch("A-10", 0, 11).unwrap();
+        assert!(faimm.read(&mut a).is_err());
+    }
+
+    #[test]
+    fn fasta_read() {
+        let mut faimm = IndexedReader::from_file(&"test/genome.fa").unwrap();
+        let mut record = Record::new();
+        let mut ids = Vec::new();
+        loop {
+            FastaRead::read(&mut faimm, &mut record).unwrap();
+            if record.is_empty() {
+                break;
+            }
+            ids.push((record.id().to_owned(), record.seq().len()));
+        }
+        assert_eq!(
+            ids,
+            [
+                ("A-10".to_owned(), 10),
+                ("A-100".to_owned(), 100),
+                ("ACGT-25".to_owned(), 100)
+            ]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Seeded uniform random sampling of positions and k-mers.
+//!
+//! `Reservoir` is a streaming reservoir sampler (Algorithm L). Besides offering items one by one
+//! it can skip over a range of items without creating them, which makes it possible to sample
+//! positions from a whole genome using only the chromosome lengths in the index.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let kmers = fa.sample_kmers(5, 10, 42).expect("Cannot sample");
+//! assert_eq!(kmers.len(), 10);
+//! for (tid, pos, view) in kmers {
+//!     println!("{}:{} {}", fa.fai().name(tid).unwrap(), pos, view.to_string());
+//! }
+//! ```
+
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// A small, seedable pseudo random number generator (SplitMix64).
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform random number in the open interval (0, 1).
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// A uniform random number in `0..n`.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        (self.next_f64() * n as f64) as u64 % n.max(1)
+    }
+}
+
+/// A seeded reservoir sampler that keeps a uniform random sample of at most `size` items from a
+/// stream of unknown length.
+#[derive(Debug, Clone)]
+pub struct Reservoir<T> {
+    size: usize,
+    items: Vec<T>,
+    seen: u64,
+    next: u64,
+    w: f64,
+    rng: SplitMix64,
+}
+
+impl<T> Reservoir<T> {
+    /// Create an empty reservoir for `size` items. The same seed and input give the same sample.
+    pub fn new(size: usize, seed: u64) -> Self {
+        let mut r = Reservoir {
+            size,
+            items: Vec::with_capacity(size),
+            seen: 0,
+            next: size as u64,
+            w: 1.0,
+            rng: SplitMix64::new(seed),
+        };
+        r.w = r.draw_w();
+        r.next = r.next.saturating_add(r.draw_skip());
+        r
+    }
+
+    /// The number of items offered so far.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// The current sample.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Consume the reservoir and return the sample.
+    pub fn into_vec(self) -> Vec<T> {
+        self.items
+    }
+
+    /// Offer a single item.
+    pub fn push(&mut self, item: T) {
+        let mut item = Some(item);
+        self.push_range(1, |_| item.take().unwrap());
+    }
+
+    /// Offer `count` items without creating them up front. `make` is only called for the items
+    /// that enter the sample, with the position of the item (zero-based) within this range.
+    pub fn push_range<F: FnMut(u64) -> T>(&mut self, count: u64, mut make: F) {
+        let start = self.seen;
+        let end = start + count;
+
+        while self.items.len() < self.size && self.seen < end {
+            self.items.push(make(self.seen - start));
+            self.seen += 1;
+        }
+
+        if self.size > 0 {
+            while self.next < end {
+                let slot = self.rng.below(self.size as u64) as usize;
+                self.items[slot] = make(self.next - start);
+                self.w *= self.draw_w();
+                self.next = self.next.saturating_add(self.draw_skip() + 1);
+            }
+        }
+        self.seen = end;
+    }
+
+    fn draw_w(&mut self) -> f64 {
+        (self.rng.next_f64().ln() / self.size.max(1) as f64).exp()
+    }
+
+    /// The number of items to skip before the next item enters a full reservoir.
+    fn draw_skip(&mut self) -> u64 {
+        // the float to int cast saturates for huge skips
+        (self.rng.next_f64().ln() / (1.0 - self.w).ln()).floor() as u64
+    }
+}
+
+impl IndexedFasta {
+    /// Draw a seeded, uniform random sample of `n` positions from all chromosomes, without
+    /// replacement. Only the chromosome lengths are used, so no sequence data is read.
+    ///
+    /// Returns `(tid, position)` tuples sorted by tid and position.
+    pub fn sample_positions(&self, n: usize, seed: u64) -> Vec<(usize, usize)> {
+        self.sample_starts(1, n, seed)
+    }
+
+    /// Draw a seeded, uniform random sample of `n` k-mers from all chromosomes, without
+    /// replacement. Every position where a k-mer fits on the chromosome has the same
+    /// probability to be drawn. k-mers containing N or other bases are not filtered out.
+    ///
+    /// Returns `(tid, position, FastaView)` tuples sorted by tid and position.
+    pub fn sample_kmers(
+        &self,
+        k: usize,
+        n: usize,
+        seed: u64,
+    ) -> Result<Vec<(usize, usize, FastaView<'_>)>> {
+        if k == 0 {
+            return Err(Error::InvalidArgument(
+                "k-mer size should be at least 1".to_owned(),
+            ));
+        }
+        self.sample_starts(k, n, seed)
+            .into_iter()
+            .map(|(tid, pos)| Ok((tid, pos, self.view(tid, pos, pos + k)?)))
+            .collect()
+    }
+
+    fn sample_starts(&self, k: usize, n: usize, seed: u64) -> Vec<(usize, usize)> {
+        let mut reservoir = Reservoir::new(n, seed);
+        for tid in 0..self.fai().names().len() {
+            let len = self.fai().size(tid).unwrap_or(0);
+            if len >= k {
+                reservoir.push_range((len - k + 1) as u64, |i| (tid, i as usize));
+            }
+        }
+        let mut sample = reservoir.into_vec();
+        sample.sort_unstable();
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservoir_uniform() {
+        // every item of a 20 item stream should be drawn in about a quarter of 4000 samples
+        let mut hits = [0usize; 20];
+        for seed in 0..4000 {
+            let mut r = Reservoir::new(5, seed);
+            r.push_range(12, |i| i as usize);
+            for i in 12..20 {
+                r.push(i);
+            }
+            assert_eq!(r.seen(), 20);
+            for &i in r.items() {
+                hits[i] += 1;
+            }
+        }
+        assert!(hits.iter().all(|&h| (850..1150).contains(&h)), "{:?}", hits);
+    }
+
+    #[test]
+    fn reservoir_small() {
+        let mut r = Reservoir::new(5, 1);
+        r.push_range(3, |i| i);
+        assert_eq!(r.into_vec(), vec![0, 1, 2]);
+        let mut r = Reservoir::new(0, 1);
+        r.push_range(3, |i| i);
+        assert!(r.items().is_empty());
+    }
+
+    #[test]
+    fn sample_kmers() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let a = fa.sample_kmers(10, 8, 7).unwrap();
+        let b = fa.sample_kmers(10, 8, 7).unwrap();
+        assert_eq!(a.len(), 8);
+        for ((t1, p1, v1), (t2, p2, v2)) in a.iter().zip(&b) {
+            assert_eq!((t1, p1), (t2, p2));
+            assert_eq!(v1.to_string().len(), 10);
+            assert_eq!(v1.to_string(), v2.to_string());
+        }
+        // the genome only has 210 positions
+        assert_eq!(fa.sample_positions(1000, 3).len(), 210);
+        assert!(fa.sample_kmers(0, 1, 1).is_err());
+    }
+}
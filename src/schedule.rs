@@ -0,0 +1,125 @@
+//! Length-weighted scheduling of whole-genome work.
+//!
+//! When every chromosome is a separate task, a parallel scan waits for the largest chromosome to
+//! finish last. `Schedule` uses the lengths in the `Fai` to divide the genome into a number of
+//! tasks with roughly the same number of bases. Chromosomes that are longer than a task are split
+//! into equal parts, the other chromosomes are combined, largest first, into the task with the
+//! fewest bases. Every task is a list of zero-based, half-open `(tid, start, stop)` regions.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::schedule::Schedule;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let tasks = Schedule::new(3).plan(fa.fai()).expect("Cannot schedule");
+//! for (i, task) in tasks.iter().enumerate() {
+//!     for &(tid, start, stop) in task {
+//!         println!("task {}: {}:{}-{}", i, fa.fai().name(tid).unwrap(), start, stop);
+//!     }
+//! }
+//! ```
+
+use crate::{Error, Fai, Result};
+
+/// Division of the chromosomes of a `Fai` into tasks of roughly equal length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    tasks: usize,
+    split: bool,
+}
+
+impl Schedule {
+    /// Create a schedule with `tasks` tasks. Long chromosomes are split by default.
+    pub fn new(tasks: usize) -> Self {
+        Schedule { tasks, split: true }
+    }
+
+    /// Choose whether chromosomes that are longer than a task are split into parts (`true`, the
+    /// default) or always kept whole (`false`).
+    pub fn split(mut self, split: bool) -> Self {
+        self.split = split;
+        self
+    }
+
+    /// Divide the chromosomes of `fai` into tasks. Tasks are returned from the most to the fewest
+    /// bases and are never empty, so there are fewer tasks than requested when the genome is
+    /// small.
+    ///
+    /// Returns the tasks if successful, `Error::InvalidArgument` if the number of tasks is 0.
+    pub fn plan(&self, fai: &Fai) -> Result<Vec<Vec<(usize, usize, usize)>>> {
+        if self.tasks == 0 {
+            return Err(Error::InvalidArgument(
+                "Number of tasks must be larger than 0".to_owned(),
+            ));
+        }
+
+        let mut regions = Vec::new();
+        for tid in 0..fai.names().len() {
+            let len = fai.size(tid)?;
+            if len > 0 {
+                regions.push((tid, 0, len));
+            }
+        }
+        let total: usize = regions.iter().map(|r| r.2).sum();
+        let target = total.div_ceil(self.tasks).max(1);
+
+        if self.split {
+            regions = regions
+                .into_iter()
+                .flat_map(|(tid, _, len)| {
+                    let parts = len.div_ceil(target);
+                    (0..parts).map(move |i| (tid, i * len / parts, (i + 1) * len / parts))
+                })
+                .collect();
+        }
+
+        // longest processing time first: largest region to the task with the fewest bases
+        regions.sort_by_key(|&(tid, start, stop)| (std::cmp::Reverse(stop - start), tid, start));
+        let mut tasks = vec![(0, Vec::new()); self.tasks];
+        for region in regions {
+            let task = tasks.iter_mut().min_by_key(|(bases, _)| *bases).unwrap();
+            task.0 += region.2 - region.1;
+            task.1.push(region);
+        }
+
+        tasks.sort_by_key(|(bases, _)| std::cmp::Reverse(*bases));
+        Ok(tasks
+            .into_iter()
+            .filter(|(bases, _)| *bases > 0)
+            .map(|(_, regions)| regions)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bases(task: &[(usize, usize, usize)]) -> usize {
+        task.iter().map(|(_, start, stop)| stop - start).sum()
+    }
+
+    #[test]
+    fn plan() {
+        let fai = Fai::from_file("test/genome.fa.fai").unwrap();
+        let tasks = Schedule::new(3).plan(&fai).unwrap();
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(
+            tasks.iter().map(|t| bases(t)).collect::<Vec<_>>(),
+            vec![100, 60, 50]
+        );
+        let mut regions: Vec<_> = tasks.concat();
+        regions.sort();
+        assert_eq!(regions[0], (0, 0, 10));
+        assert_eq!(regions.iter().map(|r| r.2 - r.1).sum::<usize>(), 210);
+
+        let tasks = Schedule::new(2).split(false).plan(&fai).unwrap();
+        assert_eq!(
+            tasks,
+            vec![vec![(1, 0, 100), (0, 0, 10)], vec![(2, 0, 100)]]
+        );
+        assert_eq!(Schedule::new(10).plan(&fai).unwrap().len(), 10);
+        assert_eq!(Schedule::new(10).split(false).plan(&fai).unwrap().len(), 3);
+        assert!(Schedule::new(0).plan(&fai).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Fast exact subsequence search.
+//!
+//! `FastaView::find` locates all occurrences of a sequence, like a probe or an adapter, in a view.
+//! The bases are copied in large chunks without the newlines and searched with the two-way
+//! algorithm of the `memchr` crate, which is much faster than comparing the bases one by one.
+//! Matches that span a line break or a chunk boundary are found as well.
+//!
+//! The search is case-sensitive, so a soft-masked (lowercase) region doesn't match an uppercase
+//! needle. Matches can overlap and are reported as zero-based base positions relative to the
+//! start of the view.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let hits = fa.find(2, b"AACC").expect("Cannot search chromosome");
+//! assert_eq!(hits, vec![23]);
+//! let view = fa.view(1, 40, 60).expect("Cannot get view");
+//! assert_eq!(view.find(b"AAAAAAAAAAAAAAAAAAAA").unwrap(), vec![0]);
+//! ```
+
+use memchr::{memchr, memmem::Finder};
+
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// The number of bases searched at once.
+const CHUNK: usize = 1 << 16;
+
+impl<'a> FastaView<'a> {
+    /// Find all occurrences of `needle` in the current view.
+    ///
+    /// Returns the start positions of the matches, or `Error::InvalidArgument` for an empty
+    /// needle.
+    pub fn find(&self, needle: &[u8]) -> Result<Vec<usize>> {
+        self.find_chunked(needle, CHUNK)
+    }
+
+    fn find_chunked(&self, needle: &[u8], chunk: usize) -> Result<Vec<usize>> {
+        if needle.is_empty() {
+            return Err(Error::InvalidArgument("Empty search sequence".to_owned()));
+        }
+        let finder = Finder::new(needle);
+        let mut hits = Vec::new();
+        let mut buf = Vec::with_capacity(chunk + needle.len());
+        // the base position of the first base in buf
+        let mut buf_start = 0;
+        let mut rest = self.0;
+        // the chunk and the bases kept from the previous chunk
+        let fill = chunk + needle.len() - 1;
+        loop {
+            while buf.len() < fill && !rest.is_empty() {
+                let (line, next) = match memchr(b'\n', rest) {
+                    Some(i) => (&rest[..i], &rest[i + 1..]),
+                    None => (rest, &rest[rest.len()..]),
+                };
+                if line.iter().all(|&b| b & 192 == 64) {
+                    buf.extend_from_slice(line);
+                } else {
+                    buf.extend(line.iter().filter(|&&b| b & 192 == 64));
+                }
+                rest = next;
+            }
+
+            let mut from = 0;
+            while let Some(pos) = finder.find(&buf[from..]) {
+                hits.push(buf_start + from + pos);
+                from += pos + 1;
+            }
+            if rest.is_empty() {
+                return Ok(hits);
+            }
+
+            // keep the bases that can be the start of a match with the next chunk
+            let keep = (needle.len() - 1).min(buf.len());
+            let done = buf.len() - keep;
+            buf.drain(..done);
+            buf_start += done;
+        }
+    }
+}
+
+impl IndexedFasta {
+    /// Find all occurrences of `needle` on chromosome tid.
+    ///
+    /// Returns the start positions of the matches if successful, Error otherwise.
+    pub fn find(&self, tid: usize, needle: &[u8]) -> Result<Vec<usize>> {
+        self.view_tid(tid)?.find(needle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find() {
+        let v = FastaView::new(b"ACGTA\nCGTAC\r\nGTacg\ntAAA");
+        assert_eq!(v.find(b"ACGT").unwrap(), vec![0, 4, 8]);
+        assert_eq!(v.find(b"TACGT").unwrap(), vec![3, 7]);
+        assert_eq!(v.find(b"acgt").unwrap(), vec![12]);
+        assert_eq!(v.find(b"AA").unwrap(), vec![16, 17]);
+        assert!(v.find(b"GG").unwrap().is_empty());
+        assert!(v.find(b"").is_err());
+    }
+
+    #[test]
+    fn chunks() {
+        let v = FastaView::new(b"ACGTACG\nTACGTAC\nGTACGT");
+        let expected = v.find(b"GTAC").unwrap();
+        assert_eq!(expected, vec![2, 6, 10, 14]);
+        for chunk in 1..10 {
+            assert_eq!(v.find_chunked(b"GTAC", chunk).unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! The bytes behind an `IndexedFasta`.
+//!
+//! An `IndexedFasta` maps the index coordinates to byte offsets and creates its views on a
+//! `SequenceSource`. `IndexedFasta::from_file` memory maps the fasta file, but the same index
+//! and views work on fasta data that is already in memory: a `Vec<u8>`, a boxed or static slice
+//! (like `include_bytes!`) or a `Cursor` over any of these. Use `IndexedFasta::from_source` with
+//! an existing index, or `IndexedFasta::index_source` to index the data first.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let data = b">chr1\nACGT\nAC\n>chr2\nGGGG\n".to_vec();
+//! let fa = IndexedFasta::index_source(data).expect("Cannot index fasta");
+//! assert_eq!(fa.view(0, 2, 6).expect("Cannot get view").to_string(), "GTAC");
+//! assert_eq!(fa.fai().tid("chr2"), Some(1));
+//! ```
+//!
+//! # WebAssembly
+//! The crate builds for `wasm32` targets, where memory mapping is not available: the file based
+//! constructors return an error there, but in-memory sources work unchanged. When the complete
+//! file is too large to download, fetch only the `.fai` and read it with `Fai::from_reader`.
+//! `Fai::offset` gives the byte range of a region, which can be fetched with an HTTP range
+//! request and wrapped in a view with `FastaView::new`.
+//!
+//! ```
+//! use faimm::{Fai, FastaView};
+//! let fai = Fai::from_reader("chr1\t6\t6\t4\t5\n".as_bytes()).expect("Invalid index");
+//! let (start_byte, stop_byte) = fai.offset(0, 2, 6).expect("Invalid region");
+//! assert_eq!((start_byte, stop_byte), (8, 13));
+//! // the bytes in this range, fetched from the server
+//! let fetched = b"GT\nAC";
+//! assert_eq!(FastaView::new(fetched).to_string(), "GTAC");
+//! ```
+
+use std::io::{self, Cursor};
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+use crate::advise::Advice;
+
+/// Contiguous fasta data that views are created on.
+pub trait SequenceSource: Send + Sync {
+    /// The complete fasta data.
+    fn bytes(&self) -> &[u8];
+
+    /// Give a hint about the use of `len` bytes at `offset`, see the `advise` module. Sources
+    /// in memory ignore the hint.
+    fn advise(&self, _advice: Advice, _offset: usize, _len: usize) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SequenceSource for Mmap {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    #[cfg(unix)]
+    fn advise(&self, advice: Advice, offset: usize, len: usize) -> io::Result<()> {
+        crate::advise::advise_mmap(self, advice, offset, len)
+    }
+}
+
+impl SequenceSource for Vec<u8> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl SequenceSource for Box<[u8]> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl SequenceSource for Arc<[u8]> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl SequenceSource for &[u8] {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// The complete inner data is used, independent of the position of the cursor.
+impl<T: SequenceSource> SequenceSource for Cursor<T> {
+    fn bytes(&self) -> &[u8] {
+        self.get_ref().bytes()
+    }
+
+    fn advise(&self, advice: Advice, offset: usize, len: usize) -> io::Result<()> {
+        self.get_ref().advise(advice, offset, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fai, IndexedFasta};
+
+    #[test]
+    fn in_memory() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let data: &'static [u8] = include_bytes!("../test/genome.fa");
+        let fai = Fai::from_reader(&include_bytes!("../test/genome.fa.fai")[..]).unwrap();
+        let sources = [
+            IndexedFasta::from_source(data, fai),
+            IndexedFasta::index_source(data.to_vec()).unwrap(),
+            IndexedFasta::index_source(Cursor::new(Box::<[u8]>::from(data))).unwrap(),
+        ];
+        for source in &sources {
+            for tid in 0..3 {
+                assert_eq!(
+                    source.view_tid(tid).unwrap().to_string(),
+                    fa.view_tid(tid).unwrap().to_string()
+                );
+            }
+            assert_eq!(source.fai().names(), fa.fai().names());
+        }
+        assert!(IndexedFasta::index_source(b">a\nAC\nACG\n".to_vec()).is_err());
+    }
+}
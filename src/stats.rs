@@ -0,0 +1,166 @@
+//! Summary statistics of a genome assembly.
+//!
+//! `IndexedFasta::stats` collects the number of sequences, the total length, the N50 and L50,
+//! the largest and smallest sequence and the N content in an `AssemblyStats`. The lengths come
+//! from the index, so only the scan for runs of N (the gaps) reads the sequences. The N50 is the
+//! length of the shortest sequence of the largest sequences that together contain at least half
+//! of the bases, and the L50 is the number of these sequences. `AssemblyStats::nx` calculates
+//! the same for other fractions, like the N90.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! assert_eq!(fa.fai().total_len().unwrap(), 210);
+//! let stats = fa.stats().expect("Cannot read fasta");
+//! assert_eq!((stats.n50, stats.l50), (100, 2));
+//! assert_eq!(stats.largest, ("A-100".to_owned(), 100));
+//! assert_eq!(stats.n_bases, 0);
+//! println!("{}", stats);
+//! ```
+
+use std::fmt;
+
+use crate::{Fai, IndexedFasta, Result};
+
+/// The summary statistics of the sequences in a fasta file, see the module documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblyStats {
+    /// The number of sequences.
+    pub sequences: usize,
+    /// The total number of bases.
+    pub total_len: usize,
+    /// The N50 length, 0 without bases.
+    pub n50: usize,
+    /// The number of sequences of at least the N50 length that contain half of the bases.
+    pub l50: usize,
+    /// The name and length of the largest sequence, the first one in the index on a tie.
+    pub largest: (String, usize),
+    /// The name and length of the smallest sequence, the first one in the index on a tie.
+    pub smallest: (String, usize),
+    /// The number of N bases.
+    pub n_bases: usize,
+    /// The number of runs of N bases.
+    pub gaps: usize,
+    /// The lengths of the sequences from large to small.
+    lengths: Vec<usize>,
+}
+
+impl AssemblyStats {
+    /// The length and number of the largest sequences that together contain at least `fraction`
+    /// (between 0 and 1) of the bases, for instance `nx(0.9)` for the N90 and L90.
+    ///
+    /// Returns `(0, 0)` without bases.
+    pub fn nx(&self, fraction: f64) -> (usize, usize) {
+        let target = (self.total_len as f64 * fraction.clamp(0.0, 1.0)).ceil() as usize;
+        let mut sum = 0;
+        for (i, &len) in self.lengths.iter().enumerate() {
+            sum += len;
+            if sum >= target && len > 0 {
+                return (len, i + 1);
+            }
+        }
+        (0, 0)
+    }
+
+    /// The fraction of N bases, NaN without bases.
+    pub fn n_fraction(&self) -> f64 {
+        self.n_bases as f64 / self.total_len as f64
+    }
+}
+
+/// Writes the statistics as tab separated name and value lines.
+impl fmt::Display for AssemblyStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "sequences\t{}", self.sequences)?;
+        writeln!(f, "total_len\t{}", self.total_len)?;
+        writeln!(f, "n50\t{}", self.n50)?;
+        writeln!(f, "l50\t{}", self.l50)?;
+        writeln!(f, "largest\t{}\t{}", self.largest.0, self.largest.1)?;
+        writeln!(f, "smallest\t{}\t{}", self.smallest.0, self.smallest.1)?;
+        writeln!(f, "n_bases\t{}", self.n_bases)?;
+        write!(f, "gaps\t{}", self.gaps)
+    }
+}
+
+impl Fai {
+    /// The total length of all chromosomes in bases.
+    ///
+    /// Returns the length if successful, `Error::MalformedFai` for a lazily loaded record that
+    /// cannot be parsed.
+    pub fn total_len(&self) -> Result<usize> {
+        self.records().map(|r| Ok(r?.1.len())).sum()
+    }
+}
+
+impl IndexedFasta {
+    /// Calculate the `AssemblyStats` of all sequences, see the module documentation.
+    ///
+    /// Returns the statistics if successful, the first `Error` of `IndexedFasta::iter`
+    /// otherwise.
+    pub fn stats(&self) -> Result<AssemblyStats> {
+        let mut stats = AssemblyStats {
+            sequences: 0,
+            total_len: 0,
+            n50: 0,
+            l50: 0,
+            largest: (String::new(), 0),
+            smallest: (String::new(), 0),
+            n_bases: 0,
+            gaps: 0,
+            lengths: Vec::with_capacity(self.fai().len()),
+        };
+        for r in self.iter() {
+            let (name, view) = r?;
+            let len = view.len_bases();
+            if stats.sequences == 0 || len > stats.largest.1 {
+                stats.largest = (name.to_owned(), len);
+            }
+            if stats.sequences == 0 || len < stats.smallest.1 {
+                stats.smallest = (name.to_owned(), len);
+            }
+            for (start, end) in view.n_runs() {
+                stats.n_bases += end - start;
+                stats.gaps += 1;
+            }
+            stats.sequences += 1;
+            stats.total_len += len;
+            stats.lengths.push(len);
+        }
+        stats.lengths.sort_unstable_by(|a, b| b.cmp(a));
+        (stats.n50, stats.l50) = stats.nx(0.5);
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let data = b">a\nACNN\nNA\n>b\nNNNNNNNNNN\n>c\nA\n>d\nACGTACGTACGTACGTACGT\n".to_vec();
+        let fa = IndexedFasta::from_source(data.clone(), Fai::create_from_bytes(&data).unwrap());
+        assert_eq!(fa.fai().total_len().unwrap(), 37);
+        let stats = fa.stats().unwrap();
+        assert_eq!(stats.sequences, 4);
+        assert_eq!(stats.total_len, 37);
+        // the largest sequence contains more than half of the 37 bases
+        assert_eq!((stats.n50, stats.l50), (20, 1));
+        assert_eq!(stats.nx(0.6), (10, 2));
+        assert_eq!(stats.nx(0.9), (6, 3));
+        assert_eq!(stats.nx(0.0), (20, 1));
+        assert_eq!(stats.largest, ("d".to_owned(), 20));
+        assert_eq!(stats.smallest, ("c".to_owned(), 1));
+        assert_eq!((stats.n_bases, stats.gaps), (13, 2));
+        assert_eq!(stats.n_fraction(), 13.0 / 37.0);
+        assert!(stats
+            .to_string()
+            .starts_with("sequences\t4\ntotal_len\t37\nn50\t20\n"));
+
+        let empty = IndexedFasta::from_source(Vec::new(), Fai::create_from_bytes(b"").unwrap());
+        let stats = empty.stats().unwrap();
+        assert_eq!((stats.sequences, stats.n50, stats.l50), (0, 0, 0));
+        assert!(stats.n_fraction().is_nan());
+    }
+}
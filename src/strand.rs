@@ -0,0 +1,324 @@
+//! Strand aware access to the sequence data.
+//!
+//! A `StrandedView` wraps a `FastaView` together with the strand it should be read from. On the
+//! reverse strand the bases are lazily reverse complemented, without copying the sequence.
+//! IUPAC ambiguity codes are complemented and case is preserved.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::strand::Strand;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let v = fa.view_stranded(2, 48, 52, Strand::Reverse).expect("Cannot get .fa view");
+//! assert_eq!(v.to_string(), "CCGG");
+//! assert_eq!(fa.view(2, 20, 30).unwrap().rc().to_string(), "GGGGGTTTTT");
+//!
+//! // every window on both strands
+//! for (start, stop, fw, rev) in fa.paired_windows(2, 10, 10).expect("Invalid window") {
+//!     println!("{}-{}\t{}\t{}", start, stop, fw.to_string(), rev.to_string());
+//! }
+//! ```
+
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::{BaseCounts, FastaView, IndexedFasta, Result, Windows};
+
+/// The strand of a sequence relative to the reference.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl Strand {
+    /// Return the opposite strand.
+    pub fn flip(self) -> Strand {
+        match self {
+            Strand::Forward => Strand::Reverse,
+            Strand::Reverse => Strand::Forward,
+        }
+    }
+}
+
+/// Formats the strand as `+` or `-`.
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+/// Lookup table with the complement of every ascii character. Characters that are not a base or
+/// IUPAC ambiguity code map to themselves.
+pub(crate) static COMPLEMENT: [u8; 256] = complement_table();
+
+const fn complement_table() -> [u8; 256] {
+    let mut t = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        t[i] = i as u8;
+        i += 1;
+    }
+    let pairs: [(u8, u8); 6] = [
+        (b'A', b'T'),
+        (b'C', b'G'),
+        (b'R', b'Y'),
+        (b'K', b'M'),
+        (b'B', b'V'),
+        (b'D', b'H'),
+    ];
+    let mut p = 0;
+    while p < pairs.len() {
+        let (a, b) = pairs[p];
+        t[a as usize] = b;
+        t[b as usize] = a;
+        t[(a | 32) as usize] = b | 32;
+        t[(b | 32) as usize] = a | 32;
+        p += 1;
+    }
+    // U pairs with A, but A complements to T
+    t[b'U' as usize] = b'A';
+    t[b'u' as usize] = b'a';
+    t
+}
+
+/// Return the complement of a single base.
+#[inline]
+pub fn complement(b: u8) -> u8 {
+    COMPLEMENT[b as usize]
+}
+
+/// A `FastaView` that is read from either the forward or the reverse strand.
+pub struct StrandedView<'a> {
+    view: FastaView<'a>,
+    strand: Strand,
+}
+
+impl<'a> StrandedView<'a> {
+    /// Create a stranded view of `view`.
+    pub fn new(view: FastaView<'a>, strand: Strand) -> Self {
+        StrandedView { view, strand }
+    }
+
+    /// The strand this view is read from.
+    pub fn strand(&self) -> Strand {
+        self.strand
+    }
+
+    /// Return the same region on the other strand.
+    pub fn rc(&self) -> StrandedView<'a> {
+        StrandedView {
+            view: self.view.clone(),
+            strand: self.strand.flip(),
+        }
+    }
+
+    /// Iterator over the bases on the strand of this view. On the reverse strand bases are
+    /// returned from the end of the region and complemented.
+    pub fn bases(&self) -> StrandedBases<'a> {
+        StrandedBases {
+            iter: self.view.0.iter(),
+            reverse: self.strand == Strand::Reverse,
+        }
+    }
+
+    /// Count the occurences of A, C, G, T, N, and other on the strand of this view.
+    pub fn count_bases(&self) -> BaseCounts {
+        let mut bc = self.view.count_bases();
+        if self.strand == Strand::Reverse {
+            std::mem::swap(&mut bc.a, &mut bc.t);
+            std::mem::swap(&mut bc.c, &mut bc.g);
+        }
+        bc
+    }
+}
+
+/// Iterator over the bases of a `StrandedView`.
+pub struct StrandedBases<'a> {
+    iter: std::slice::Iter<'a, u8>,
+    reverse: bool,
+}
+
+impl<'a> Iterator for StrandedBases<'a> {
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        if self.reverse {
+            self.iter.rfind(|&&b| b & 192 == 64).map(|&b| complement(b))
+        } else {
+            self.iter.find(|&&b| b & 192 == 64).copied()
+        }
+    }
+}
+
+impl<'a> DoubleEndedIterator for StrandedBases<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<u8> {
+        if self.reverse {
+            self.iter.find(|&&b| b & 192 == 64).map(|&b| complement(b))
+        } else {
+            self.iter.rfind(|&&b| b & 192 == 64).copied()
+        }
+    }
+}
+
+/// Writes the sequence data on the strand of `Self`
+impl<'a> fmt::Display for StrandedView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.strand == Strand::Forward {
+            return self.view.fmt(f);
+        }
+
+        let mut buf = [0; 4096];
+        let mut bases = self.bases();
+        loop {
+            let mut len = 0;
+            for (t, b) in buf.iter_mut().zip(&mut bases) {
+                *t = b;
+                len += 1;
+            }
+            if len == 0 {
+                return Ok(());
+            }
+            // the complement of a base is in the ascii range as well
+            f.write_str(std::str::from_utf8(&buf[..len]).map_err(|_| fmt::Error)?)?;
+        }
+    }
+}
+
+impl<'a> Read for StrandedView<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.strand == Strand::Forward {
+            return self.view.read(buf);
+        }
+
+        let mut bases = self.bases();
+        let mut read = 0;
+        for (t, b) in buf.iter_mut().zip(&mut bases) {
+            *t = b;
+            read += 1;
+        }
+        self.view.0 = bases.iter.as_slice();
+        self.view.1 = self.view.1.saturating_sub(read);
+        Ok(read)
+    }
+}
+
+/// Iterator over windows on both strands, created with `IndexedFasta::paired_windows`.
+pub struct PairedWindows<'a> {
+    windows: Windows<'a>,
+}
+
+impl<'a> Iterator for PairedWindows<'a> {
+    type Item = (usize, usize, StrandedView<'a>, StrandedView<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, stop, view) = self.windows.next()?;
+        let rev = view.rc();
+        Some((start, stop, StrandedView::new(view, Strand::Forward), rev))
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Return the reverse complement of this view.
+    pub fn rc(&self) -> StrandedView<'a> {
+        StrandedView::new(self.clone(), Strand::Reverse)
+    }
+}
+
+impl IndexedFasta {
+    /// Use tid, start and end to get a view on the requested strand. On the reverse strand the
+    /// bases are reverse complemented.
+    ///
+    /// Returns StrandedView for the provided chromsome, start, end if successful, Error
+    /// otherwise.
+    pub fn view_stranded(
+        &self,
+        tid: usize,
+        start: usize,
+        stop: usize,
+        strand: Strand,
+    ) -> Result<StrandedView<'_>> {
+        Ok(StrandedView::new(self.view(tid, start, stop)?, strand))
+    }
+
+    /// Iterate over windows like `IndexedFasta::windows`, but yield every window on the forward
+    /// and on the reverse strand. Both views share the same data, the reverse complement is only
+    /// computed when it is read.
+    ///
+    /// Returns an iterator of `(start, stop, forward, reverse)` if successful, Error otherwise.
+    pub fn paired_windows(
+        &self,
+        tid: usize,
+        size: usize,
+        step: usize,
+    ) -> Result<PairedWindows<'_>> {
+        Ok(PairedWindows {
+            windows: self.windows(tid, size, step)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complements() {
+        assert_eq!(complement(b'A'), b'T');
+        assert_eq!(complement(b'g'), b'c');
+        assert_eq!(complement(b'R'), b'Y');
+        assert_eq!(complement(b'n'), b'n');
+        assert_eq!(complement(b'\n'), b'\n');
+    }
+
+    #[test]
+    fn reverse_complement() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let v = fa.view_stranded(2, 45, 80, Strand::Reverse).unwrap();
+        assert_eq!(v.to_string(), "AAAAACCCCCCCCCCCCCCCCCCCCCCCCCGGGGG");
+        assert_eq!(v.bases().rev().take(3).collect::<Vec<_>>(), b"GGG");
+        assert_eq!(v.rc().to_string(), fa.view(2, 45, 80).unwrap().to_string());
+        assert_eq!(v.count_bases().g, 5);
+        assert_eq!(v.count_bases().c, 25);
+        let fw = fa.view_stranded(2, 45, 80, Strand::Forward).unwrap();
+        assert_eq!(fw.count_bases().g, 25);
+        assert_eq!(FastaView::new(b"acGT\nNr").rc().to_string(), "yNACgt");
+        // longer than the buffer of Display
+        let long = b"ACGGT\n".repeat(1000);
+        let rc = FastaView::new(&long).rc();
+        assert_eq!(rc.to_string().as_bytes(), rc.bases().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn paired_windows() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let windows: Vec<_> = fa
+            .paired_windows(2, 30, 20)
+            .unwrap()
+            .map(|(start, stop, fw, rev)| (start, stop, fw.to_string(), rev.to_string()))
+            .collect();
+        assert_eq!(windows.len(), 5);
+        assert_eq!(windows[1].2, "AAAAACCCCCCCCCCCCCCCCCCCCCCCCC");
+        assert_eq!(windows[1].3, "GGGGGGGGGGGGGGGGGGGGGGGGGTTTTT");
+        assert_eq!((windows[4].0, windows[4].1), (80, 100));
+        assert!(fa.paired_windows(2, 0, 1).is_err());
+    }
+
+    #[test]
+    fn read_reverse() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut v = fa.view_tid(2).unwrap().rc();
+        let mut buf = vec![0; 30];
+        assert_eq!(v.read(&mut buf).unwrap(), 30);
+        assert_eq!(&buf[..], &[vec![b'A'; 25], vec![b'C'; 5]].concat()[..]);
+        let mut rest = Vec::new();
+        v.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest.len(), 70);
+        assert_eq!(rest[69], b'T');
+    }
+}
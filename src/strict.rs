@@ -0,0 +1,117 @@
+//! Strict validation of the bases in a view.
+//!
+//! Reading a view normally skips every byte outside the ASCII range 64..128, and doesn't check
+//! the bytes inside it, so a reference with stray characters is read without complaint.
+//! `FastaView::strict_bases` is an iterator over the bases that fails with
+//! `Error::InvalidBase` on the first byte that is neither an IUPAC nucleotide code (in upper or
+//! lower case) nor part of a line break. The position of the error is the chromosome position
+//! for views of an indexed file, and the offset in the view otherwise. `FastaView::strict_symbols`
+//! validates against another `alphabet::Alphabet`, like the amino acids of `alphabet::Protein`.
+//!
+//! # Example
+//! ```
+//! use faimm::{Error, FastaView, IndexedFasta};
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! fa.view_tid(2).unwrap().check_bases().expect("Invalid reference");
+//!
+//! let bases: Result<Vec<u8>, Error> = FastaView::new(b"ACgt\nN-A").strict_bases().collect();
+//! assert!(matches!(bases, Err(Error::InvalidBase { position: 5, base: b'-' })));
+//! ```
+
+use std::marker::PhantomData;
+use std::slice;
+
+use crate::alphabet::{Alphabet, Dna, INVALID};
+use crate::{Error, FastaView, Result};
+
+/// Iterator over the symbols of a view that fails on symbols that are not in alphabet `A`, see
+/// `FastaView::strict_bases` and `FastaView::strict_symbols`.
+pub struct StrictBases<'a, A = Dna> {
+    iter: slice::Iter<'a, u8>,
+    position: usize,
+    alphabet: PhantomData<A>,
+}
+
+impl<'a, A: Alphabet> Iterator for StrictBases<'a, A> {
+    type Item = Result<u8>;
+
+    fn next(&mut self) -> Option<Result<u8>> {
+        for &b in self.iter.by_ref() {
+            if b == b'\n' || b == b'\r' {
+                continue;
+            }
+            if A::CODES[b as usize] == INVALID {
+                // stop after the first error
+                self.iter = [].iter();
+                return Some(Err(Error::InvalidBase {
+                    position: self.position,
+                    base: b,
+                }));
+            }
+            self.position += 1;
+            return Some(Ok(b));
+        }
+        None
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Iterator over the bases in the current view that returns `Error::InvalidBase` for the
+    /// first byte that is not an IUPAC nucleotide code or a line break, and ends after it.
+    pub fn strict_bases(&self) -> StrictBases<'a> {
+        self.strict_symbols()
+    }
+
+    /// Iterator over the symbols in the current view that returns `Error::InvalidBase` for the
+    /// first byte that is not in alphabet `A` or a line break, and ends after it.
+    pub fn strict_symbols<A: Alphabet>(&self) -> StrictBases<'a, A> {
+        StrictBases {
+            iter: self.0.iter(),
+            position: self.start().unwrap_or(0),
+            alphabet: PhantomData,
+        }
+    }
+
+    /// Check that the current view only contains IUPAC nucleotide codes and line breaks.
+    ///
+    /// Returns `Error::InvalidBase` for the first invalid byte.
+    pub fn check_bases(&self) -> Result<()> {
+        self.strict_bases().try_for_each(|b| b.map(|_| ()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alphabet::Protein;
+    use crate::IndexedFasta;
+
+    #[test]
+    fn strict() {
+        let v = FastaView::new(b"ACGTUacgturyswkmbdhvnRYSWKMBDHVN\r\nA\n");
+        assert_eq!(
+            v.strict_bases().collect::<Result<Vec<u8>>>().unwrap(),
+            v.bases().copied().collect::<Vec<u8>>()
+        );
+        // bytes that are skipped or accepted by the ASCII mask
+        for (data, position, base) in [
+            (&b"AC GT"[..], 2, b' '),
+            (b"ACXT", 2, b'X'),
+            (b"\tA", 0, b'\t'),
+        ] {
+            let mut iter = FastaView::new(data).strict_bases();
+            let err = iter.find_map(|b| b.err()).unwrap();
+            assert!(
+                matches!(err, Error::InvalidBase { position: p, base: b } if p == position && b == base)
+            );
+            assert!(iter.next().is_none());
+        }
+
+        let fa = IndexedFasta::index_source(b">a\nACGTAC\nG*T\n".to_vec()).unwrap();
+        assert!(fa.view(0, 0, 6).unwrap().check_bases().is_ok());
+        let err = fa.view(0, 2, 9).unwrap().check_bases().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid base '*' at position 7");
+        let protein = fa.view(0, 2, 9).unwrap().strict_symbols::<Protein>();
+        assert_eq!(protein.collect::<Result<Vec<u8>>>().unwrap(), b"GTACG*T");
+    }
+}
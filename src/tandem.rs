@@ -0,0 +1,187 @@
+//! Perfect short tandem repeats (microsatellites).
+//!
+//! A tandem repeat is a unit of 1 up to `MAX_UNIT` bases that is repeated without mismatches,
+//! like `CACACACA`. The repeats are found in one pass over a view and reported as zero-based,
+//! half-open coordinates relative to the start of the view, so for `IndexedFasta::tandem_repeats`
+//! they are chromosome coordinates. A repeat ends after its last complete copy and its unit is
+//! written in uppercase as it occurs at the start of the repeat. Only units that are not a repeat
+//! of a shorter unit are reported, so `AAAAAA` is reported with unit `A` and not `AA`. Case is
+//! ignored, and N or other bases end a repeat.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let repeats = fa.tandem_repeats(2, 6, 10).expect("Invalid arguments");
+//! let units: Vec<_> = repeats.iter().map(|r| (r.start, r.end, &r.unit[..])).collect();
+//! assert_eq!(units[0], (0, 25, &b"A"[..]));
+//! assert_eq!(repeats[0].copies(), 25);
+//! ```
+
+use crate::oligo::{CODE, OTHER, SKIPPED};
+use crate::{Error, FastaView, IndexedFasta, Result};
+
+/// The largest repeat unit.
+pub const MAX_UNIT: usize = 6;
+
+/// A perfect tandem repeat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TandemRepeat {
+    pub start: usize,
+    pub end: usize,
+    /// The repeated unit in uppercase.
+    pub unit: Vec<u8>,
+}
+
+impl TandemRepeat {
+    /// The number of copies of the unit.
+    pub fn copies(&self) -> usize {
+        (self.end - self.start) / self.unit.len()
+    }
+}
+
+/// The state of the repeats with a single unit size.
+#[derive(Clone, Copy, Default)]
+struct Run {
+    /// The number of bases that equal the base one unit back.
+    matches: usize,
+    start: usize,
+    unit: [u8; MAX_UNIT],
+}
+
+/// Whether a unit is not a repeat of a shorter unit, which is the case when no rotation of the
+/// unit equals the unit.
+fn is_primitive(unit: &[u8]) -> bool {
+    let n = unit.len();
+    (1..n).all(|d| unit[d..] != unit[..n - d] || unit[..d] != unit[n - d..])
+}
+
+impl<'a> FastaView<'a> {
+    /// Find the perfect tandem repeats with a unit of up to `max_unit` bases and at least
+    /// `min_copies` copies in the current view, ordered by start position.
+    ///
+    /// Returns the repeats if successful, `Error::InvalidArgument` for a `max_unit` of 0 or more
+    /// than `MAX_UNIT` or a `min_copies` below 2.
+    pub fn tandem_repeats(&self, max_unit: usize, min_copies: usize) -> Result<Vec<TandemRepeat>> {
+        if max_unit == 0 || max_unit > MAX_UNIT || min_copies < 2 {
+            return Err(Error::InvalidArgument(format!(
+                "Repeat unit should be between 1 and {} bases and have at least 2 copies",
+                MAX_UNIT
+            )));
+        }
+        let mut repeats = Vec::new();
+        let mut emit = |p: usize, run: &Run| {
+            let copies = (run.matches + p) / p;
+            let unit = &run.unit[..p];
+            if run.matches > 0 && copies >= min_copies && is_primitive(unit) {
+                repeats.push(TandemRepeat {
+                    start: run.start,
+                    end: run.start + copies * p,
+                    unit: unit.iter().map(|&v| b"ACGT"[v as usize]).collect(),
+                });
+            }
+        };
+
+        let mut runs = [Run::default(); MAX_UNIT];
+        // the last MAX_UNIT base codes, the most recent first
+        let mut history = [0u8; MAX_UNIT];
+        let mut valid = 0;
+        let mut pos = 0;
+        for &b in self.0 {
+            let v = CODE[b as usize];
+            if v == SKIPPED {
+                continue;
+            }
+            if v == OTHER {
+                for (p, run) in runs.iter_mut().enumerate().take(max_unit) {
+                    emit(p + 1, run);
+                    run.matches = 0;
+                }
+                valid = 0;
+            } else {
+                for (p, run) in runs.iter_mut().enumerate().take(max_unit) {
+                    let p = p + 1;
+                    if valid >= p && history[p - 1] == v {
+                        if run.matches == 0 {
+                            run.start = pos - p;
+                            for (i, u) in run.unit[..p].iter_mut().enumerate() {
+                                *u = history[p - 1 - i];
+                            }
+                        }
+                        run.matches += 1;
+                    } else {
+                        emit(p, run);
+                        run.matches = 0;
+                    }
+                }
+                history.copy_within(..MAX_UNIT - 1, 1);
+                history[0] = v;
+                valid += 1;
+            }
+            pos += 1;
+        }
+        for (p, run) in runs.iter().enumerate().take(max_unit) {
+            emit(p + 1, run);
+        }
+        repeats.sort_by_key(|r| (r.start, r.unit.len()));
+        Ok(repeats)
+    }
+}
+
+impl IndexedFasta {
+    /// Find the perfect tandem repeats on chromosome tid, see `FastaView::tandem_repeats`.
+    ///
+    /// Returns the repeats in chromosome coordinates if successful, Error otherwise.
+    pub fn tandem_repeats(
+        &self,
+        tid: usize,
+        max_unit: usize,
+        min_copies: usize,
+    ) -> Result<Vec<TandemRepeat>> {
+        self.view_tid(tid)?.tandem_repeats(max_unit, min_copies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(seq: &[u8], max_unit: usize, min_copies: usize) -> Vec<(usize, usize, String)> {
+        FastaView::new(seq)
+            .tandem_repeats(max_unit, min_copies)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.start, r.end, String::from_utf8(r.unit).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn repeats() {
+        assert_eq!(
+            found(b"GCACAC\nacaTTTTNTTTCAGCAGCAG", 6, 3),
+            [
+                (1, 9, "CA".to_owned()),
+                (9, 13, "T".to_owned()),
+                (14, 17, "T".to_owned()),
+                (17, 26, "CAG".to_owned()),
+            ]
+        );
+        // a partial last copy is not included, AA is reported as A
+        assert_eq!(
+            found(b"ATATAGAAAAAA", 2, 2),
+            [(0, 4, "AT".to_owned()), (6, 12, "A".to_owned())]
+        );
+        assert!(found(b"NNNNNN", 1, 2).is_empty());
+        assert!(found(b"ACGTACGT", 3, 2).is_empty());
+        assert!(FastaView::new(b"A").tandem_repeats(7, 2).is_err());
+        assert!(FastaView::new(b"A").tandem_repeats(1, 1).is_err());
+    }
+
+    #[test]
+    fn chromosome() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let repeats = fa.tandem_repeats(2, 1, 20).unwrap();
+        let starts: Vec<_> = repeats.iter().map(|r| (r.start, r.copies())).collect();
+        assert_eq!(starts, [(0, 25), (25, 25), (50, 25), (75, 25)]);
+    }
+}
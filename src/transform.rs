@@ -0,0 +1,198 @@
+//! Lazy base transformations on views.
+//!
+//! `FastaView::map_bases` wraps a view in a `MappedView` that applies a `Transform` to every base
+//! when it is read. Transformations can be chained and are combined into a single lookup table,
+//! so a stack of transformations costs the same as one. The same transformed bases are returned
+//! by `MappedView::bases`, `Display` (`to_string`) and `Read`, and written by
+//! `MappedView::write_to` and `FastaWriter::write_mapped`. `FastaView::uppercased` and
+//! `FastaView::masked_to_n` are shorthands for the common normalizations of soft-masked bases,
+//! for instance for tools that expect uppercase input.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::transform::Transform;
+//! use std::io::Read;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let v = fa.view(2, 70, 80).expect("Cannot get view");
+//! let rna = v.map_bases(Transform::Complement).map_bases(Transform::DnaToRna);
+//! assert_eq!(rna.to_string(), "CCCCCAAAAA");
+//! let mut upper = String::new();
+//! fa.view(0, 0, 5).unwrap().uppercased().read_to_string(&mut upper).unwrap();
+//! assert_eq!(upper, "AAAAA");
+//! ```
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::strand::complement;
+use crate::FastaView;
+
+/// A transformation of single bases.
+#[derive(Debug, Clone, Copy)]
+pub enum Transform {
+    /// Convert soft-masked (lowercase) bases to uppercase.
+    Uppercase,
+    /// Complement the bases, keeping the order. Use `FastaView::rc` for the reverse complement.
+    Complement,
+    /// Replace T by U, keeping case.
+    DnaToRna,
+    /// Replace soft-masked (lowercase) bases by N (hard-masking).
+    MaskToN,
+    /// Apply a custom function to every base.
+    Custom(fn(u8) -> u8),
+}
+
+impl Transform {
+    /// Apply the transformation to base `b`.
+    pub fn apply(self, b: u8) -> u8 {
+        match self {
+            Transform::Uppercase => b.to_ascii_uppercase(),
+            Transform::Complement => complement(b),
+            Transform::DnaToRna => match b {
+                b'T' => b'U',
+                b't' => b'u',
+                _ => b,
+            },
+            Transform::MaskToN if b.is_ascii_lowercase() => b'N',
+            Transform::MaskToN => b,
+            Transform::Custom(f) => f(b),
+        }
+    }
+}
+
+/// A `FastaView` with a stack of base transformations.
+pub struct MappedView<'a> {
+    view: FastaView<'a>,
+    table: [u8; 256],
+}
+
+impl<'a> MappedView<'a> {
+    /// Add `transform` after the current transformations.
+    pub fn map_bases(mut self, transform: Transform) -> MappedView<'a> {
+        for t in self.table.iter_mut() {
+            *t = transform.apply(*t);
+        }
+        self
+    }
+
+    /// Iterator over the transformed bases.
+    pub fn bases(&self) -> impl Iterator<Item = u8> + '_ {
+        self.view.bases().map(move |&b| self.table[b as usize])
+    }
+
+    /// Write the transformed bases to `w` without line breaks, see `FastaView::write_to`.
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        self.try_for_each_chunk(|chunk| w.write_all(chunk))
+    }
+
+    /// Call `f` with the transformed bases, a part of a line at a time.
+    fn try_for_each_chunk<E, F>(&self, mut f: F) -> std::result::Result<(), E>
+    where
+        F: FnMut(&[u8]) -> std::result::Result<(), E>,
+    {
+        let mut buf = [0; 4096];
+        for line in self.view.line_chunks() {
+            for chunk in line.chunks(buf.len()) {
+                let mapped = &mut buf[..chunk.len()];
+                for (m, &b) in mapped.iter_mut().zip(chunk) {
+                    *m = self.table[b as usize];
+                }
+                f(mapped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the transformed bases. Fails for a `Transform::Custom` that returns bytes outside
+/// of the ascii range.
+impl<'a> fmt::Display for MappedView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.try_for_each_chunk(|chunk| {
+            f.write_str(std::str::from_utf8(chunk).map_err(|_| fmt::Error)?)
+        })
+    }
+}
+
+impl<'a> Read for MappedView<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.view.read(buf)?;
+        for b in &mut buf[..read] {
+            *b = self.table[*b as usize];
+        }
+        Ok(read)
+    }
+}
+
+impl<'a> FastaView<'a> {
+    /// Apply `transform` to the bases of this view when they are read.
+    pub fn map_bases(&self, transform: Transform) -> MappedView<'a> {
+        let mut table = [0u8; 256];
+        for (i, t) in table.iter_mut().enumerate() {
+            *t = i as u8;
+        }
+        MappedView {
+            view: self.clone(),
+            table,
+        }
+        .map_bases(transform)
+    }
+
+    /// The bases of this view in uppercase, see `Transform::Uppercase`.
+    pub fn uppercased(&self) -> MappedView<'a> {
+        self.map_bases(Transform::Uppercase)
+    }
+
+    /// The bases of this view with soft-masked bases replaced by N, see `Transform::MaskToN`.
+    pub fn masked_to_n(&self) -> MappedView<'a> {
+        self.map_bases(Transform::MaskToN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms() {
+        let v = FastaView::new(b"ACgt\nNnT");
+        assert_eq!(v.map_bases(Transform::Uppercase).to_string(), "ACGTNNT");
+        assert_eq!(v.map_bases(Transform::Complement).to_string(), "TGcaNnA");
+        assert_eq!(v.map_bases(Transform::DnaToRna).to_string(), "ACguNnU");
+        assert_eq!(v.map_bases(Transform::MaskToN).to_string(), "ACNNNNT");
+        let x = v.map_bases(Transform::Custom(|b| if b == b'A' { b'X' } else { b }));
+        assert_eq!(x.to_string(), "XCgtNnT");
+        // order matters: masking after uppercasing masks nothing
+        let chained = v
+            .map_bases(Transform::Uppercase)
+            .map_bases(Transform::MaskToN);
+        assert_eq!(chained.to_string(), "ACGTNNT");
+    }
+
+    #[test]
+    fn read() {
+        let mut v = FastaView::new(b"acgt\nac\ngt").map_bases(Transform::Uppercase);
+        let mut buf = [0; 5];
+        assert_eq!(v.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"ACGTA");
+        let mut rest = String::new();
+        v.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "CGT");
+
+        let v = FastaView::new(
+            b"acGT
+nnAC
+",
+        );
+        let mut upper = Vec::new();
+        v.uppercased().read_to_end(&mut upper).unwrap();
+        assert_eq!(upper, b"ACGTNNAC");
+        let masked: Vec<u8> = v.masked_to_n().bases().collect();
+        assert_eq!(masked, b"NNGTNNAC");
+        let mut out = Vec::new();
+        v.masked_to_n().write_to(&mut out).unwrap();
+        assert_eq!(out, masked);
+        assert_eq!(v.uppercased().to_string(), "ACGTNNAC");
+    }
+}
@@ -0,0 +1,378 @@
+//! Codon iteration and translation to protein.
+//!
+//! Codons are read from the bases of a view, so they span line breaks. Translation uses the
+//! standard genetic code, or one of the NCBI translation tables in `GeneticCode` for organelle
+//! and microbial genomes. Stop codons translate to `*` and codons with other bases than A, C, G
+//! or T (ignoring case) translate to `X`. An incomplete codon at the end of a view is ignored.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let view = fa.view(2, 20, 32).expect("Cannot get view");
+//! assert_eq!(view.translate(0).expect("Invalid frame"), "KNPP");
+//! assert_eq!(view.codons(1).expect("Invalid frame").count(), 3);
+//!
+//! use faimm::translate::GeneticCode;
+//! let mito = GeneticCode::from_ncbi_id(2).expect("Unknown table");
+//! assert_eq!(mito.translate_codon(b"AGA"), b'*');
+//! ```
+
+use crate::{Error, FastaView, Result};
+
+/// The NCBI genetic codes (translation tables).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GeneticCode {
+    /// Table 1, the standard code.
+    #[default]
+    Standard,
+    /// Table 2, the vertebrate mitochondrial code.
+    VertebrateMitochondrial,
+    /// Table 3, the yeast mitochondrial code.
+    YeastMitochondrial,
+    /// Table 4, the mold, protozoan and coelenterate mitochondrial and mycoplasma/spiroplasma
+    /// code.
+    MoldMitochondrial,
+    /// Table 5, the invertebrate mitochondrial code.
+    InvertebrateMitochondrial,
+    /// Table 6, the ciliate, dasycladacean and hexamita nuclear code.
+    CiliateNuclear,
+    /// Table 9, the echinoderm and flatworm mitochondrial code.
+    EchinodermMitochondrial,
+    /// Table 10, the euplotid nuclear code.
+    EuplotidNuclear,
+    /// Table 11, the bacterial, archaeal and plant plastid code.
+    Bacterial,
+    /// Table 12, the alternative yeast nuclear code.
+    AlternativeYeastNuclear,
+    /// Table 13, the ascidian mitochondrial code.
+    AscidianMitochondrial,
+    /// Table 14, the alternative flatworm mitochondrial code.
+    AlternativeFlatwormMitochondrial,
+    /// Table 16, the chlorophycean mitochondrial code.
+    ChlorophyceanMitochondrial,
+    /// Table 21, the trematode mitochondrial code.
+    TrematodeMitochondrial,
+    /// Table 22, the Scenedesmus obliquus mitochondrial code.
+    ScenedesmusMitochondrial,
+    /// Table 23, the Thraustochytrium mitochondrial code.
+    ThraustochytriumMitochondrial,
+    /// Table 24, the Rhabdopleuridae mitochondrial code.
+    RhabdopleuridaeMitochondrial,
+    /// Table 25, the candidate division SR1 and gracilibacteria code.
+    Gracilibacteria,
+}
+
+impl GeneticCode {
+    /// All supported genetic codes.
+    pub const ALL: [GeneticCode; 18] = [
+        GeneticCode::Standard,
+        GeneticCode::VertebrateMitochondrial,
+        GeneticCode::YeastMitochondrial,
+        GeneticCode::MoldMitochondrial,
+        GeneticCode::InvertebrateMitochondrial,
+        GeneticCode::CiliateNuclear,
+        GeneticCode::EchinodermMitochondrial,
+        GeneticCode::EuplotidNuclear,
+        GeneticCode::Bacterial,
+        GeneticCode::AlternativeYeastNuclear,
+        GeneticCode::AscidianMitochondrial,
+        GeneticCode::AlternativeFlatwormMitochondrial,
+        GeneticCode::ChlorophyceanMitochondrial,
+        GeneticCode::TrematodeMitochondrial,
+        GeneticCode::ScenedesmusMitochondrial,
+        GeneticCode::ThraustochytriumMitochondrial,
+        GeneticCode::RhabdopleuridaeMitochondrial,
+        GeneticCode::Gracilibacteria,
+    ];
+
+    /// The NCBI translation table number.
+    pub fn ncbi_id(self) -> u8 {
+        match self {
+            GeneticCode::Standard => 1,
+            GeneticCode::VertebrateMitochondrial => 2,
+            GeneticCode::YeastMitochondrial => 3,
+            GeneticCode::MoldMitochondrial => 4,
+            GeneticCode::InvertebrateMitochondrial => 5,
+            GeneticCode::CiliateNuclear => 6,
+            GeneticCode::EchinodermMitochondrial => 9,
+            GeneticCode::EuplotidNuclear => 10,
+            GeneticCode::Bacterial => 11,
+            GeneticCode::AlternativeYeastNuclear => 12,
+            GeneticCode::AscidianMitochondrial => 13,
+            GeneticCode::AlternativeFlatwormMitochondrial => 14,
+            GeneticCode::ChlorophyceanMitochondrial => 16,
+            GeneticCode::TrematodeMitochondrial => 21,
+            GeneticCode::ScenedesmusMitochondrial => 22,
+            GeneticCode::ThraustochytriumMitochondrial => 23,
+            GeneticCode::RhabdopleuridaeMitochondrial => 24,
+            GeneticCode::Gracilibacteria => 25,
+        }
+    }
+
+    /// The genetic code with NCBI translation table number `id`, `None` if it is not supported.
+    pub fn from_ncbi_id(id: u8) -> Option<GeneticCode> {
+        GeneticCode::ALL.into_iter().find(|c| c.ncbi_id() == id)
+    }
+
+    /// The amino acids of every codon, indexed by codon with `T=0, C=1, A=2, G=3`, in the same
+    /// order as the NCBI tables.
+    fn amino_acids(self) -> &'static [u8; 64] {
+        match self {
+            GeneticCode::Standard | GeneticCode::Bacterial => {
+                b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::VertebrateMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG"
+            }
+            GeneticCode::YeastMitochondrial => {
+                b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::MoldMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::InvertebrateMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::CiliateNuclear => {
+                b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::EchinodermMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::EuplotidNuclear => {
+                b"FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::AlternativeYeastNuclear => {
+                b"FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::AscidianMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::AlternativeFlatwormMitochondrial => {
+                b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::ChlorophyceanMitochondrial => {
+                b"FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::TrematodeMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::ScenedesmusMitochondrial => {
+                b"FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::ThraustochytriumMitochondrial => {
+                b"FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::RhabdopleuridaeMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG"
+            }
+            GeneticCode::Gracilibacteria => {
+                b"FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+        }
+    }
+
+    /// The start codons, marked with `M` in the same order as `GeneticCode::amino_acids`, like
+    /// the `Starts` line of the NCBI tables.
+    fn starts(self) -> &'static [u8; 64] {
+        match self {
+            GeneticCode::Standard => {
+                b"---M---------------M---------------M----------------------------"
+            }
+            GeneticCode::VertebrateMitochondrial => {
+                b"--------------------------------MMMM---------------M------------"
+            }
+            GeneticCode::YeastMitochondrial => {
+                b"----------------------------------MM---------------M------------"
+            }
+            GeneticCode::MoldMitochondrial => {
+                b"--MM---------------M------------MMMM---------------M------------"
+            }
+            GeneticCode::InvertebrateMitochondrial => {
+                b"---M----------------------------MMMM---------------M------------"
+            }
+            GeneticCode::EchinodermMitochondrial | GeneticCode::TrematodeMitochondrial => {
+                b"-----------------------------------M---------------M------------"
+            }
+            GeneticCode::Bacterial => {
+                b"---M---------------M------------MMMM---------------M------------"
+            }
+            GeneticCode::AlternativeYeastNuclear => {
+                b"-------------------M---------------M----------------------------"
+            }
+            GeneticCode::AscidianMitochondrial => {
+                b"---M------------------------------MM---------------M------------"
+            }
+            GeneticCode::ThraustochytriumMitochondrial => {
+                b"--------------------------------M--M---------------M------------"
+            }
+            GeneticCode::RhabdopleuridaeMitochondrial => {
+                b"---M---------------M---------------M---------------M------------"
+            }
+            GeneticCode::Gracilibacteria => {
+                b"---M-------------------------------M---------------M------------"
+            }
+            GeneticCode::CiliateNuclear
+            | GeneticCode::EuplotidNuclear
+            | GeneticCode::AlternativeFlatwormMitochondrial
+            | GeneticCode::ChlorophyceanMitochondrial
+            | GeneticCode::ScenedesmusMitochondrial => {
+                b"-----------------------------------M----------------------------"
+            }
+        }
+    }
+
+    /// Returns `true` if `codon` is a start codon of this genetic code, like `ATG` and the
+    /// alternative starts `GTG` and `TTG` of the bacterial code.
+    pub fn is_start(self, codon: &[u8; 3]) -> bool {
+        codon_index(codon).is_some_and(|i| self.starts()[i] == b'M')
+    }
+
+    /// Returns `true` if `codon` is a stop codon of this genetic code.
+    pub fn is_stop(self, codon: &[u8; 3]) -> bool {
+        codon_index(codon).is_some_and(|i| self.amino_acids()[i] == b'*')
+    }
+
+    /// Translate a codon with this genetic code. Codons with other bases than A, C, G or T and
+    /// slices that are not 3 bases long translate to `X`.
+    pub fn translate_codon(self, codon: &[u8]) -> u8 {
+        codon
+            .try_into()
+            .ok()
+            .and_then(codon_index)
+            .map_or(b'X', |i| self.amino_acids()[i])
+    }
+}
+
+/// The index of `codon` in the tables of `GeneticCode`, `None` for other bases than A, C, G or T.
+fn codon_index(codon: &[u8; 3]) -> Option<usize> {
+    codon.iter().try_fold(0, |index, &b| {
+        let v = match b.to_ascii_uppercase() {
+            b'T' | b'U' => 0,
+            b'C' => 1,
+            b'A' => 2,
+            b'G' => 3,
+            _ => return None,
+        };
+        Some(index * 4 + v)
+    })
+}
+
+/// Translate a codon with the standard genetic code. Codons with other bases than A, C, G or T
+/// and slices that are not 3 bases long translate to `X`.
+pub fn translate_codon(codon: &[u8]) -> u8 {
+    GeneticCode::Standard.translate_codon(codon)
+}
+
+/// Iterator over the codons of a `FastaView`, created with `FastaView::codons`.
+pub struct Codons<'a> {
+    iter: std::slice::Iter<'a, u8>,
+}
+
+impl<'a> Iterator for Codons<'a> {
+    type Item = [u8; 3];
+
+    #[inline]
+    fn next(&mut self) -> Option<[u8; 3]> {
+        let mut codon = [0; 3];
+        for b in codon.iter_mut() {
+            *b = *self.iter.find(|&&b| b & 192 == 64)?;
+        }
+        Some(codon)
+    }
+}
+
+/// Check that `frame` is 0, 1 or 2.
+fn check_frame(frame: usize) -> Result<()> {
+    if frame > 2 {
+        return Err(Error::InvalidArgument(format!(
+            "Reading frame should be 0, 1 or 2, not {}",
+            frame
+        )));
+    }
+    Ok(())
+}
+
+impl<'a> FastaView<'a> {
+    /// Iterator over the complete codons in the current view, starting `frame` (0, 1 or 2)
+    /// bases from the start of the view.
+    ///
+    /// Returns the iterator, or `Error::InvalidArgument` for an invalid frame.
+    pub fn codons(&self, frame: usize) -> Result<Codons<'a>> {
+        check_frame(frame)?;
+        let mut iter = self.0.iter();
+        for _ in 0..frame {
+            iter.find(|&&b| b & 192 == 64);
+        }
+        Ok(Codons { iter })
+    }
+
+    /// Translate the current view to protein with the standard genetic code, starting `frame`
+    /// (0, 1 or 2) bases from the start of the view.
+    ///
+    /// Returns the amino acid sequence, or `Error::InvalidArgument` for an invalid frame.
+    pub fn translate(&self, frame: usize) -> Result<String> {
+        self.translate_with(frame, GeneticCode::Standard)
+    }
+
+    /// Translate the current view to protein with genetic code `code`, starting `frame` (0, 1
+    /// or 2) bases from the start of the view.
+    ///
+    /// Returns the amino acid sequence, or `Error::InvalidArgument` for an invalid frame.
+    pub fn translate_with(&self, frame: usize, code: GeneticCode) -> Result<String> {
+        let protein = self
+            .codons(frame)?
+            .map(|c| code.translate_codon(&c))
+            .collect();
+        Ok(String::from_utf8(protein).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codons() {
+        assert_eq!(translate_codon(b"ATG"), b'M');
+        assert_eq!(translate_codon(b"taa"), b'*');
+        assert_eq!(translate_codon(b"GGN"), b'X');
+        assert_eq!(translate_codon(b"UGG"), b'W');
+        assert_eq!(translate_codon(b"ATGA"), b'X');
+        assert_eq!(translate_codon(b"AT"), b'X');
+        assert_eq!(GeneticCode::Bacterial.translate_codon(b""), b'X');
+
+        let v = FastaView::new(b"AT\ngGC\ntTAA");
+        let codons: Vec<_> = v.codons(0).unwrap().collect();
+        assert_eq!(codons, vec![*b"ATg", *b"GCt", *b"TAA"]);
+        assert_eq!(v.codons(2).unwrap().count(), 2);
+        assert!(v.codons(3).is_err());
+    }
+
+    #[test]
+    fn translate() {
+        let v = FastaView::new(b"AT\ngGC\ntTAA");
+        assert_eq!(v.translate(0).unwrap(), "MA*");
+        assert_eq!(v.translate(1).unwrap(), "WL");
+        assert_eq!(FastaView::new(b"AN\nN").translate(0).unwrap(), "X");
+        assert_eq!(FastaView::new(b"AT").translate(0).unwrap(), "");
+    }
+
+    #[test]
+    fn genetic_codes() {
+        for code in GeneticCode::ALL {
+            assert_eq!(GeneticCode::from_ncbi_id(code.ncbi_id()), Some(code));
+        }
+        assert_eq!(GeneticCode::from_ncbi_id(7), None);
+        assert_eq!(GeneticCode::default(), GeneticCode::Standard);
+
+        let v = FastaView::new(b"TGAATAAGA");
+        assert_eq!(v.translate(0).unwrap(), "*IR");
+        let mito = GeneticCode::VertebrateMitochondrial;
+        assert_eq!(v.translate_with(0, mito).unwrap(), "WM*");
+        let yeast = GeneticCode::YeastMitochondrial;
+        assert_eq!(yeast.translate_codon(b"CTG"), b'T');
+        assert_eq!(GeneticCode::CiliateNuclear.translate_codon(b"TAA"), b'Q');
+    }
+}
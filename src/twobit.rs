@@ -0,0 +1,372 @@
+//! Random access to UCSC `.2bit` files.
+//!
+//! A `.2bit` file stores every base in two bits, with separate lists of the blocks of N and of
+//! soft-masked (lowercase) bases, and an index of the sequences in the header. It doesn't need a
+//! `.fai`. A `TwoBit` memory maps the file and decodes only the requested region.
+//!
+//! Like for bgzip compressed files, the bases have to be decoded, so views are created in a
+//! caller supplied buffer that can be reused between queries. The views are the same
+//! `FastaView`s as for an `IndexedFasta`, with N for the N blocks and lowercase bases for the
+//! mask blocks, so the analyses of this crate work on both formats. Coordinates are zero-based
+//! and half-open.
+//!
+//! # Example
+//! ```
+//! use faimm::twobit::TwoBit;
+//! let tb = TwoBit::from_file("test/genome.2bit").expect("Error opening 2bit");
+//! let tid = tb.tid("ACGT-25").expect("Cannot find chr in index");
+//! let mut buf = Vec::new();
+//! let v = tb.view(tid, 20, 30, &mut buf).expect("Cannot get 2bit view");
+//! assert_eq!(v.to_string(), "AAAAACCCCC");
+//! ```
+
+use std::fs::File;
+use std::path::Path;
+
+use indexmap::IndexSet;
+use memmap2::{Mmap, MmapOptions};
+
+use crate::limits::Limits;
+use crate::{Error, FastaView, Layout, Result};
+
+/// The signature at the start of a `.2bit` file.
+const SIGNATURE: u32 = 0x1A41_2743;
+
+/// The location of a sequence in a `.2bit` file, with its N and mask blocks as `(start, end)`.
+#[derive(Debug, Clone)]
+struct TwoBitRecord {
+    len: usize,
+    dna_offset: usize,
+    n_blocks: Vec<(usize, usize)>,
+    mask_blocks: Vec<(usize, usize)>,
+}
+
+/// A memory mapped `.2bit` file.
+pub struct TwoBit {
+    mmap: Mmap,
+    names: IndexSet<String>,
+    records: Vec<TwoBitRecord>,
+    limits: Limits,
+}
+
+/// The error for a malformed `.2bit` file.
+fn malformed(message: &str) -> Error {
+    Error::Malformed {
+        format: "2bit",
+        line: None,
+        message: message.to_owned(),
+    }
+}
+
+/// Reads the integers of a `.2bit` file in the byte order of its signature.
+struct Reader<'a> {
+    data: &'a [u8],
+    swap: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, pos: usize, len: usize) -> Result<&'a [u8]> {
+        pos.checked_add(len)
+            .and_then(|end| self.data.get(pos..end))
+            .ok_or_else(|| malformed("Truncated file"))
+    }
+
+    fn u32(&self, pos: usize) -> Result<usize> {
+        let v = u32::from_le_bytes(self.bytes(pos, 4)?.try_into().unwrap());
+        Ok(if self.swap { v.swap_bytes() } else { v } as usize)
+    }
+
+    fn u64(&self, pos: usize) -> Result<usize> {
+        let v = u64::from_le_bytes(self.bytes(pos, 8)?.try_into().unwrap());
+        Ok(if self.swap { v.swap_bytes() } else { v } as usize)
+    }
+
+    /// Read `count` blocks stored as a list of starts followed by a list of sizes.
+    fn blocks(&self, pos: usize, count: usize, len: usize) -> Result<Vec<(usize, usize)>> {
+        let mut blocks = (0..count)
+            .map(|i| {
+                let start = self.u32(pos + 4 * i)?;
+                let size = self.u32(pos + 4 * (count + i))?;
+                Ok((start.min(len), start.saturating_add(size).min(len)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        blocks.sort_unstable();
+        Ok(blocks)
+    }
+
+    fn record(&self, offset: usize) -> Result<TwoBitRecord> {
+        let len = self.u32(offset)?;
+        let n_count = self.u32(offset + 4)?;
+        let n_blocks = self.blocks(offset + 8, n_count, len)?;
+        let mask_pos = offset + 8 + 8 * n_count;
+        let mask_count = self.u32(mask_pos)?;
+        let mask_blocks = self.blocks(mask_pos + 4, mask_count, len)?;
+        // skip the reserved field
+        let dna_offset = mask_pos + 4 + 8 * mask_count + 4;
+        self.bytes(dna_offset, len.div_ceil(4))?;
+        Ok(TwoBitRecord {
+            len,
+            dna_offset,
+            n_blocks,
+            mask_blocks,
+        })
+    }
+}
+
+/// Parse the header and sequence index of a `.2bit` file.
+fn read_index(data: &[u8]) -> Result<(IndexSet<String>, Vec<TwoBitRecord>)> {
+    let mut r = Reader { data, swap: false };
+    if r.u32(0)? != SIGNATURE as usize {
+        r.swap = true;
+        if r.u32(0)? != SIGNATURE as usize {
+            return Err(malformed("Not a 2bit file"));
+        }
+    }
+    let long_offsets = match r.u32(4)? {
+        0 => false,
+        1 => true,
+        _ => return Err(malformed("Unsupported 2bit version")),
+    };
+    let count = r.u32(8)?;
+
+    let mut names = IndexSet::with_capacity(count);
+    let mut records = Vec::with_capacity(count);
+    let mut pos = 16;
+    for _ in 0..count {
+        let name_len = r.bytes(pos, 1)?[0] as usize;
+        let name = std::str::from_utf8(r.bytes(pos + 1, name_len)?)
+            .map_err(|_| malformed("Invalid sequence name"))?;
+        pos += 1 + name_len;
+        let offset = if long_offsets {
+            pos += 8;
+            r.u64(pos - 8)?
+        } else {
+            pos += 4;
+            r.u32(pos - 4)?
+        };
+        if !names.insert(name.to_owned()) {
+            return Err(malformed("Duplicate sequence name"));
+        }
+        records.push(r.record(offset)?);
+    }
+    Ok((names, records))
+}
+
+/// Apply `f` to the bases of `buf` (starting at `start`) that are covered by `blocks`.
+fn apply_blocks(buf: &mut [u8], start: usize, blocks: &[(usize, usize)], f: fn(&mut u8)) {
+    let stop = start + buf.len();
+    let first = blocks.partition_point(|b| b.1 <= start);
+    for &(block_start, block_end) in blocks[first..].iter().take_while(|b| b.0 < stop) {
+        let from = block_start.max(start) - start;
+        let to = block_end.min(stop) - start;
+        buf[from..to].iter_mut().for_each(f);
+    }
+}
+
+impl TwoBit {
+    /// Open the `.2bit` file at path `P`. Both byte orders and the 64-bit offsets of version 1
+    /// files are supported.
+    ///
+    /// Returns the `TwoBit` if successful, `Error::Malformed` for a malformed
+    /// file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file) }.map_err(Error::Mmap)?;
+        let (names, records) = read_index(&mmap)?;
+        Ok(TwoBit {
+            mmap,
+            names,
+            records,
+            limits: Limits::new(),
+        })
+    }
+
+    /// Limit the size of queries on this file (see `limits::Limits`).
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Return the index of the sequence by name.
+    pub fn tid(&self, name: &str) -> Option<usize> {
+        self.names.get_index_of(name)
+    }
+
+    /// Return the name of the sequence at index tid.
+    pub fn name(&self, tid: usize) -> Result<&String> {
+        self.names.get_index(tid).ok_or(Error::UnknownTid(tid))
+    }
+
+    /// Return the names of the sequences in the order of the file.
+    pub fn names(&self) -> Vec<&str> {
+        self.names.iter().map(|s| s.as_str()).collect()
+    }
+
+    /// Return the size in bases of the sequence at index tid.
+    pub fn size(&self, tid: usize) -> Result<usize> {
+        Ok(self.record(tid)?.len)
+    }
+
+    fn record(&self, tid: usize) -> Result<&TwoBitRecord> {
+        self.records.get(tid).ok_or(Error::UnknownTid(tid))
+    }
+
+    /// Use tid, start and end to decode a region into `buf`. The previous contents of `buf` are
+    /// discarded.
+    ///
+    /// Returns a FastaView on `buf` for the provided chromosome, start, end if successful,
+    /// Error otherwise.
+    pub fn view<'b>(
+        &self,
+        tid: usize,
+        start: usize,
+        stop: usize,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<FastaView<'b>> {
+        let record = self.record(tid)?;
+        if start > stop {
+            return Err(Error::InvalidInterval { start, stop });
+        }
+        if stop > record.len {
+            return Err(Error::OutOfRange {
+                tid,
+                start,
+                stop,
+                len: record.len,
+            });
+        }
+        self.limits.check_view(stop - start)?;
+
+        buf.clear();
+        let dna = &self.mmap[record.dna_offset..];
+        buf.extend((start..stop).map(|pos| {
+            let code = dna[pos / 4] >> (6 - 2 * (pos % 4)) & 3;
+            b"TCAG"[code as usize]
+        }));
+        apply_blocks(buf, start, &record.n_blocks, |b| *b = b'N');
+        apply_blocks(buf, start, &record.mask_blocks, |b| {
+            b.make_ascii_lowercase()
+        });
+        Ok(FastaView::with_layout(
+            &buf[..],
+            buf.len(),
+            Layout::unwrapped(start),
+        ))
+    }
+
+    /// Use tid to decode an entire sequence into `buf`. The previous contents of `buf` are
+    /// discarded.
+    ///
+    /// Returns a FastaView on `buf` for the provided chromosome if successful, Error otherwise.
+    pub fn view_tid<'b>(&self, tid: usize, buf: &'b mut Vec<u8>) -> Result<FastaView<'b>> {
+        self.view(tid, 0, self.size(tid)?, buf)
+    }
+
+    /// Use the sequence name, start and end to decode a region into `buf`.
+    ///
+    /// Returns a FastaView on `buf` if successful, `Error::UnknownName` if the name is not in
+    /// the file, Error otherwise.
+    pub fn view_by_name<'b>(
+        &self,
+        name: &str,
+        start: usize,
+        stop: usize,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<FastaView<'b>> {
+        let tid = self
+            .tid(name)
+            .ok_or_else(|| Error::UnknownName(name.to_owned()))?;
+        self.view(tid, start, stop, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexedFasta;
+
+    /// Encode a single sequence as a little endian version 0 `.2bit` file.
+    fn encode(name: &str, seq: &[u8], n: &[(u32, u32)], mask: &[(u32, u32)]) -> Vec<u8> {
+        let u32s = |v: &mut Vec<u8>, values: &[u32]| {
+            values.iter().for_each(|x| v.extend(x.to_le_bytes()));
+        };
+        let mut data = Vec::new();
+        u32s(&mut data, &[SIGNATURE, 0, 1, 0]);
+        data.push(name.len() as u8);
+        data.extend(name.as_bytes());
+        let offset = data.len() as u32 + 4;
+        u32s(&mut data, &[offset]);
+        u32s(&mut data, &[seq.len() as u32, n.len() as u32]);
+        let starts = |blocks: &[(u32, u32)]| blocks.iter().map(|b| b.0).collect::<Vec<_>>();
+        let sizes = |blocks: &[(u32, u32)]| blocks.iter().map(|b| b.1).collect::<Vec<_>>();
+        u32s(&mut data, &starts(n));
+        u32s(&mut data, &sizes(n));
+        u32s(&mut data, &[mask.len() as u32]);
+        u32s(&mut data, &starts(mask));
+        u32s(&mut data, &sizes(mask));
+        u32s(&mut data, &[0]);
+        for chunk in seq.chunks(4) {
+            let byte = (0..4).fold(0u8, |byte, i| {
+                let code = chunk.get(i).map_or(0, |&b| match b.to_ascii_uppercase() {
+                    b'C' => 1,
+                    b'A' => 2,
+                    b'G' => 3,
+                    _ => 0,
+                });
+                byte << 2 | code
+            });
+            data.push(byte);
+        }
+        data
+    }
+
+    #[test]
+    fn blocks() {
+        let dir = std::env::temp_dir().join(format!("faimm-twobit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blocks.2bit");
+        let data = encode("chr1", b"ACGTACGTAC", &[(2, 3)], &[(4, 4), (9, 1)]);
+        std::fs::write(&path, data).unwrap();
+
+        let tb = TwoBit::from_file(&path).unwrap();
+        assert_eq!(tb.names(), vec!["chr1"]);
+        let mut buf = Vec::new();
+        assert_eq!(tb.view_tid(0, &mut buf).unwrap().to_string(), "ACNNncgtAc");
+        assert_eq!(tb.view(0, 3, 6, &mut buf).unwrap().to_string(), "Nnc");
+        assert_eq!(tb.view(0, 3, 6, &mut buf).unwrap().at(2), Some(b'c'));
+        assert_eq!(tb.view(0, 5, 5, &mut buf).unwrap().to_string(), "");
+        assert!(matches!(
+            tb.view(0, 5, 11, &mut buf),
+            Err(Error::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            tb.view_by_name("chr2", 0, 1, &mut buf),
+            Err(Error::UnknownName(_))
+        ));
+
+        std::fs::write(&path, b"not a 2bit file").unwrap();
+        assert!(matches!(
+            TwoBit::from_file(&path),
+            Err(Error::Malformed { format: "2bit", .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn same_as_fasta() {
+        let tb = TwoBit::from_file("test/genome.2bit").unwrap();
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        assert_eq!(tb.names(), fa.fai().names());
+        let mut buf = Vec::new();
+        for tid in 0..3 {
+            let len = tb.size(tid).unwrap();
+            assert_eq!(len, fa.fai().size(tid).unwrap());
+            for (start, stop) in [(0, len), (1, len - 1), (len / 2, len)] {
+                assert_eq!(
+                    tb.view(tid, start, stop, &mut buf).unwrap().to_string(),
+                    fa.view(tid, start, stop).unwrap().to_string()
+                );
+            }
+        }
+    }
+}
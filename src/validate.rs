@@ -0,0 +1,249 @@
+//! Consistency checks of a fasta file and its index.
+//!
+//! `IndexedFasta::validate` walks the sequence of every record in the index and compares the
+//! fasta file with what the index describes: the header of the record must end just before its
+//! offset and carry its name, every line except the last must have `line_bases` bases and be
+//! `line_width` bytes long including the line break, and the number of bases must match the
+//! length of the record. Problems are collected in a `ValidationReport` instead of failing on
+//! the first one. Only the first line with a problem is reported for every record.
+//!
+//! # Example
+//! ```
+//! use faimm::{Fai, IndexedFasta};
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! assert!(fa.validate().expect("Invalid index").is_valid());
+//!
+//! let fai = Fai::from_reader(&b"b\t5\t3\t4\t5\n"[..]).unwrap();
+//! let fa = IndexedFasta::from_source(b">a\nACGT\nACG\n".to_vec(), fai);
+//! let report = fa.validate().expect("Invalid index");
+//! // the header has name a and the sequence has 7 bases
+//! assert_eq!(report.issues.len(), 2);
+//! for issue in &report.issues {
+//!     println!("{}", issue);
+//! }
+//! ```
+
+use std::fmt;
+
+use memchr::memchr;
+
+use crate::{IndexedFasta, Result};
+
+/// A difference between the fasta file and its index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// No header line ends just before the offset of the record.
+    MissingHeader { tid: usize },
+    /// The header before the offset of the record has the name `found`.
+    NameMismatch { tid: usize, found: String },
+    /// Line `line` (zero-based) of the sequence has `found` bases instead of `expected`, or
+    /// more than `expected` for the last line.
+    LineBases {
+        tid: usize,
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// Line `line` (zero-based) of the sequence is `found` bytes long including the line break
+    /// instead of `expected`.
+    LineWidth {
+        tid: usize,
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The sequence has `found` bases instead of the `expected` length of the record.
+    Length {
+        tid: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::MissingHeader { tid } => {
+                write!(f, "Chromosome tid {}: no header before the offset", tid)
+            }
+            Issue::NameMismatch { tid, found } => {
+                write!(f, "Chromosome tid {}: the header has name {}", tid, found)
+            }
+            Issue::LineBases {
+                tid,
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Chromosome tid {}: line {} has {} bases instead of {}",
+                tid, line, found, expected
+            ),
+            Issue::LineWidth {
+                tid,
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Chromosome tid {}: line {} is {} bytes long instead of {}",
+                tid, line, found, expected
+            ),
+            Issue::Length {
+                tid,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Chromosome tid {}: the sequence has {} bases instead of {}",
+                tid, found, expected
+            ),
+        }
+    }
+}
+
+/// The result of `IndexedFasta::validate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The number of records that were checked.
+    pub records: usize,
+    /// The problems that were found, ordered by tid.
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Whether the fasta file matches its index.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl IndexedFasta {
+    /// Check that the fasta file matches its index, see the `validate` module.
+    ///
+    /// Returns the `ValidationReport` if successful, Error if a record of a lazily loaded index
+    /// cannot be parsed.
+    pub fn validate(&self) -> Result<ValidationReport> {
+        let data = self.data();
+        let fai = self.fai();
+        let mut report = ValidationReport::default();
+        for (tid, name) in fai.names().into_iter().enumerate() {
+            let chr = fai.record(tid)?;
+            report.records += 1;
+            let offset = chr.offset.min(data.len());
+
+            // the header is the line before the offset
+            let before = data[..offset].strip_suffix(b"\n").unwrap_or(&[]);
+            let before = before.strip_suffix(b"\r").unwrap_or(before);
+            let header = &before[before
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |p| p + 1)..];
+            match header.strip_prefix(b">") {
+                None => report.issues.push(Issue::MissingHeader { tid }),
+                Some(header) => {
+                    let found = String::from_utf8_lossy(header);
+                    let found = found.split_whitespace().next().unwrap_or("");
+                    if found != name {
+                        report.issues.push(Issue::NameMismatch {
+                            tid,
+                            found: found.to_owned(),
+                        });
+                    }
+                }
+            }
+
+            let mut bases = 0;
+            let mut line_issue = None;
+            let mut pos = offset;
+            let mut line = 0;
+            while pos < data.len() && data[pos] != b'>' {
+                let end = memchr(b'\n', &data[pos..]).map_or(data.len(), |p| pos + p + 1);
+                let width = end - pos;
+                let text = data[pos..end]
+                    .strip_suffix(b"\n")
+                    .unwrap_or(&data[pos..end]);
+                let n = text.strip_suffix(b"\r").unwrap_or(text).len();
+                let last = end == data.len() || data[end] == b'>';
+                if line_issue.is_none() {
+                    if n > chr.line_bases || (!last && n < chr.line_bases) {
+                        line_issue = Some(Issue::LineBases {
+                            tid,
+                            line,
+                            expected: chr.line_bases,
+                            found: n,
+                        });
+                    } else if n == chr.line_bases && !last && width != chr.line_width {
+                        line_issue = Some(Issue::LineWidth {
+                            tid,
+                            line,
+                            expected: chr.line_width,
+                            found: width,
+                        });
+                    }
+                }
+                bases += n;
+                line += 1;
+                pos = end;
+            }
+            report.issues.extend(line_issue);
+            if bases != chr.len {
+                report.issues.push(Issue::Length {
+                    tid,
+                    expected: chr.len,
+                    found: bases,
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fai;
+
+    #[test]
+    fn validate() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let report = fa.validate().unwrap();
+        assert_eq!((report.records, report.is_valid()), (3, true));
+
+        let data = b">a desc\nACGT\nAC\nACG\n>c\nAAAA\r\nAA\r\n>d\nAC\n".to_vec();
+        let fai = "a\t9\t8\t4\t5\nb\t6\t23\t4\t5\nd\t2\t36\t4\t5\nx\t2\t37\t2\t3\n";
+        let fa = IndexedFasta::from_source(data, Fai::from_reader(fai.as_bytes()).unwrap());
+        let report = fa.validate().unwrap();
+        assert_eq!(
+            report.issues,
+            [
+                Issue::LineBases {
+                    tid: 0,
+                    line: 1,
+                    expected: 4,
+                    found: 2
+                },
+                Issue::NameMismatch {
+                    tid: 1,
+                    found: "c".to_owned()
+                },
+                Issue::LineWidth {
+                    tid: 1,
+                    line: 0,
+                    expected: 5,
+                    found: 6
+                },
+                Issue::MissingHeader { tid: 3 },
+                Issue::Length {
+                    tid: 3,
+                    expected: 2,
+                    found: 1
+                },
+            ]
+        );
+        assert_eq!(
+            report.issues[0].to_string(),
+            "Chromosome tid 0: line 1 has 2 bases instead of 4"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! Prefault the pages of hot regions.
+//!
+//! The pages of an mmapped file are only read from disk when they are first accessed, so the
+//! first query of a region can be much slower than the next. A latency sensitive service can
+//! warm up the regions it expects to serve at startup. `IndexedFasta::warmup` touches every page
+//! of the requested regions, `IndexedFasta::warmup_in_background` does the same in a separate
+//! thread.
+//!
+//! # Example
+//! ```
+//! use std::sync::Arc;
+//! use faimm::IndexedFasta;
+//! let fa = Arc::new(IndexedFasta::from_file("test/genome.fa").expect("Error opening fa"));
+//! fa.warmup(&[(2, 0, 50)]).expect("Invalid region");
+//! let handle = fa.warmup_in_background(None);
+//! handle.join().unwrap().expect("Warmup failed");
+//! ```
+
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::{IndexedFasta, Result};
+
+/// Touch one byte every `PAGE_STEP` bytes. Smaller than or equal to the page size of all
+/// supported platforms.
+const PAGE_STEP: usize = 4096;
+
+impl IndexedFasta {
+    /// Read every page of the zero-based, half-open `(tid, start, stop)` regions, so later
+    /// queries of these regions don't have to wait for the disk.
+    ///
+    /// Returns `Ok(())` if successful, `Error` for an invalid region. Regions before the invalid
+    /// region are warmed up.
+    pub fn warmup(&self, regions: &[(usize, usize, usize)]) -> Result<()> {
+        for &(tid, start, stop) in regions {
+            let (start_byte, stop_byte) = self.checked_offset(tid, start, stop)?;
+            touch(&self.data()[start_byte..stop_byte]);
+        }
+        Ok(())
+    }
+
+    /// Read every page of the complete fasta file.
+    pub fn warmup_all(&self) {
+        touch(self.data());
+    }
+
+    /// Warm up `regions` in a new thread, or the complete file if `regions` is `None`. The
+    /// returned handle can be joined to wait for the result.
+    pub fn warmup_in_background(
+        self: &Arc<Self>,
+        regions: Option<Vec<(usize, usize, usize)>>,
+    ) -> JoinHandle<Result<()>> {
+        let fasta = Arc::clone(self);
+        thread::spawn(move || match regions {
+            Some(regions) => fasta.warmup(&regions),
+            None => {
+                fasta.warmup_all();
+                Ok(())
+            }
+        })
+    }
+}
+
+/// Read one byte of every page of `data`.
+fn touch(data: &[u8]) {
+    let mut sum = 0u8;
+    for b in data.iter().step_by(PAGE_STEP).chain(data.last()) {
+        sum = sum.wrapping_add(*b);
+    }
+    std::hint::black_box(sum);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmup() {
+        let fa = Arc::new(IndexedFasta::from_file("test/genome.fa").unwrap());
+        assert!(fa.warmup(&[(0, 0, 10), (2, 10, 20)]).is_ok());
+        assert!(fa.warmup(&[(3, 0, 1)]).is_err());
+        assert!(fa
+            .warmup_in_background(Some(vec![(1, 0, 101)]))
+            .join()
+            .unwrap()
+            .is_err());
+        assert!(fa.warmup_in_background(None).join().unwrap().is_ok());
+    }
+}
@@ -0,0 +1,349 @@
+//! Writing FASTA records.
+//!
+//! A `FastaWriter` writes records with a `>` header line and the bases wrapped in lines of a
+//! fixed width (60 by default). The bases can come from a `FastaView`, so regions or subsets of a
+//! reference can be extracted to a new FASTA file without copying them first, or from any
+//! `Read`. Line breaks and other bytes that are not bases are skipped, so the input doesn't have
+//! to be unwrapped.
+//!
+//! # Example
+//! ```
+//! use faimm::IndexedFasta;
+//! use faimm::writer::FastaWriter;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! let mut w = FastaWriter::new(Vec::new()).line_width(4);
+//! let view = fa.view(2, 20, 30).expect("Cannot get view");
+//! w.write_view("ACGT-25:21-30", &view).expect("Cannot write record");
+//! w.write_record("extra", "ac\ngt".as_bytes()).expect("Cannot write record");
+//! let out = String::from_utf8(w.into_inner().unwrap()).unwrap();
+//! assert_eq!(out, ">ACGT-25:21-30\nAAAA\nACCC\nCC\n>extra\nacgt\n");
+//! ```
+//!
+//! The writer keeps track of the index of the records it writes, so `FastaWriter::fai` returns
+//! the `.fai` of the new file without scanning it. `IndexedFasta::write_subset` uses this to
+//! extract selected chromosomes with their index, and `IndexedFasta::subset` can rename or
+//! rewrap them.
+//!
+//! ```no_run
+//! use faimm::IndexedFasta;
+//! let fa = IndexedFasta::from_file("test/genome.fa").expect("Error opening fa");
+//! fa.write_subset(&["ACGT-25"], "mini.fa").expect("Cannot write subset");
+//! fa.subset(&["A-10", "A-100"])
+//!     .rename("A-100", "chrA")
+//!     .line_width(60)
+//!     .write("renamed.fa")
+//!     .expect("Cannot write subset");
+//! ```
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use indexmap::IndexSet;
+
+use crate::transform::MappedView;
+use crate::{fai_path, Error, Fai, FaiRecord, FastaView, IndexedFasta, Records, Result};
+
+/// Writes FASTA records with wrapped sequence lines to `W`.
+pub struct FastaWriter<W: Write> {
+    w: W,
+    line_width: usize,
+    line: Vec<u8>,
+    /// The number of bytes written.
+    written: usize,
+    /// The names and index records of the written records.
+    records: Vec<(String, FaiRecord)>,
+}
+
+impl<W: Write> FastaWriter<W> {
+    /// Write records to `w` with 60 bases per line. Wrap `w` in a `BufWriter` when it is a file.
+    pub fn new(w: W) -> Self {
+        FastaWriter {
+            w,
+            line_width: 60,
+            line: Vec::new(),
+            written: 0,
+            records: Vec::new(),
+        }
+    }
+
+    /// Set the number of bases per line.
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.line_width = width;
+        self
+    }
+
+    /// Write a record with header `name` and the bases of `view`.
+    ///
+    /// Returns `Ok` if successful, `Error::InvalidArgument` for a line width of 0, Error
+    /// otherwise.
+    pub fn write_view(&mut self, name: &str, view: &FastaView) -> Result<()> {
+        self.write_bases(name, view.bases().copied().map(Ok))
+    }
+
+    /// Write a record with header `name` and the transformed bases of `view`, see the
+    /// `transform` module.
+    ///
+    /// Returns `Ok` if successful, `Error::InvalidArgument` for a line width of 0, Error
+    /// otherwise.
+    pub fn write_mapped(&mut self, name: &str, view: &MappedView) -> Result<()> {
+        self.write_bases(name, view.bases().map(Ok))
+    }
+
+    /// Write a record with header `name` and the bases read from `r`. Bytes that are not bases,
+    /// like line breaks, are skipped.
+    ///
+    /// Returns `Ok` if successful, `Error::InvalidArgument` for a line width of 0, Error
+    /// otherwise.
+    pub fn write_record<R: Read>(&mut self, name: &str, r: R) -> Result<()> {
+        let bases = io::BufReader::new(r)
+            .bytes()
+            .filter(|b| b.as_ref().map_or(true, |&b| b & 192 == 64));
+        self.write_bases(name, bases)
+    }
+
+    fn write_bases<I: Iterator<Item = io::Result<u8>>>(
+        &mut self,
+        name: &str,
+        bases: I,
+    ) -> Result<()> {
+        if self.line_width == 0 {
+            return Err(Error::InvalidArgument(
+                "Line width should be at least 1".to_owned(),
+            ));
+        }
+        writeln!(self.w, ">{}", name)?;
+        self.written += name.len() + 2;
+        let offset = self.written;
+        self.line.clear();
+        for b in bases {
+            self.line.push(b?);
+            if self.line.len() == self.line_width {
+                self.line.push(b'\n');
+                self.w.write_all(&self.line)?;
+                self.written += self.line.len();
+                self.line.clear();
+            }
+        }
+        if !self.line.is_empty() {
+            self.line.push(b'\n');
+            self.w.write_all(&self.line)?;
+            self.written += self.line.len();
+        }
+
+        let len = self.written - offset - (self.written - offset).div_ceil(self.line_width + 1);
+        let line_bases = len.min(self.line_width);
+        self.records.push((
+            name.split_whitespace().next().unwrap_or("").to_owned(),
+            FaiRecord {
+                len,
+                offset,
+                line_bases,
+                line_width: if len == 0 { 0 } else { line_bases + 1 },
+                qual_offset: None,
+            },
+        ));
+        Ok(())
+    }
+
+    /// The index of the records written so far, as `Fai::create` would create it for the
+    /// written data. The name of a record is its header up to the first whitespace.
+    ///
+    /// Returns the index if successful, `Error::InvalidArgument` if a name was written twice.
+    pub fn fai(&self) -> Result<Fai> {
+        let mut name_map = IndexSet::with_capacity(self.records.len());
+        for (name, _) in &self.records {
+            if !name_map.insert(name.clone()) {
+                return Err(Error::InvalidArgument(format!(
+                    "Duplicate sequence name {}",
+                    name
+                )));
+            }
+        }
+        Ok(Fai {
+            chromosomes: Records::Parsed(self.records.iter().map(|(_, r)| *r).collect()),
+            name_map,
+            aliases: None,
+        })
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+
+    /// Flush and return the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+/// Chromosomes of an `IndexedFasta` to write to a new fasta file, created with
+/// `IndexedFasta::subset`.
+pub struct Subset<'a> {
+    fasta: &'a IndexedFasta,
+    names: Vec<(String, String)>,
+    line_width: Option<usize>,
+}
+
+impl<'a> Subset<'a> {
+    /// Write chromosome `name` with the new name `to`. Names that are not in the subset are
+    /// ignored.
+    pub fn rename(mut self, name: &str, to: &str) -> Self {
+        for (from, new) in self.names.iter_mut() {
+            if from == name {
+                *new = to.to_owned();
+            }
+        }
+        self
+    }
+
+    /// Wrap the sequences in lines of `width` bases, instead of the line width of every
+    /// chromosome in the original file.
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.line_width = Some(width);
+        self
+    }
+
+    /// Write the chromosomes in the order they were given to a new fasta file at path `P`, and
+    /// its index to `P.fai`.
+    ///
+    /// Returns the index of the new file if successful, `Error::UnknownName` for a name that is
+    /// not in the fasta file, `Error::InvalidArgument` for a duplicate name or a line width of
+    /// 0, Error otherwise.
+    pub fn write<P: AsRef<Path>>(self, path: P) -> Result<Fai> {
+        let tids = self
+            .names
+            .iter()
+            .map(|(name, _)| self.fasta.tid_by_name(name))
+            .collect::<Result<Vec<_>>>()?;
+        // check the arguments before creating the file, so no fasta without an index is left
+        if self.line_width == Some(0) {
+            return Err(Error::InvalidArgument(
+                "Line width should be at least 1".to_owned(),
+            ));
+        }
+        let mut seen = HashSet::with_capacity(self.names.len());
+        for (_, name) in &self.names {
+            let name = name.split_whitespace().next().unwrap_or("");
+            if !seen.insert(name) {
+                return Err(Error::InvalidArgument(format!(
+                    "Duplicate sequence name {}",
+                    name
+                )));
+            }
+        }
+        let mut w = FastaWriter::new(BufWriter::new(File::create(&path)?));
+        for (&tid, (_, name)) in tids.iter().zip(&self.names) {
+            let line_bases = self.fasta.fai().record(tid)?.line_bases;
+            w.line_width = self.line_width.unwrap_or(line_bases.max(1));
+            w.write_view(name, &self.fasta.view_tid(tid)?)?;
+        }
+        w.flush()?;
+        let fai = w.fai()?;
+        fai.to_file(fai_path(path))?;
+        Ok(fai)
+    }
+}
+
+impl IndexedFasta {
+    /// Select chromosomes by name to write to a new fasta file, see `Subset`.
+    pub fn subset(&self, names: &[&str]) -> Subset<'_> {
+        Subset {
+            fasta: self,
+            names: names
+                .iter()
+                .map(|&n| (n.to_owned(), n.to_owned()))
+                .collect(),
+            line_width: None,
+        }
+    }
+
+    /// Write the chromosomes `names` with their line widths to a new fasta file at path `P` and
+    /// its index to `P.fai`, see `Subset::write`.
+    pub fn write_subset<P: AsRef<Path>>(&self, names: &[&str], path: P) -> Result<Fai> {
+        self.subset(names).write(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let mut w = FastaWriter::new(Vec::new()).line_width(50);
+        for tid in 0..3 {
+            let name = fa.fai().name(tid).unwrap().clone();
+            w.write_view(&name, &fa.view_tid(tid).unwrap()).unwrap();
+        }
+        w.write_record("empty", io::empty()).unwrap();
+        let view = fa.view(2, 20, 30).unwrap();
+        w.write_mapped(
+            "rna",
+            &view.map_bases(crate::transform::Transform::DnaToRna),
+        )
+        .unwrap();
+        w.write_record("short desc", "ACG".as_bytes()).unwrap();
+        let (mut fai, mut created) = (Vec::new(), Vec::new());
+        w.fai().unwrap().write_to(&mut fai).unwrap();
+        let mut expected = std::fs::read("test/genome.fa").unwrap();
+        expected.extend(b">empty\n>rna\nAAAAACCCCC\n>short desc\nACG\n");
+        Fai::create_from_bytes(&expected)
+            .unwrap()
+            .write_to(&mut created)
+            .unwrap();
+        assert_eq!(fai, created);
+        assert_eq!(w.into_inner().unwrap(), expected);
+
+        let mut w = FastaWriter::new(Vec::new()).line_width(0);
+        assert!(matches!(
+            w.write_record("a", "A".as_bytes()),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn subset() {
+        let fa = IndexedFasta::from_file("test/genome.fa").unwrap();
+        let dir = std::env::temp_dir().join(format!("faimm-subset-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("subset.fa");
+        fa.write_subset(&["ACGT-25", "A-10"], &out).unwrap();
+        let sub = IndexedFasta::from_file(&out).unwrap();
+        assert_eq!(sub.fai().names(), ["ACGT-25", "A-10"]);
+        assert_eq!(
+            sub.view_tid(0).unwrap().to_string(),
+            fa.view_tid(2).unwrap().to_string()
+        );
+        assert!(sub.validate().unwrap().is_valid());
+
+        let fai = fa
+            .subset(&["A-100"])
+            .rename("A-100", "chrA")
+            .line_width(30)
+            .write(dir.join("renamed.fa"))
+            .unwrap();
+        assert_eq!(fai.names(), ["chrA"]);
+        let sub = IndexedFasta::from_file(dir.join("renamed.fa")).unwrap();
+        assert_eq!(sub.view(0, 25, 35).unwrap().to_string(), "AAAAAAAAAA");
+        assert!(sub.validate().unwrap().is_valid());
+        assert!(matches!(
+            fa.write_subset(&["chr1"], &out),
+            Err(Error::UnknownName(_))
+        ));
+        let dup = dir.join("dup.fa");
+        let renamed = fa.subset(&["A-10", "A-100"]).rename("A-10", "A-100 short");
+        assert!(matches!(
+            renamed.write(&dup),
+            Err(Error::InvalidArgument(_))
+        ));
+        let zero = fa.subset(&["A-10"]).line_width(0);
+        assert!(matches!(zero.write(&dup), Err(Error::InvalidArgument(_))));
+        assert!(!dup.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::process::{Command, Output};
+
+fn faimm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_faimm"))
+        .args(args)
+        .output()
+        .expect("Cannot run faimm")
+}
+
+fn stdout(args: &[&str]) -> String {
+    let out = faimm(args);
+    assert!(out.status.success(), "{:?}", out);
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn extract() {
+    let out = stdout(&[
+        "extract",
+        "-w",
+        "4",
+        "test/genome.fa",
+        "ACGT-25:21-30",
+        "A-10",
+    ]);
+    assert_eq!(
+        out,
+        ">ACGT-25:21-30\nAAAA\nACCC\nCC\n>A-10\nAAAA\nAAAA\nAA\n"
+    );
+    assert!(!faimm(&["extract", "test/genome.fa", "chr1:1-10"])
+        .status
+        .success());
+    assert!(!faimm(&["extract", "test/genome.fa"]).status.success());
+}
+
+#[test]
+fn nuc_and_stats() {
+    let dir = std::env::temp_dir().join(format!("faimm-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let bed = dir.join("regions.bed");
+    fs::write(&bed, "ACGT-25\t20\t30\nA-10\t0\t5\n").unwrap();
+    let out = stdout(&["nuc", "test/genome.fa", bed.to_str().unwrap()]);
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("#chrom\tstart\tend\tat\tgc"));
+    assert_eq!(
+        lines[1],
+        "ACGT-25\t20\t30\t0.500000\t0.500000\t5\t5\t0\t0\t0\t0\t10"
+    );
+
+    let out = stdout(&["stats", "test/genome.fa"]);
+    assert_eq!(
+        out.lines().nth(3),
+        Some("ACGT-25\t0.500000\t0.500000\t25\t25\t25\t25\t0\t0\t100")
+    );
+
+    let fasta = dir.join("copy.fa");
+    fs::copy("test/genome.fa", &fasta).unwrap();
+    stdout(&["faidx", fasta.to_str().unwrap()]);
+    assert_eq!(
+        fs::read(dir.join("copy.fa.fai")).unwrap(),
+        fs::read("test/genome.fa.fai").unwrap()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}